@@ -25,6 +25,10 @@ const (
 	Uint8  = api.Uint8
 	Uint16 = api.Uint16
 	Uint32 = api.Uint32
+	Uint64 = api.Uint64
+	Bool   = api.Bool
+
+	UnlimitedGas = api.UnlimitedGas
 )
 
 // Function
@@ -153,7 +153,7 @@ func Add(skPath string, pksPath string) error {
 		return fmt.Errorf("error from tfhe NewCipherText: %s", err)
 	}
 
-	res, err := num1.Add(num2)
+	res, err := num1.Add(num2, tfhelib.UnlimitedGas)
 	if err != nil {
 		return fmt.Errorf("error while adding: %s", err)
 	}
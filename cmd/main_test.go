@@ -73,7 +73,7 @@ func TestRunInternalInManyThreads(t *testing.T) {
 			// Decrement the counter when the goroutine completes.
 			defer wg.Done()
 
-			res, err := num1.Add(num2)
+			res, err := num1.Add(num2, tfhelib.UnlimitedGas)
 			if err != nil {
 				t.Fail()
 			}
@@ -9,19 +9,78 @@
 #include <stdint.h>
 #include <stdlib.h>
 
-#if !defined(DEFINE_WASM32)
+/**
+ * Passing this as a gas budget disables the budget check.
+ */
+#define UNLIMITED_GAS UINT64_MAX
+
+/**
+ * The key set used by callers that do not care about multiple key sets.
+ */
+#define DEFAULT_KEY_SET 0
+
+/**
+ * Length of the seed taken by `generate_seeded_keys_insecure`.
+ */
+#define KEYGEN_SEED_LEN 32
+
+/**
+ * The kinds of operations `operation_cost` can price.
+ */
+enum CostedOperation {
+  CostedOperation_Math = 0,
+  CostedOperation_ScalarMath = 1,
+  CostedOperation_UnaryMath = 2,
+  CostedOperation_Cast = 3,
+  CostedOperation_Cmux = 4,
+  CostedOperation_Comparison = 5,
+  CostedOperation_Expand = 6,
+  CostedOperation_TrivialEncrypt = 7,
+};
+typedef int32_t CostedOperation;
+
+/**
+ * Stable numeric error codes, set as errno by every FFI call alongside the error message.
+ *
+ * The values are part of the FFI contract - never renumber them, only append new ones.
+ */
 enum ErrnoValue {
   ErrnoValue_Success = 0,
   ErrnoValue_Other = 1,
   ErrnoValue_OutOfGas = 2,
+  ErrnoValue_KeyNotLoaded = 3,
+  ErrnoValue_DeserializationFailed = 4,
+  ErrnoValue_SerializationFailed = 5,
+  ErrnoValue_TypeMismatch = 6,
+  /**
+   * Reserved - dividing by an encrypted (or plaintext) zero is defined and yields the maximum
+   * value of the type, so nothing returns this yet.
+   */
+  ErrnoValue_DivisionByZero = 7,
+  ErrnoValue_Panic = 8,
+  ErrnoValue_InvalidCiphertext = 9,
+  ErrnoValue_InvalidArgument = 10,
+  ErrnoValue_UnsupportedOperation = 11,
+  ErrnoValue_KeyMismatch = 12,
+  /**
+   * A different key of that kind is already loaded into the key set.
+   */
+  ErrnoValue_AlreadyLoaded = 13,
 };
 typedef int32_t ErrnoValue;
-#endif
 
 enum FheUintType {
   FheUintType_Uint8 = 0,
   FheUintType_Uint16 = 1,
   FheUintType_Uint32 = 2,
+  FheUintType_Uint64 = 3,
+  /**
+   * A single radix block that holds either 0 or 1 - not tfhe's `FheBool`, which tfhe 0.3 only
+   * offers through the boolean module under its own parameters, so it cannot be mixed with
+   * integer ciphertexts. Logical operations on a `Bool` bootstrap that one block only. Compact
+   * `Bool`s are encrypted as a `Uint8` and narrowed when expanded (see `boolean`).
+   */
+  FheUintType_Bool = 4,
 };
 typedef int32_t FheUintType;
 
@@ -47,11 +106,34 @@ enum Op {
 };
 typedef int32_t Op;
 
+/**
+ * The named tfhe parameter sets keys can be generated with.
+ *
+ * The numeric values are recorded in key files, so never renumber them, only append new ones.
+ */
+enum ParameterProfile {
+  /**
+   * The parameters every key set was generated with before profiles existed.
+   */
+  ParameterProfile_Message2Carry2KsPbs = 0,
+  ParameterProfile_Message2Carry2PbsKs = 1,
+  ParameterProfile_Message1Carry1KsPbs = 2,
+  ParameterProfile_Message1Carry1PbsKs = 3,
+  ParameterProfile_Message2Carry2CompactPk = 4,
+  ParameterProfile_Message1Carry1CompactPk = 5,
+};
+typedef int32_t ParameterProfile;
+
 enum UnaryOp {
   Not = 0,
 };
 typedef int32_t UnaryOp;
 
+/**
+ * Identifies one of the key sets loaded side by side, e.g. one per network.
+ */
+typedef uint32_t KeySetId;
+
 /**
  * An optional Vector type that requires explicit creation and destruction
  * and can be sent via FFI.
@@ -117,52 +199,347 @@ typedef struct ByteSliceView {
   uintptr_t len;
 } ByteSliceView;
 
-bool generate_full_keys(const char *path_to_cks, const char *path_to_sks, const char *path_to_pks);
+/**
+ * Generates a key set with the parameters of `profile` and writes each key to its path. The
+ * profile and the key set fingerprint are recorded in the key files, so loading them into a key
+ * set of another profile or generation fails.
+ *
+ * If `path_to_compressed_sks` is not null, the server key is also written there in tfhe's
+ * compressed form, which `load_server_key` accepts as well and decompresses on load. Both files
+ * hold the same key.
+ *
+ * If `cks_passphrase` is not nil, the client key is encrypted with it before it is written, and
+ * has to be loaded with `load_encrypted_client_key`.
+ */
+bool generate_full_keys(const char *path_to_cks,
+                        const char *path_to_sks,
+                        const char *path_to_compressed_sks,
+                        const char *path_to_pks,
+                        ParameterProfile profile,
+                        ByteSliceView cks_passphrase);
 
-struct UnmanagedVector math_operation(struct ByteSliceView lhs,
+/**
+ * Like `generate_full_keys`, but derives the keys from a 32 byte `seed`, so the same seed and
+ * profile always produce bit-identical key files.
+ *
+ * INSECURE - anyone who knows the seed can rebuild the client key. Only meant for devnets and
+ * CI, never for production keys. `cks_passphrase` works as in `generate_full_keys`.
+ */
+bool generate_full_keys_from_seed_insecure(const char *path_to_cks,
+                                           const char *path_to_sks,
+                                           const char *path_to_compressed_sks,
+                                           const char *path_to_pks,
+                                           ParameterProfile profile,
+                                           ByteSliceView seed,
+                                           ByteSliceView cks_passphrase);
+
+/**
+ * Performs `operation` on two encrypted values of type `uint_type`.
+ *
+ * Every call that touches keys takes the `key_set` to run under (see `load_server_key`), and
+ * rejects ciphertexts produced by a different key set.
+ *
+ * Like all the math, cast and cmux calls, this fails with `OutOfGas` before doing any FHE work if
+ * the cost of the operation (see `operation_cost`) exceeds `gas_budget`. Pass `UNLIMITED_GAS` to
+ * skip the check.
+ */
+struct UnmanagedVector math_operation(KeySetId key_set,
+                                      struct ByteSliceView lhs,
                                       struct ByteSliceView rhs,
                                       Op operation,
                                       FheUintType uint_type,
+                                      uint64_t gas_budget,
                                       struct UnmanagedVector *err_msg);
 
-struct UnmanagedVector unary_math_operation(struct ByteSliceView lhs,
+/**
+ * Performs `operation` on an encrypted value and a plaintext `u64`, which is truncated to the
+ * width of `uint_type`. This is much cheaper than trivially encrypting the plaintext and calling
+ * `math_operation`.
+ */
+struct UnmanagedVector scalar_math_operation(KeySetId key_set,
+                                             struct ByteSliceView lhs,
+                                             uint64_t rhs,
+                                             Op operation,
+                                             FheUintType uint_type,
+                                             uint64_t gas_budget,
+                                             struct UnmanagedVector *err_msg);
+
+/**
+ * Returns the gas cost of an operation without running it, so callers can check it against
+ * their budget up front.
+ *
+ * `operation` is the numeric value of the `Op` (or `UnaryOp`) for `Math`, `ScalarMath`,
+ * `UnaryMath` and `Comparison`, and is ignored otherwise. `to_type` is the target type of a
+ * `Cast` and the control type of a `Cmux`, and is ignored otherwise. An unknown `operation`
+ * costs `u64::MAX`.
+ */
+uint64_t operation_cost(CostedOperation kind,
+                        uint32_t operation,
+                        FheUintType uint_type,
+                        FheUintType to_type);
+
+/**
+ * Runs many independent `math_operation`s in parallel on a thread pool, all under `key_set`.
+ *
+ * `operations` is a bincode encoded `Vec<BatchOperation>`: a u64 (LE) count followed by, for
+ * every operation, the u64-length-prefixed `lhs` and `rhs` ciphertexts and then `operation` and
+ * `uint_type` as u32 (LE).
+ *
+ * The result is a bincode encoded `Vec<BatchResult>` in the same order: a u64 count followed by,
+ * for every operation, a u32 tag (0 = Ok, 1 = Err) and a u64-length-prefixed payload holding
+ * either the result ciphertext or the UTF-8 error message.
+ *
+ * The batch costs the sum of its operations (see `operation_cost`), and fails with `OutOfGas`
+ * before any of them runs if that exceeds `gas_budget`. Entries with an unknown tag are free, as
+ * they fail without running.
+ */
+struct UnmanagedVector batch_math_operation(KeySetId key_set,
+                                            struct ByteSliceView operations,
+                                            uint64_t gas_budget,
+                                            struct UnmanagedVector *err_msg);
+
+/**
+ * Evaluates a circuit of FHE operations, computing independent nodes in parallel.
+ *
+ * `circuit` is a bincode encoded `Circuit` (see `crate::circuit`). Each node produces a new
+ * value that later nodes and the outputs reference by id, so shared intermediates are computed
+ * only once.
+ *
+ * Circuits with more than `MAX_CIRCUIT_NODES` (1024) nodes, `MAX_CIRCUIT_INPUTS` (256) inputs or
+ * `MAX_CIRCUIT_OUTPUTS` (256) outputs, or with an unknown operation or type tag, fail with
+ * `InvalidArgument` before any node is evaluated. So do circuits costing more than
+ * `gas_budget` - the sum of their nodes - with `OutOfGas`.
+ *
+ * The result is a bincode encoded `Vec<CircuitOutput>`: a u64 (LE) count followed by, for every
+ * output, the u64-length-prefixed ciphertext and its `uint_type` as u32 (LE).
+ */
+struct UnmanagedVector evaluate_circuit(KeySetId key_set,
+                                        struct ByteSliceView circuit,
+                                        uint64_t gas_budget,
+                                        struct UnmanagedVector *err_msg);
+
+struct UnmanagedVector unary_math_operation(KeySetId key_set,
+                                            struct ByteSliceView lhs,
                                             UnaryOp operation,
                                             FheUintType uint_type,
+                                            uint64_t gas_budget,
                                             struct UnmanagedVector *err_msg);
 
-struct UnmanagedVector cast_operation(struct ByteSliceView val,
+struct UnmanagedVector cast_operation(KeySetId key_set,
+                                      struct ByteSliceView val,
                                       FheUintType from_type,
                                       FheUintType to_type,
+                                      uint64_t gas_budget,
                                       struct UnmanagedVector *err_msg);
 
-struct UnmanagedVector cmux(struct ByteSliceView control,
+struct UnmanagedVector cmux(KeySetId key_set,
+                            struct ByteSliceView control,
                             struct ByteSliceView if_true,
                             struct ByteSliceView if_false,
+                            FheUintType control_type,
                             FheUintType uint_type,
+                            uint64_t gas_budget,
                             struct UnmanagedVector *err_msg);
 
-void load_server_key(struct ByteSliceView key, struct UnmanagedVector *err_msg);
+/**
+ * Compares two encrypted x-bit unsigned integers and returns the result as a
+ * `FheUintType::Bool`, which can be used directly as the control of `cmux`.
+ */
+struct UnmanagedVector comparison_operation(KeySetId key_set,
+                                            struct ByteSliceView lhs,
+                                            struct ByteSliceView rhs,
+                                            Op operation,
+                                            FheUintType uint_type,
+                                            uint64_t gas_budget,
+                                            struct UnmanagedVector *err_msg);
 
-void load_client_key(struct ByteSliceView key, struct UnmanagedVector *err_msg);
+/**
+ * Switches between the tfhe backend and the plaintext mock backend (see `mock`).
+ *
+ * Ciphertexts created under one backend are rejected by the other. For tests only - the mock
+ * backend does not encrypt anything, so this is only exported by builds with the `mock-backend`
+ * feature.
+ */
+#if defined(MOCK_BACKEND)
+void set_mock_backend(bool enabled);
+#endif
 
-void load_public_key(struct ByteSliceView key, struct UnmanagedVector *err_msg);
+/**
+ * Loads a server key into `key_set`, creating the key set if needed, and returns the epoch of
+ * the key (0 on error).
+ *
+ * Key sets are independent of each other, so one process can serve several networks or keep an
+ * old key set around during a migration. Reloading the client or public key a key set already
+ * holds is a no-op, while loading a different one fails with `AlreadyLoaded` - unload the client
+ * key first to replace it.
+ *
+ * Loading a different server key into a key set rotates it: the epoch is bumped and every call
+ * that starts afterwards runs under the new key, while calls already in flight finish under the
+ * old one. Reloading the current server key leaves the epoch unchanged.
+ */
+uint64_t load_server_key(KeySetId key_set,
+                         struct ByteSliceView key,
+                         struct UnmanagedVector *err_msg);
+
+/**
+ * Returns the current server key epoch of `key_set`, or 0 if it has no server key.
+ */
+uint64_t server_key_epoch(KeySetId key_set);
+
+void load_client_key(KeySetId key_set, struct ByteSliceView key, struct UnmanagedVector *err_msg);
 
-struct UnmanagedVector get_public_key(struct UnmanagedVector *err_msg);
+/**
+ * Like `load_client_key`, for a client key file encrypted with `passphrase`, e.g. by
+ * `generate_full_keys`. Fails with `DeserializationFailed` if the passphrase is wrong.
+ */
+void load_encrypted_client_key(KeySetId key_set,
+                               struct ByteSliceView key,
+                               struct ByteSliceView passphrase,
+                               struct UnmanagedVector *err_msg);
 
-struct UnmanagedVector expand_compressed(struct ByteSliceView ciphertext,
+/**
+ * Drops the client key of `key_set`, e.g. once a node is done decrypting. Decryptions that
+ * are already running finish first. Returns false if no client key was loaded.
+ *
+ * The key set keeps the key in a buffer of its own, which is wiped here.
+ */
+bool unload_client_key(KeySetId key_set);
+
+void load_public_key(KeySetId key_set, struct ByteSliceView key, struct UnmanagedVector *err_msg);
+
+/**
+ * Checks that the keys loaded into `key_set` belong together, by running a probe through the
+ * public, server and (if loaded) client key. Meant to be called once all keys are loaded.
+ *
+ * Returns false and sets `KeyMismatch` if the keys are from different generations, or
+ * `KeyNotLoaded` if the server or public key is missing.
+ */
+bool validate_keys(KeySetId key_set, struct UnmanagedVector *err_msg);
+
+/**
+ * Returns metadata about the keys loaded into `key_set`: the bincode encoded `Vec<KeyInfo>`,
+ * one entry per loaded key in server, client, public order. Each entry holds the key kind, its
+ * 8 byte fingerprint, the parameter profile it was generated with (if recorded), the supported
+ * integer widths and the serialized size, so nodes can compare keys without hashing them.
+ */
+struct UnmanagedVector key_info(KeySetId key_set, struct UnmanagedVector *err_msg);
+
+struct UnmanagedVector get_public_key(KeySetId key_set, struct UnmanagedVector *err_msg);
+
+struct UnmanagedVector expand_compressed(KeySetId key_set,
+                                         struct ByteSliceView ciphertext,
                                          FheUintType int_type,
                                          struct UnmanagedVector *err_msg);
 
-struct UnmanagedVector trivial_encrypt(uint64_t msg,
+/**
+ * Expands a compact list made by `encrypt_list` into one expanded ciphertext per value.
+ *
+ * The result is a bincode encoded `Vec<Vec<u8>>`: a u64 (LE) count followed by the
+ * u64-length-prefixed ciphertexts, in the order the values were encrypted.
+ *
+ * Lists of more than `MAX_LIST_LEN` (256) values fail with `InvalidCiphertext`. Expanding costs
+ * the `Expand` cost of `int_type` per value, and fails with `OutOfGas` if the list costs more
+ * than `gas_budget`. Both are checked before any value is expanded.
+ */
+struct UnmanagedVector expand_list(KeySetId key_set,
+                                   struct ByteSliceView list,
+                                   FheUintType int_type,
+                                   uint64_t gas_budget,
+                                   struct UnmanagedVector *err_msg);
+
+struct UnmanagedVector trivial_encrypt(KeySetId key_set,
+                                       uint64_t msg,
                                        FheUintType int_type,
                                        struct UnmanagedVector *err_msg);
 
-struct UnmanagedVector encrypt(uint64_t msg, FheUintType int_type, struct UnmanagedVector *err_msg);
+struct UnmanagedVector encrypt(KeySetId key_set,
+                               uint64_t msg,
+                               FheUintType int_type,
+                               struct UnmanagedVector *err_msg);
+
+/**
+ * Encrypts several values of one type into a single compact list, see `encrypt_list_safe`.
+ *
+ * `values` holds the values as consecutive little-endian u64s.
+ */
+struct UnmanagedVector encrypt_list(KeySetId key_set,
+                                    struct ByteSliceView values,
+                                    FheUintType int_type,
+                                    struct UnmanagedVector *err_msg);
 
-uint64_t decrypt(struct ByteSliceView ciphertext,
+uint64_t decrypt(KeySetId key_set,
+                 struct ByteSliceView ciphertext,
                  FheUintType int_type,
                  struct UnmanagedVector *err_msg);
 
+/**
+ * Decrypts `ciphertext` and seals the value to the 32 byte X25519 `user_public_key` in a NaCl
+ * box, without the plaintext ever leaving the library. Returns the sealed value in the JSON
+ * layout of MetaMask's `eth_decrypt` (see `sealing`).
+ */
+struct UnmanagedVector seal_output(KeySetId key_set,
+                                   struct ByteSliceView ciphertext,
+                                   FheUintType int_type,
+                                   struct ByteSliceView user_public_key,
+                                   struct UnmanagedVector *err_msg);
+
+/**
+ * Deserializes an expanded ciphertext and keeps it alive on the Rust side.
+ *
+ * Returns a handle that can be passed to the other `handle_` functions, or `INVALID_HANDLE` (0)
+ * on error. Every handle must eventually be released with `free_handle`.
+ */
+uint64_t handle_from_bytes(KeySetId key_set,
+                           struct ByteSliceView ciphertext,
+                           FheUintType uint_type,
+                           struct UnmanagedVector *err_msg);
+
+/**
+ * Performs `operation` on two live ciphertexts of the same type and returns a handle to the
+ * result. The operands stay alive.
+ *
+ * Like `math_operation`, this fails with `OutOfGas` before doing any FHE work if the operation
+ * costs more than `gas_budget`. The same goes for the other handle operations.
+ */
+uint64_t handle_math_operation(KeySetId key_set,
+                               uint64_t lhs,
+                               uint64_t rhs,
+                               Op operation,
+                               uint64_t gas_budget,
+                               struct UnmanagedVector *err_msg);
+
+/**
+ * The handle counterpart of `scalar_math_operation`.
+ */
+uint64_t handle_scalar_math_operation(KeySetId key_set,
+                                      uint64_t lhs,
+                                      uint64_t rhs,
+                                      Op operation,
+                                      uint64_t gas_budget,
+                                      struct UnmanagedVector *err_msg);
+
+/**
+ * The handle counterpart of `unary_math_operation`.
+ */
+uint64_t handle_unary_math_operation(KeySetId key_set,
+                                     uint64_t lhs,
+                                     UnaryOp operation,
+                                     uint64_t gas_budget,
+                                     struct UnmanagedVector *err_msg);
+
+/**
+ * Serializes a live ciphertext into the same format `math_operation` returns.
+ * The handle stays alive.
+ */
+struct UnmanagedVector handle_to_bytes(KeySetId key_set,
+                                       uint64_t handle,
+                                       struct UnmanagedVector *err_msg);
+
+/**
+ * Releases a live ciphertext. Returns false if the handle was not live.
+ */
+bool free_handle(uint64_t handle);
+
 struct UnmanagedVector new_unmanaged_vector(bool nil, const uint8_t *ptr, uintptr_t length);
 
 void destroy_unmanaged_vector(struct UnmanagedVector v);
@@ -6,8 +6,8 @@ package amd64
 // #include "bindings.h"
 import "C"
 import (
+	"encoding/binary"
 	"fmt"
-	"go/types"
 	"math/big"
 	"runtime"
 	"syscall"
@@ -20,6 +20,8 @@ const (
 	Uint8  UintType = C.FheUintType_Uint8
 	Uint16 UintType = C.FheUintType_Uint16
 	Uint32 UintType = C.FheUintType_Uint32
+	Uint64 UintType = C.FheUintType_Uint64
+	Bool   UintType = C.FheUintType_Bool
 )
 
 // Value types
@@ -37,12 +39,89 @@ type (
 
 type OperationType C.Op
 
+// ParameterProfile selects the tfhe parameters keys are generated with. The profile is recorded
+// in the key files, and keys of different profiles cannot be loaded into the same key set.
+type ParameterProfile int32
+
+const (
+	Message2Carry2KsPbs     ParameterProfile = C.ParameterProfile_Message2Carry2KsPbs
+	Message2Carry2PbsKs     ParameterProfile = C.ParameterProfile_Message2Carry2PbsKs
+	Message1Carry1KsPbs     ParameterProfile = C.ParameterProfile_Message1Carry1KsPbs
+	Message1Carry1PbsKs     ParameterProfile = C.ParameterProfile_Message1Carry1PbsKs
+	Message2Carry2CompactPk ParameterProfile = C.ParameterProfile_Message2Carry2CompactPk
+	Message1Carry1CompactPk ParameterProfile = C.ParameterProfile_Message1Carry1CompactPk
+
+	// DefaultParameterProfile is the profile keys were generated with before profiles existed
+	DefaultParameterProfile = Message2Carry2KsPbs
+)
+
+// ErrorCode is the stable numeric code of a library error, see ErrnoValue in bindings.h
+type ErrorCode int32
+
+const (
+	ErrOther                 ErrorCode = C.ErrnoValue_Other
+	ErrOutOfGas              ErrorCode = C.ErrnoValue_OutOfGas
+	ErrKeyNotLoaded          ErrorCode = C.ErrnoValue_KeyNotLoaded
+	ErrDeserializationFailed ErrorCode = C.ErrnoValue_DeserializationFailed
+	ErrSerializationFailed   ErrorCode = C.ErrnoValue_SerializationFailed
+	ErrTypeMismatch          ErrorCode = C.ErrnoValue_TypeMismatch
+	ErrDivisionByZero        ErrorCode = C.ErrnoValue_DivisionByZero
+	ErrPanic                 ErrorCode = C.ErrnoValue_Panic
+	ErrInvalidCiphertext     ErrorCode = C.ErrnoValue_InvalidCiphertext
+	ErrInvalidArgument       ErrorCode = C.ErrnoValue_InvalidArgument
+	ErrUnsupportedOperation  ErrorCode = C.ErrnoValue_UnsupportedOperation
+	ErrKeyMismatch           ErrorCode = C.ErrnoValue_KeyMismatch
+	ErrAlreadyLoaded         ErrorCode = C.ErrnoValue_AlreadyLoaded
+)
+
+// TfheError is returned for every error raised by the library, so callers can match on Code
+// instead of the message
+type TfheError struct {
+	Code    ErrorCode
+	Message string
+}
+
+func (e *TfheError) Error() string {
+	return e.Message
+}
+
 // Pointers
 type (
 	cu8_ptr = *C.uint8_t
 )
 
-func MathOperation(lhs []byte, rhs []byte, uintType uint8, op OperationType) ([]byte, error) {
+// KeySetId selects one of the key sets loaded side by side, e.g. one per network. Every call that
+// touches keys runs under the given key set and rejects ciphertexts produced by another one.
+type KeySetId uint32
+
+// DefaultKeySet is the key set used by callers that do not care about multiple key sets
+const DefaultKeySet KeySetId = C.DEFAULT_KEY_SET
+
+// UnlimitedGas disables the gas budget check of the math, cast and cmux calls
+const UnlimitedGas uint64 = ^uint64(0)
+
+// CostedOperation selects which kind of operation OperationCost prices
+type CostedOperation int32
+
+const (
+	CostMath           CostedOperation = C.CostedOperation_Math
+	CostScalarMath     CostedOperation = C.CostedOperation_ScalarMath
+	CostUnaryMath      CostedOperation = C.CostedOperation_UnaryMath
+	CostCast           CostedOperation = C.CostedOperation_Cast
+	CostCmux           CostedOperation = C.CostedOperation_Cmux
+	CostComparison     CostedOperation = C.CostedOperation_Comparison
+	CostExpand         CostedOperation = C.CostedOperation_Expand
+	CostTrivialEncrypt CostedOperation = C.CostedOperation_TrivialEncrypt
+)
+
+// OperationCost returns the gas an operation would cost without running it. op is only used by
+// math, scalar math, unary math and comparison; toType is the target type of a cast and the
+// control type of a cmux.
+func OperationCost(kind CostedOperation, op OperationType, uintType uint8, toType uint8) uint64 {
+	return uint64(C.operation_cost(C.CostedOperation(kind), C.uint32_t(op), C.FheUintType(uintType), C.FheUintType(toType)))
+}
+
+func MathOperation(keySet KeySetId, lhs []byte, rhs []byte, uintType uint8, op OperationType, gasBudget uint64) ([]byte, error) {
 	errmsg := uninitializedUnmanagedVector()
 
 	num1 := makeView(lhs)
@@ -51,7 +130,7 @@ func MathOperation(lhs []byte, rhs []byte, uintType uint8, op OperationType) ([]
 	num2 := makeView(rhs)
 	defer runtime.KeepAlive(num2)
 
-	res, err := C.math_operation(num1, num2, ci32(op), C.FheUintType(uintType), &errmsg)
+	res, err := C.math_operation(C.KeySetId(keySet), num1, num2, ci32(op), C.FheUintType(uintType), cu64(gasBudget), &errmsg)
 	if err != nil {
 		return nil, errorWithMessage(err, errmsg)
 	}
@@ -59,13 +138,233 @@ func MathOperation(lhs []byte, rhs []byte, uintType uint8, op OperationType) ([]
 	return copyAndDestroyUnmanagedVector(res), nil
 }
 
-func CastOperation(val []byte, fromType uint8, toType uint8) ([]byte, error) {
+func ScalarMathOperation(keySet KeySetId, lhs []byte, rhs uint64, uintType uint8, op OperationType, gasBudget uint64) ([]byte, error) {
+	errmsg := uninitializedUnmanagedVector()
+
+	num1 := makeView(lhs)
+	defer runtime.KeepAlive(num1)
+
+	res, err := C.scalar_math_operation(C.KeySetId(keySet), num1, cu64(rhs), ci32(op), C.FheUintType(uintType), cu64(gasBudget), &errmsg)
+	if err != nil {
+		return nil, errorWithMessage(err, errmsg)
+	}
+
+	return copyAndDestroyUnmanagedVector(res), nil
+}
+
+type BatchOperation struct {
+	Lhs      []byte
+	Rhs      []byte
+	Op       OperationType
+	UintType uint8
+}
+
+// BatchResult holds either the Result of a batched operation, or the Err it failed with
+type BatchResult struct {
+	Result []byte
+	Err    error
+}
+
+// BatchMathOperation runs all operations in parallel and returns their results in the same order.
+// The operations are encoded the way bincode encodes a Rust Vec<BatchOperation>. The batch costs
+// the sum of its operations, and fails before any of them runs if that exceeds gasBudget.
+func BatchMathOperation(keySet KeySetId, operations []BatchOperation, gasBudget uint64) ([]BatchResult, error) {
+	encoded := binary.LittleEndian.AppendUint64(nil, uint64(len(operations)))
+	for _, op := range operations {
+		encoded = binary.LittleEndian.AppendUint64(encoded, uint64(len(op.Lhs)))
+		encoded = append(encoded, op.Lhs...)
+		encoded = binary.LittleEndian.AppendUint64(encoded, uint64(len(op.Rhs)))
+		encoded = append(encoded, op.Rhs...)
+		encoded = binary.LittleEndian.AppendUint32(encoded, uint32(op.Op))
+		encoded = binary.LittleEndian.AppendUint32(encoded, uint32(op.UintType))
+	}
+
+	errmsg := uninitializedUnmanagedVector()
+
+	ops := makeView(encoded)
+	defer runtime.KeepAlive(ops)
+
+	res, err := C.batch_math_operation(C.KeySetId(keySet), ops, cu64(gasBudget), &errmsg)
+	if err != nil {
+		return nil, errorWithMessage(err, errmsg)
+	}
+
+	return decodeBatchResults(copyAndDestroyUnmanagedVector(res))
+}
+
+func decodeBatchResults(data []byte) ([]BatchResult, error) {
+	malformed := fmt.Errorf("malformed batch result")
+
+	if len(data) < 8 {
+		return nil, malformed
+	}
+	count := binary.LittleEndian.Uint64(data)
+	data = data[8:]
+
+	results := make([]BatchResult, 0, count)
+	for i := uint64(0); i < count; i++ {
+		if len(data) < 12 {
+			return nil, malformed
+		}
+		tag := binary.LittleEndian.Uint32(data)
+		length := binary.LittleEndian.Uint64(data[4:])
+		data = data[12:]
+		if uint64(len(data)) < length {
+			return nil, malformed
+		}
+		payload := data[:length]
+		data = data[length:]
+
+		if tag == 0 {
+			results = append(results, BatchResult{Result: payload})
+		} else {
+			results = append(results, BatchResult{Err: fmt.Errorf("%s", string(payload))})
+		}
+	}
+
+	return results, nil
+}
+
+// CircuitNodeKind selects which fields of a CircuitNode are used
+type CircuitNodeKind uint32
+
+const (
+	CircuitConstant CircuitNodeKind = iota // Value, UintType
+	CircuitOp                              // Op, Lhs, Rhs
+	CircuitScalarOp                        // Op, Lhs, Value
+	CircuitUnaryOp                         // UnaryOp, Lhs
+	CircuitCast                            // Lhs, UintType
+	CircuitCmux                            // Lhs (control), Rhs (if true), Third (if false)
+)
+
+type CircuitInput struct {
+	Ciphertext []byte
+	UintType   uint8
+}
+
+// CircuitNode is a single gate of a Circuit. Operands are value ids: inputs come first, and node i
+// produces the value with id len(Inputs) + i. Nodes may only reference earlier values.
+type CircuitNode struct {
+	Kind     CircuitNodeKind
+	Op       OperationType
+	UnaryOp  OperationType
+	Lhs      uint32
+	Rhs      uint32
+	Third    uint32
+	Value    uint64
+	UintType uint8
+}
+
+type Circuit struct {
+	Inputs  []CircuitInput
+	Nodes   []CircuitNode
+	Outputs []uint32
+}
+
+type CircuitOutput struct {
+	Ciphertext []byte
+	UintType   uint8
+}
+
+// EvaluateCircuit runs the circuit, computing independent nodes in parallel.
+// The circuit is encoded the way bincode encodes the Rust Circuit struct. Circuits are limited
+// to 1024 nodes, and unknown operation or type tags are rejected before anything runs - as are
+// circuits whose nodes cost more than gasBudget in total.
+func EvaluateCircuit(keySet KeySetId, circuit Circuit, gasBudget uint64) ([]CircuitOutput, error) {
+	u32 := binary.LittleEndian.AppendUint32
+	u64 := binary.LittleEndian.AppendUint64
+
+	encoded := u64(nil, uint64(len(circuit.Inputs)))
+	for _, input := range circuit.Inputs {
+		encoded = u64(encoded, uint64(len(input.Ciphertext)))
+		encoded = append(encoded, input.Ciphertext...)
+		encoded = u32(encoded, uint32(input.UintType))
+	}
+
+	encoded = u64(encoded, uint64(len(circuit.Nodes)))
+	for _, node := range circuit.Nodes {
+		encoded = u32(encoded, uint32(node.Kind))
+		switch node.Kind {
+		case CircuitConstant:
+			encoded = u64(encoded, node.Value)
+			encoded = u32(encoded, uint32(node.UintType))
+		case CircuitOp:
+			encoded = u32(encoded, uint32(node.Op))
+			encoded = u32(encoded, node.Lhs)
+			encoded = u32(encoded, node.Rhs)
+		case CircuitScalarOp:
+			encoded = u32(encoded, uint32(node.Op))
+			encoded = u32(encoded, node.Lhs)
+			encoded = u64(encoded, node.Value)
+		case CircuitUnaryOp:
+			encoded = u32(encoded, uint32(node.UnaryOp))
+			encoded = u32(encoded, node.Lhs)
+		case CircuitCast:
+			encoded = u32(encoded, node.Lhs)
+			encoded = u32(encoded, uint32(node.UintType))
+		case CircuitCmux:
+			encoded = u32(encoded, node.Lhs)
+			encoded = u32(encoded, node.Rhs)
+			encoded = u32(encoded, node.Third)
+		default:
+			return nil, fmt.Errorf("unknown circuit node kind %d", node.Kind)
+		}
+	}
+
+	encoded = u64(encoded, uint64(len(circuit.Outputs)))
+	for _, output := range circuit.Outputs {
+		encoded = u32(encoded, output)
+	}
+
+	errmsg := uninitializedUnmanagedVector()
+
+	circuitView := makeView(encoded)
+	defer runtime.KeepAlive(circuitView)
+
+	res, err := C.evaluate_circuit(C.KeySetId(keySet), circuitView, cu64(gasBudget), &errmsg)
+	if err != nil {
+		return nil, errorWithMessage(err, errmsg)
+	}
+
+	return decodeCircuitOutputs(copyAndDestroyUnmanagedVector(res))
+}
+
+func decodeCircuitOutputs(data []byte) ([]CircuitOutput, error) {
+	malformed := fmt.Errorf("malformed circuit outputs")
+
+	if len(data) < 8 {
+		return nil, malformed
+	}
+	count := binary.LittleEndian.Uint64(data)
+	data = data[8:]
+
+	outputs := make([]CircuitOutput, 0, count)
+	for i := uint64(0); i < count; i++ {
+		if len(data) < 8 {
+			return nil, malformed
+		}
+		length := binary.LittleEndian.Uint64(data)
+		data = data[8:]
+		if uint64(len(data)) < length+4 {
+			return nil, malformed
+		}
+		ciphertext := data[:length]
+		uintType := binary.LittleEndian.Uint32(data[length:])
+		data = data[length+4:]
+
+		outputs = append(outputs, CircuitOutput{Ciphertext: ciphertext, UintType: uint8(uintType)})
+	}
+
+	return outputs, nil
+}
+
+func CastOperation(keySet KeySetId, val []byte, fromType uint8, toType uint8, gasBudget uint64) ([]byte, error) {
 	errmsg := uninitializedUnmanagedVector()
 
 	valView := makeView(val)
 	defer runtime.KeepAlive(valView)
 
-	res, err := C.cast_operation(valView, C.FheUintType(fromType), C.FheUintType(toType), &errmsg)
+	res, err := C.cast_operation(C.KeySetId(keySet), valView, C.FheUintType(fromType), C.FheUintType(toType), cu64(gasBudget), &errmsg)
 	if err != nil {
 		return nil, errorWithMessage(err, errmsg)
 	}
@@ -73,7 +372,7 @@ func CastOperation(val []byte, fromType uint8, toType uint8) ([]byte, error) {
 	return copyAndDestroyUnmanagedVector(res), nil
 }
 
-func Cmux(control []byte, ifTrue []byte, ifFalse []byte, uintType uint8) ([]byte, error) {
+func Cmux(keySet KeySetId, control []byte, ifTrue []byte, ifFalse []byte, controlType uint8, uintType uint8, gasBudget uint64) ([]byte, error) {
 	errmsg := uninitializedUnmanagedVector()
 
 	con := makeView(control)
@@ -85,7 +384,24 @@ func Cmux(control []byte, ifTrue []byte, ifFalse []byte, uintType uint8) ([]byte
 	f := makeView(ifFalse)
 	defer runtime.KeepAlive(f)
 
-	res, err := C.cmux(con, t, f, C.FheUintType(uintType), &errmsg)
+	res, err := C.cmux(C.KeySetId(keySet), con, t, f, C.FheUintType(controlType), C.FheUintType(uintType), cu64(gasBudget), &errmsg)
+	if err != nil {
+		return nil, errorWithMessage(err, errmsg)
+	}
+
+	return copyAndDestroyUnmanagedVector(res), nil
+}
+
+func ComparisonOperation(keySet KeySetId, lhs []byte, rhs []byte, uintType uint8, op OperationType, gasBudget uint64) ([]byte, error) {
+	errmsg := uninitializedUnmanagedVector()
+
+	num1 := makeView(lhs)
+	defer runtime.KeepAlive(num1)
+
+	num2 := makeView(rhs)
+	defer runtime.KeepAlive(num2)
+
+	res, err := C.comparison_operation(C.KeySetId(keySet), num1, num2, ci32(op), C.FheUintType(uintType), cu64(gasBudget), &errmsg)
 	if err != nil {
 		return nil, errorWithMessage(err, errmsg)
 	}
@@ -93,13 +409,13 @@ func Cmux(control []byte, ifTrue []byte, ifFalse []byte, uintType uint8) ([]byte
 	return copyAndDestroyUnmanagedVector(res), nil
 }
 
-func UnaryMathOperation(lhs []byte, uintType uint8, op OperationType) ([]byte, error) {
+func UnaryMathOperation(keySet KeySetId, lhs []byte, uintType uint8, op OperationType, gasBudget uint64) ([]byte, error) {
 	errmsg := uninitializedUnmanagedVector()
 
 	num1 := makeView(lhs)
 	defer runtime.KeepAlive(num1)
 
-	res, err := C.unary_math_operation(num1, ci32(op), C.FheUintType(uintType), &errmsg)
+	res, err := C.unary_math_operation(C.KeySetId(keySet), num1, ci32(op), C.FheUintType(uintType), cu64(gasBudget), &errmsg)
 	if err != nil {
 		return nil, errorWithMessage(err, errmsg)
 	}
@@ -107,53 +423,200 @@ func UnaryMathOperation(lhs []byte, uintType uint8, op OperationType) ([]byte, e
 	return copyAndDestroyUnmanagedVector(res), nil
 }
 
-func DeserializeServerKey(serverKeyBytes []byte) (bool, error) {
+func DeserializeServerKey(keySet KeySetId, serverKeyBytes []byte) (bool, error) {
 
 	sks := makeView(serverKeyBytes)
 	defer runtime.KeepAlive(sks)
 
 	errmsg := uninitializedUnmanagedVector()
 
-	_, err := C.load_server_key(sks, &errmsg)
+	_, err := C.load_server_key(C.KeySetId(keySet), sks, &errmsg)
 	if err != nil {
 		return false, errorWithMessage(err, errmsg)
 	}
 	return true, nil
 }
 
-func DeserializeClientKey(clientKeyBytes []byte) (bool, error) {
+// RotateServerKey loads a new server key into keySet and returns its epoch. Calls already running
+// finish under the previous key; every later call uses the new one.
+func RotateServerKey(keySet KeySetId, serverKeyBytes []byte) (uint64, error) {
+	sks := makeView(serverKeyBytes)
+	defer runtime.KeepAlive(sks)
+
+	errmsg := uninitializedUnmanagedVector()
+
+	epoch, err := C.load_server_key(C.KeySetId(keySet), sks, &errmsg)
+	if err != nil {
+		return 0, errorWithMessage(err, errmsg)
+	}
+	return uint64(epoch), nil
+}
+
+// ServerKeyEpoch returns the current server key epoch of keySet, or 0 if it has no server key
+func ServerKeyEpoch(keySet KeySetId) uint64 {
+	return uint64(C.server_key_epoch(C.KeySetId(keySet)))
+}
+
+func DeserializeClientKey(keySet KeySetId, clientKeyBytes []byte) (bool, error) {
+
+	clientKeyView := makeView(clientKeyBytes)
+	defer runtime.KeepAlive(clientKeyView)
+
+	errmsg := uninitializedUnmanagedVector()
+
+	_, err := C.load_client_key(C.KeySetId(keySet), clientKeyView, &errmsg)
+	if err != nil {
+		return false, errorWithMessage(err, errmsg)
+	}
+	return true, nil
+}
+
+// UnloadClientKey drops the client key of keySet. It returns false if no client key was
+// loaded. The key set keeps the key in a buffer of its own, which is wiped here.
+func UnloadClientKey(keySet KeySetId) bool {
+	return bool(C.unload_client_key(C.KeySetId(keySet)))
+}
+
+// DeserializeEncryptedClientKey loads a client key file that was encrypted with passphrase, e.g.
+// by GenerateFheKeys.
+func DeserializeEncryptedClientKey(keySet KeySetId, clientKeyBytes []byte, passphrase []byte) (bool, error) {
 
 	clientKeyView := makeView(clientKeyBytes)
 	defer runtime.KeepAlive(clientKeyView)
 
+	passphraseView := makeView(passphrase)
+	defer runtime.KeepAlive(passphraseView)
+
 	errmsg := uninitializedUnmanagedVector()
 
-	_, err := C.load_client_key(clientKeyView, &errmsg)
+	_, err := C.load_encrypted_client_key(C.KeySetId(keySet), clientKeyView, passphraseView, &errmsg)
 	if err != nil {
 		return false, errorWithMessage(err, errmsg)
 	}
 	return true, nil
 }
 
-func DeserializePublicKey(publicKeyBytes []byte) (bool, error) {
+func DeserializePublicKey(keySet KeySetId, publicKeyBytes []byte) (bool, error) {
 
 	publicKeyView := makeView(publicKeyBytes)
 	defer runtime.KeepAlive(publicKeyView)
 
 	errmsg := uninitializedUnmanagedVector()
 
-	_, err := C.load_public_key(publicKeyView, &errmsg)
+	_, err := C.load_public_key(C.KeySetId(keySet), publicKeyView, &errmsg)
 	if err != nil {
 		return false, errorWithMessage(err, errmsg)
 	}
 	return true, nil
 }
 
-func GetPublicKey() ([]byte, error) {
+// ValidateKeys checks that the keys loaded into keySet belong together, by running a probe through
+// the public, server and (if loaded) client key. Keys from different generations fail with
+// ErrKeyMismatch.
+func ValidateKeys(keySet KeySetId) error {
+	errmsg := uninitializedUnmanagedVector()
+
+	_, err := C.validate_keys(C.KeySetId(keySet), &errmsg)
+	if err != nil {
+		return errorWithMessage(err, errmsg)
+	}
+	return nil
+}
+
+// KeyKind is the kind of key a KeyInfo describes
+type KeyKind uint32
+
+const (
+	ServerKeyKind KeyKind = iota
+	ClientKeyKind
+	PublicKeyKind
+)
+
+// KeyInfo describes a loaded key without the key itself
+type KeyInfo struct {
+	Kind        KeyKind
+	Fingerprint [8]byte
+	// Profile is nil for keys whose file did not record the profile they were generated with
+	Profile        *ParameterProfile
+	IntegerWidths  []uint32
+	SerializedSize uint64
+	// Compressed is set for server keys loaded in compressed form. Fingerprint and SerializedSize
+	// are then those of the compressed key.
+	Compressed bool
+}
+
+// GetKeyInfo returns metadata about the keys loaded into keySet, in server, client, public order.
+// Fingerprints are stable, so they can be logged and compared across nodes.
+func GetKeyInfo(keySet KeySetId) ([]KeyInfo, error) {
+	errmsg := uninitializedUnmanagedVector()
+
+	res, err := C.key_info(C.KeySetId(keySet), &errmsg)
+	if err != nil {
+		return nil, errorWithMessage(err, errmsg)
+	}
+
+	return decodeKeyInfo(copyAndDestroyUnmanagedVector(res))
+}
+
+// decodeKeyInfo decodes the bincode encoding of a Rust Vec<KeyInfo>
+func decodeKeyInfo(data []byte) ([]KeyInfo, error) {
+	malformed := fmt.Errorf("malformed key info")
+
+	if len(data) < 8 {
+		return nil, malformed
+	}
+	count := binary.LittleEndian.Uint64(data)
+	data = data[8:]
+
+	infos := make([]KeyInfo, 0, count)
+	for i := uint64(0); i < count; i++ {
+		var info KeyInfo
+
+		if len(data) < 13 {
+			return nil, malformed
+		}
+		info.Kind = KeyKind(binary.LittleEndian.Uint32(data))
+		copy(info.Fingerprint[:], data[4:12])
+		hasProfile := data[12] == 1
+		data = data[13:]
+
+		if hasProfile {
+			if len(data) < 4 {
+				return nil, malformed
+			}
+			profile := ParameterProfile(binary.LittleEndian.Uint32(data))
+			info.Profile = &profile
+			data = data[4:]
+		}
+
+		if len(data) < 8 {
+			return nil, malformed
+		}
+		widths := binary.LittleEndian.Uint64(data)
+		data = data[8:]
+		if widths > uint64(len(data))/4 || uint64(len(data)) < widths*4+9 {
+			return nil, malformed
+		}
+		for w := uint64(0); w < widths; w++ {
+			info.IntegerWidths = append(info.IntegerWidths, binary.LittleEndian.Uint32(data))
+			data = data[4:]
+		}
+
+		info.SerializedSize = binary.LittleEndian.Uint64(data)
+		info.Compressed = data[8] == 1
+		data = data[9:]
+
+		infos = append(infos, info)
+	}
+
+	return infos, nil
+}
+
+func GetPublicKey(keySet KeySetId) ([]byte, error) {
 	errmsg := uninitializedUnmanagedVector()
 
 	res := C.UnmanagedVector{}
-	res, err := C.get_public_key(&errmsg)
+	res, err := C.get_public_key(C.KeySetId(keySet), &errmsg)
 	if err != nil {
 		return nil, errorWithMessage(err, errmsg)
 	}
@@ -161,12 +624,12 @@ func GetPublicKey() ([]byte, error) {
 	return copyAndDestroyUnmanagedVector(res), nil
 }
 
-func Encrypt(value big.Int, intType UintType) ([]byte, error) {
+func Encrypt(keySet KeySetId, value big.Int, intType UintType) ([]byte, error) {
 	val := value.Uint64()
 
 	errmsg := uninitializedUnmanagedVector()
 
-	res, err := C.encrypt(cu64(val), C.FheUintType(intType), &errmsg)
+	res, err := C.encrypt(C.KeySetId(keySet), cu64(val), C.FheUintType(intType), &errmsg)
 	if err != nil {
 		return nil, errorWithMessage(err, errmsg)
 	}
@@ -174,12 +637,12 @@ func Encrypt(value big.Int, intType UintType) ([]byte, error) {
 	return copyAndDestroyUnmanagedVector(res), nil
 }
 
-func EncryptTrivial(value big.Int, intType UintType) ([]byte, error) {
+func EncryptTrivial(keySet KeySetId, value big.Int, intType UintType) ([]byte, error) {
 	val := value.Uint64()
 
 	errmsg := uninitializedUnmanagedVector()
 
-	res, err := C.trivial_encrypt(cu64(val), C.FheUintType(intType), &errmsg)
+	res, err := C.trivial_encrypt(C.KeySetId(keySet), cu64(val), C.FheUintType(intType), &errmsg)
 	if err != nil {
 		return nil, errorWithMessage(err, errmsg)
 	}
@@ -188,12 +651,12 @@ func EncryptTrivial(value big.Int, intType UintType) ([]byte, error) {
 
 }
 
-func ExpandCompressedValue(cipherText []byte, intType UintType) ([]byte, error) {
+func ExpandCompressedValue(keySet KeySetId, cipherText []byte, intType UintType) ([]byte, error) {
 	ctView := makeView(cipherText)
 	defer runtime.KeepAlive(ctView)
 	errmsg := uninitializedUnmanagedVector()
 
-	res, err := C.expand_compressed(ctView, C.FheUintType(intType), &errmsg)
+	res, err := C.expand_compressed(C.KeySetId(keySet), ctView, C.FheUintType(intType), &errmsg)
 	if err != nil {
 		return nil, errorWithMessage(err, errmsg)
 	}
@@ -201,13 +664,78 @@ func ExpandCompressedValue(cipherText []byte, intType UintType) ([]byte, error)
 	return copyAndDestroyUnmanagedVector(res), nil
 }
 
-func Decrypt(cipherText []byte, intType UintType) (uint64, error) {
+// EncryptList encrypts values into a single compact list, which is much smaller than the values
+// encrypted one by one. Use ExpandList to get the individual ciphertexts back.
+func EncryptList(keySet KeySetId, values []uint64, intType UintType) ([]byte, error) {
+	encoded := make([]byte, 0, 8*len(values))
+	for _, value := range values {
+		encoded = binary.LittleEndian.AppendUint64(encoded, value)
+	}
+
+	valuesView := makeView(encoded)
+	defer runtime.KeepAlive(valuesView)
+
+	errmsg := uninitializedUnmanagedVector()
+
+	res, err := C.encrypt_list(C.KeySetId(keySet), valuesView, C.FheUintType(intType), &errmsg)
+	if err != nil {
+		return nil, errorWithMessage(err, errmsg)
+	}
+
+	return copyAndDestroyUnmanagedVector(res), nil
+}
+
+// ExpandList expands a list made by EncryptList into one expanded ciphertext per value, in order.
+// Lists of more than 256 values are rejected, and every value costs the Expand cost of intType.
+// Both are checked before anything is expanded, so the call fails early if the list costs more
+// than gasBudget.
+func ExpandList(keySet KeySetId, list []byte, intType UintType, gasBudget uint64) ([][]byte, error) {
+	listView := makeView(list)
+	defer runtime.KeepAlive(listView)
+
+	errmsg := uninitializedUnmanagedVector()
+
+	res, err := C.expand_list(C.KeySetId(keySet), listView, C.FheUintType(intType), cu64(gasBudget), &errmsg)
+	if err != nil {
+		return nil, errorWithMessage(err, errmsg)
+	}
+
+	return decodeExpandedList(copyAndDestroyUnmanagedVector(res))
+}
+
+func decodeExpandedList(data []byte) ([][]byte, error) {
+	malformed := fmt.Errorf("malformed expanded list")
+
+	if len(data) < 8 {
+		return nil, malformed
+	}
+	count := binary.LittleEndian.Uint64(data)
+	data = data[8:]
+
+	ciphertexts := make([][]byte, 0, count)
+	for i := uint64(0); i < count; i++ {
+		if len(data) < 8 {
+			return nil, malformed
+		}
+		length := binary.LittleEndian.Uint64(data)
+		data = data[8:]
+		if uint64(len(data)) < length {
+			return nil, malformed
+		}
+		ciphertexts = append(ciphertexts, data[:length])
+		data = data[length:]
+	}
+
+	return ciphertexts, nil
+}
+
+func Decrypt(keySet KeySetId, cipherText []byte, intType UintType) (uint64, error) {
 	ctView := makeView(cipherText)
 	defer runtime.KeepAlive(ctView)
 
 	errmsg := uninitializedUnmanagedVector()
 
-	res, err := C.decrypt(ctView, C.FheUintType(intType), &errmsg)
+	res, err := C.decrypt(C.KeySetId(keySet), ctView, C.FheUintType(intType), &errmsg)
 	if err != nil {
 		return 0, errorWithMessage(err, errmsg)
 	}
@@ -215,17 +743,120 @@ func Decrypt(cipherText []byte, intType UintType) (uint64, error) {
 	return uint64(res), nil
 }
 
-func GenerateFheKeys(clientKeyPath string, serverKeyPath string, publicKeyPath string) error {
+// SealOutput decrypts cipherText and seals the value to the user's 32 byte X25519 public key, so
+// the plaintext never crosses into Go. The result is the JSON used by MetaMask's eth_decrypt.
+func SealOutput(keySet KeySetId, cipherText []byte, intType UintType, userPublicKey []byte) ([]byte, error) {
+	ctView := makeView(cipherText)
+	defer runtime.KeepAlive(ctView)
+
+	pkView := makeView(userPublicKey)
+	defer runtime.KeepAlive(pkView)
+
+	errmsg := uninitializedUnmanagedVector()
+
+	res, err := C.seal_output(C.KeySetId(keySet), ctView, C.FheUintType(intType), pkView, &errmsg)
+	if err != nil {
+		return nil, errorWithMessage(err, errmsg)
+	}
+
+	return copyAndDestroyUnmanagedVector(res), nil
+}
+
+// CiphertextHandle refers to a ciphertext that is kept deserialized on the Rust side.
+// It must be released with FreeHandle.
+type CiphertextHandle uint64
+
+func HandleFromBytes(keySet KeySetId, cipherText []byte, intType UintType) (CiphertextHandle, error) {
+	ctView := makeView(cipherText)
+	defer runtime.KeepAlive(ctView)
+
+	errmsg := uninitializedUnmanagedVector()
+
+	res, err := C.handle_from_bytes(C.KeySetId(keySet), ctView, C.FheUintType(intType), &errmsg)
+	if err != nil {
+		return 0, errorWithMessage(err, errmsg)
+	}
+
+	return CiphertextHandle(res), nil
+}
+
+func HandleMathOperation(keySet KeySetId, lhs CiphertextHandle, rhs CiphertextHandle, op OperationType, gasBudget uint64) (CiphertextHandle, error) {
+	errmsg := uninitializedUnmanagedVector()
+
+	res, err := C.handle_math_operation(C.KeySetId(keySet), cu64(lhs), cu64(rhs), ci32(op), cu64(gasBudget), &errmsg)
+	if err != nil {
+		return 0, errorWithMessage(err, errmsg)
+	}
+
+	return CiphertextHandle(res), nil
+}
+
+func HandleScalarMathOperation(keySet KeySetId, lhs CiphertextHandle, rhs uint64, op OperationType, gasBudget uint64) (CiphertextHandle, error) {
+	errmsg := uninitializedUnmanagedVector()
+
+	res, err := C.handle_scalar_math_operation(C.KeySetId(keySet), cu64(lhs), cu64(rhs), ci32(op), cu64(gasBudget), &errmsg)
+	if err != nil {
+		return 0, errorWithMessage(err, errmsg)
+	}
+
+	return CiphertextHandle(res), nil
+}
+
+func HandleUnaryMathOperation(keySet KeySetId, lhs CiphertextHandle, op OperationType, gasBudget uint64) (CiphertextHandle, error) {
+	errmsg := uninitializedUnmanagedVector()
+
+	res, err := C.handle_unary_math_operation(C.KeySetId(keySet), cu64(lhs), ci32(op), cu64(gasBudget), &errmsg)
+	if err != nil {
+		return 0, errorWithMessage(err, errmsg)
+	}
+
+	return CiphertextHandle(res), nil
+}
+
+func HandleToBytes(keySet KeySetId, handle CiphertextHandle) ([]byte, error) {
+	errmsg := uninitializedUnmanagedVector()
+
+	res, err := C.handle_to_bytes(C.KeySetId(keySet), cu64(handle), &errmsg)
+	if err != nil {
+		return nil, errorWithMessage(err, errmsg)
+	}
+
+	return copyAndDestroyUnmanagedVector(res), nil
+}
+
+func FreeHandle(handle CiphertextHandle) bool {
+	return bool(C.free_handle(cu64(handle)))
+}
+
+// optionalCString is C.CString, except that an empty path is passed as NULL
+func optionalCString(path string) *C.char {
+	if path == "" {
+		return nil
+	}
+	return C.CString(path)
+}
+
+// GenerateFheKeys generates a key set with the given profile and writes each key to its path. If
+// compressedServerKeyPath is not empty, the server key is also written there in compressed form,
+// which loads like the full one. If clientKeyPassphrase is not nil, the client key file is
+// encrypted with it and has to be loaded with DeserializeEncryptedClientKey.
+func GenerateFheKeys(clientKeyPath string, serverKeyPath string, compressedServerKeyPath string, publicKeyPath string, profile ParameterProfile, clientKeyPassphrase []byte) error {
 	cClientPath := C.CString(clientKeyPath)
 	defer C.free(unsafe.Pointer(cClientPath))
 
 	cServerPath := C.CString(serverKeyPath)
 	defer C.free(unsafe.Pointer(cServerPath))
 
+	cCompressedServerPath := optionalCString(compressedServerKeyPath)
+	defer C.free(unsafe.Pointer(cCompressedServerPath))
+
 	cPublicPath := C.CString(publicKeyPath)
 	defer C.free(unsafe.Pointer(cPublicPath))
 
-	success := C.generate_full_keys(cClientPath, cServerPath, cPublicPath)
+	passphraseView := makeView(clientKeyPassphrase)
+	defer runtime.KeepAlive(passphraseView)
+
+	success := C.generate_full_keys(cClientPath, cServerPath, cCompressedServerPath, cPublicPath, C.ParameterProfile(profile), passphraseView)
 
 	if !success {
 		return fmt.Errorf("failed to generate keys for fhe")
@@ -234,16 +865,57 @@ func GenerateFheKeys(clientKeyPath string, serverKeyPath string, publicKeyPath s
 	return nil
 }
 
+// KeygenSeedLen is the length of the seed taken by GenerateFheKeysFromSeedInsecure
+const KeygenSeedLen = C.KEYGEN_SEED_LEN
+
+// GenerateFheKeysFromSeedInsecure is GenerateFheKeys with all randomness derived from seed, so
+// the same seed and profile always produce bit-identical key files.
+//
+// INSECURE: anyone who knows the seed can rebuild the client key. Only use it for devnets and CI,
+// never for production keys.
+func GenerateFheKeysFromSeedInsecure(clientKeyPath string, serverKeyPath string, compressedServerKeyPath string, publicKeyPath string, profile ParameterProfile, seed [KeygenSeedLen]byte, clientKeyPassphrase []byte) error {
+	cClientPath := C.CString(clientKeyPath)
+	defer C.free(unsafe.Pointer(cClientPath))
+
+	cServerPath := C.CString(serverKeyPath)
+	defer C.free(unsafe.Pointer(cServerPath))
+
+	cCompressedServerPath := optionalCString(compressedServerKeyPath)
+	defer C.free(unsafe.Pointer(cCompressedServerPath))
+
+	cPublicPath := C.CString(publicKeyPath)
+	defer C.free(unsafe.Pointer(cPublicPath))
+
+	seedView := makeView(seed[:])
+	defer runtime.KeepAlive(seedView)
+
+	passphraseView := makeView(clientKeyPassphrase)
+	defer runtime.KeepAlive(passphraseView)
+
+	success := C.generate_full_keys_from_seed_insecure(cClientPath, cServerPath, cCompressedServerPath, cPublicPath, C.ParameterProfile(profile), seedView, passphraseView)
+
+	if !success {
+		return fmt.Errorf("failed to generate seeded keys for fhe")
+	}
+
+	return nil
+}
+
 /**** To error module ***/
 
 func errorWithMessage(err error, b C.UnmanagedVector) error {
-	// this checks for out of gas as a special case
-	if errno, ok := err.(syscall.Errno); ok && int(errno) == 2 {
-		return types.Error{}
-	}
 	msg := copyAndDestroyUnmanagedVector(b)
+
+	errno, ok := err.(syscall.Errno)
+	if !ok {
+		if msg == nil {
+			return err
+		}
+		return fmt.Errorf("%s", string(msg))
+	}
+
 	if msg == nil {
-		return err
+		msg = []byte(errno.Error())
 	}
-	return fmt.Errorf("%s", string(msg))
+	return &TfheError{Code: ErrorCode(errno), Message: string(msg)}
 }
@@ -0,0 +1,13 @@
+//go:build cgo && mock_backend
+
+package amd64
+
+// #cgo CFLAGS: -DMOCK_BACKEND
+// #include "bindings.h"
+import "C"
+
+// SetMockBackend swaps the tfhe backend for a plaintext mock. Ciphertexts are not encrypted while
+// it is enabled - only use it in tests. Needs a library built with the mock-backend feature.
+func SetMockBackend(enabled bool) {
+	C.set_mock_backend(C.bool(enabled))
+}
@@ -15,6 +15,9 @@ type Config struct {
 	OraclePrivateKeyPath string `koanf:"oracle-private-key-path"`
 	OraclePublicKeyPath  string `koanf:"oracle-public-key-path"`
 	HomeDir              string `koanf:"home-dir"`
+	// ClientKeyPassphrasePath points to a file holding the passphrase the client key file is
+	// encrypted with. Leave it empty for an unencrypted client key.
+	ClientKeyPassphrasePath string `koanf:"client-key-passphrase-path"`
 }
 
 var ConfigDefault = Config{
@@ -9,59 +9,87 @@ import (
 	"github.com/fhenixprotocol/go-tfhe/internal/api/amd64"
 )
 
-func mathOperation(lhs []byte, rhs []byte, uintType uint8, op uint32) ([]byte, error) {
-	return amd64.MathOperation(lhs, rhs, uintType, amd64.OperationType(op))
+func mathOperation(lhs []byte, rhs []byte, uintType uint8, op uint32, gasBudget uint64) ([]byte, error) {
+	return amd64.MathOperation(amd64.DefaultKeySet, lhs, rhs, uintType, amd64.OperationType(op), gasBudget)
 }
 
-func castOperation(val []byte, fromType uint8, toType uint8) ([]byte, error) {
-	return amd64.CastOperation(val, fromType, toType)
+func castOperation(val []byte, fromType uint8, toType uint8, gasBudget uint64) ([]byte, error) {
+	return amd64.CastOperation(amd64.DefaultKeySet, val, fromType, toType, gasBudget)
 }
 
-func cmux(control []byte, ifTrue []byte, ifFalse []byte, uintType uint8) ([]byte, error) {
-	return amd64.Cmux(control, ifTrue, ifFalse, uintType)
+func cmux(control []byte, ifTrue []byte, ifFalse []byte, controlType uint8, uintType uint8, gasBudget uint64) ([]byte, error) {
+	return amd64.Cmux(amd64.DefaultKeySet, control, ifTrue, ifFalse, controlType, uintType, gasBudget)
 }
 
-func unaryMathOperation(lhs []byte, uintType uint8, op uint32) ([]byte, error) {
-	return amd64.UnaryMathOperation(lhs, uintType, amd64.OperationType(op))
+func comparisonOperation(lhs []byte, rhs []byte, uintType uint8, op uint32, gasBudget uint64) ([]byte, error) {
+	return amd64.ComparisonOperation(amd64.DefaultKeySet, lhs, rhs, uintType, amd64.OperationType(op), gasBudget)
+}
+
+func scalarMathOperation(lhs []byte, rhs uint64, uintType uint8, op uint32, gasBudget uint64) ([]byte, error) {
+	return amd64.ScalarMathOperation(amd64.DefaultKeySet, lhs, rhs, uintType, amd64.OperationType(op), gasBudget)
+}
+
+func unaryMathOperation(lhs []byte, uintType uint8, op uint32, gasBudget uint64) ([]byte, error) {
+	return amd64.UnaryMathOperation(amd64.DefaultKeySet, lhs, uintType, amd64.OperationType(op), gasBudget)
 }
 
 func DeserializeServerKey(serverKeyBytes []byte) (bool, error) {
-	return amd64.DeserializeServerKey(serverKeyBytes)
+	return amd64.DeserializeServerKey(amd64.DefaultKeySet, serverKeyBytes)
 
 }
 
 func DeserializeClientKey(clientKeyBytes []byte) (bool, error) {
-	return amd64.DeserializeClientKey(clientKeyBytes)
+	return amd64.DeserializeClientKey(amd64.DefaultKeySet, clientKeyBytes)
 
 }
 
 func DeserializePublicKey(publicKeyBytes []byte) (bool, error) {
-	return amd64.DeserializePublicKey(publicKeyBytes)
+	return amd64.DeserializePublicKey(amd64.DefaultKeySet, publicKeyBytes)
+
+}
+
+func DeserializeEncryptedClientKey(clientKeyBytes []byte, passphrase []byte) (bool, error) {
+	return amd64.DeserializeEncryptedClientKey(amd64.DefaultKeySet, clientKeyBytes, passphrase)
+}
 
+func ValidateKeys() error {
+	return amd64.ValidateKeys(amd64.DefaultKeySet)
 }
 
 func GetPublicKey() ([]byte, error) {
-	return amd64.GetPublicKey()
+	return amd64.GetPublicKey(amd64.DefaultKeySet)
 }
 
 func Encrypt(value big.Int, intType UintType) ([]byte, error) {
-	return amd64.Encrypt(value, amd64.UintType(intType))
+	return amd64.Encrypt(amd64.DefaultKeySet, value, amd64.UintType(intType))
 }
 
 func EncryptTrivial(value big.Int, intType UintType) ([]byte, error) {
-	return amd64.EncryptTrivial(value, amd64.UintType(intType))
+	return amd64.EncryptTrivial(amd64.DefaultKeySet, value, amd64.UintType(intType))
 }
 
 func ExpandCompressedValue(cipherText []byte, intType UintType) ([]byte, error) {
-	return amd64.ExpandCompressedValue(cipherText, amd64.UintType(intType))
+	return amd64.ExpandCompressedValue(amd64.DefaultKeySet, cipherText, amd64.UintType(intType))
+}
+
+func EncryptList(values []uint64, intType UintType) ([]byte, error) {
+	return amd64.EncryptList(amd64.DefaultKeySet, values, amd64.UintType(intType))
+}
+
+func ExpandList(list []byte, intType UintType, gasBudget uint64) ([][]byte, error) {
+	return amd64.ExpandList(amd64.DefaultKeySet, list, amd64.UintType(intType), gasBudget)
 }
 
 func Decrypt(cipherText []byte, intType UintType) (uint64, error) {
-	return amd64.Decrypt(cipherText, amd64.UintType(intType))
+	return amd64.Decrypt(amd64.DefaultKeySet, cipherText, amd64.UintType(intType))
+}
+
+func SealOutput(cipherText []byte, intType UintType, userPublicKey []byte) ([]byte, error) {
+	return amd64.SealOutput(amd64.DefaultKeySet, cipherText, amd64.UintType(intType), userPublicKey)
 }
 
 func GenerateFheKeys(clientKeyPath string, serverKeyPath string, publicKeyPath string) error {
-	return amd64.GenerateFheKeys(clientKeyPath, serverKeyPath, publicKeyPath)
+	return amd64.GenerateFheKeys(clientKeyPath, serverKeyPath, "", publicKeyPath, amd64.DefaultParameterProfile, nil)
 }
 
 func LibTfheVersion() uint32 {
@@ -6,20 +6,32 @@ import (
 	"github.com/fhenixprotocol/go-tfhe/internal/api/wasm"
 )
 
-func mathOperation(lhs []byte, rhs []byte, uintType uint8, op uint32) ([]byte, error) {
-	return wasm.MathOperation(lhs, rhs, uintType, wasm.OperationType(op))
+func mathOperation(lhs []byte, rhs []byte, uintType uint8, op uint32, gasBudget uint64) ([]byte, error) {
+	return wasm.MathOperation(lhs, rhs, uintType, wasm.OperationType(op), gasBudget)
 }
 
-func unaryMathOperation(lhs []byte, uintType uint8, op uint32) ([]byte, error) {
-	return wasm.UnaryMathOperation(lhs, uintType, wasm.OperationType(op))
+func scalarMathOperation(lhs []byte, rhs uint64, uintType uint8, op uint32, gasBudget uint64) ([]byte, error) {
+	return wasm.ScalarMathOperation(lhs, rhs, uintType, wasm.OperationType(op), gasBudget)
 }
 
-func castOperation(val []byte, fromType uint8, toType uint8) ([]byte, error) {
-	return wasm.CastOperation(val, fromType, toType)
+func unaryMathOperation(lhs []byte, uintType uint8, op uint32, gasBudget uint64) ([]byte, error) {
+	return wasm.UnaryMathOperation(lhs, uintType, wasm.OperationType(op), gasBudget)
 }
 
-func cmux(control []byte, ifTrue []byte, ifFalse []byte, uintType uint8) ([]byte, error) {
-	return wasm.Cmux(control, ifTrue, ifFalse, uintType)
+func castOperation(val []byte, fromType uint8, toType uint8, gasBudget uint64) ([]byte, error) {
+	return wasm.CastOperation(val, fromType, toType, gasBudget)
+}
+
+func cmux(control []byte, ifTrue []byte, ifFalse []byte, controlType uint8, uintType uint8, gasBudget uint64) ([]byte, error) {
+	return wasm.Cmux(control, ifTrue, ifFalse, controlType, uintType, gasBudget)
+}
+
+func comparisonOperation(lhs []byte, rhs []byte, uintType uint8, op uint32, gasBudget uint64) ([]byte, error) {
+	return wasm.ComparisonOperation(lhs, rhs, uintType, wasm.OperationType(op), gasBudget)
+}
+
+func ValidateKeys() error {
+	return wasm.ValidateKeys()
 }
 
 func DeserializeServerKey(serverKeyBytes []byte) (bool, error) {
@@ -31,6 +43,10 @@ func DeserializeClientKey(clientKeyBytes []byte) (bool, error) {
 	return wasm.DeserializeClientKey(clientKeyBytes)
 }
 
+func DeserializeEncryptedClientKey(clientKeyBytes []byte, passphrase []byte) (bool, error) {
+	return wasm.DeserializeEncryptedClientKey(clientKeyBytes, passphrase)
+}
+
 func DeserializePublicKey(publicKeyBytes []byte) (bool, error) {
 	return wasm.DeserializePublicKey(publicKeyBytes)
 
@@ -52,10 +68,22 @@ func ExpandCompressedValue(cipherText []byte, intType UintType) ([]byte, error)
 	return wasm.ExpandCompressedValue(cipherText, wasm.UintType(intType))
 }
 
+func EncryptList(values []uint64, intType UintType) ([]byte, error) {
+	return wasm.EncryptList(values, wasm.UintType(intType))
+}
+
+func ExpandList(list []byte, intType UintType, gasBudget uint64) ([][]byte, error) {
+	return wasm.ExpandList(list, wasm.UintType(intType), gasBudget)
+}
+
 func Decrypt(cipherText []byte, intType UintType) (uint64, error) {
 	return wasm.Decrypt(cipherText, wasm.UintType(intType))
 }
 
+func SealOutput(cipherText []byte, intType UintType, userPublicKey []byte) ([]byte, error) {
+	return wasm.SealOutput(cipherText, wasm.UintType(intType), userPublicKey)
+}
+
 func SignRequire(ciphertext []byte, value bool) string {
 	return wasm.SignRequire(ciphertext, value)
 }
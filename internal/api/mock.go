@@ -0,0 +1,9 @@
+//go:build (amd64 || arm64) && mock_backend
+
+package api
+
+import "github.com/fhenixprotocol/go-tfhe/internal/api/amd64"
+
+func SetMockBackend(enabled bool) {
+	amd64.SetMockBackend(enabled)
+}
@@ -0,0 +1,9 @@
+//go:build mock_backend
+
+package api
+
+import "github.com/fhenixprotocol/go-tfhe/internal/api/wasm"
+
+func SetMockBackend(enabled bool) {
+	wasm.SetMockBackend(enabled)
+}
@@ -122,12 +122,12 @@ func (ct *Ciphertext) IsRandom() bool {
 	return ct.random
 }
 
-func (ct *Ciphertext) Cast(toType UintType) (*Ciphertext, error) {
+func (ct *Ciphertext) Cast(toType UintType, gasBudget uint64) (*Ciphertext, error) {
 	if ct.UintType == toType {
 		return ct, nil
 	}
 
-	res, err := castOperation(ct.Serialization, uint8(ct.UintType), uint8(toType))
+	res, err := castOperation(ct.Serialization, uint8(ct.UintType), uint8(toType), gasBudget)
 	if err != nil {
 		return nil, err
 	}
@@ -139,12 +139,12 @@ func (ct *Ciphertext) Cast(toType UintType) (*Ciphertext, error) {
 	}, nil
 }
 
-func (ct *Ciphertext) Cmux(ifTrue *Ciphertext, ifFalse *Ciphertext) (*Ciphertext, error) {
+func (ct *Ciphertext) Cmux(ifTrue *Ciphertext, ifFalse *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
 	if ifFalse.UintType != ifTrue.UintType {
 		return nil, fmt.Errorf("cannot use selector on uints of different types")
 	}
 
-	res, err := cmux(ct.Serialization, ifTrue.Serialization, ifFalse.Serialization, uint8(ifTrue.UintType))
+	res, err := cmux(ct.Serialization, ifTrue.Serialization, ifFalse.Serialization, uint8(ct.UintType), uint8(ifTrue.UintType), gasBudget)
 	if err != nil {
 		return nil, err
 	}
@@ -162,12 +162,19 @@ func (ct *Ciphertext) Decrypt() (*big.Int, error) {
 	return big.NewInt(int64(res)), err
 }
 
-func (ct *Ciphertext) performMathOperation(rhs *Ciphertext, operation uint32) (*Ciphertext, error) {
+// SealOutput decrypts the ciphertext and seals the value to the user's X25519 public key
+func (ct *Ciphertext) SealOutput(userPublicKey []byte) ([]byte, error) {
+	return SealOutput(ct.Serialization, ct.UintType, userPublicKey)
+}
+
+// performMathOperation fails before doing any FHE work if the operation costs more than gasBudget -
+// pass UnlimitedGas to skip the check. The same goes for the other operations below.
+func (ct *Ciphertext) performMathOperation(rhs *Ciphertext, operation uint32, gasBudget uint64) (*Ciphertext, error) {
 	if ct.UintType != rhs.UintType {
 		return nil, fmt.Errorf("cannot perform operation on uints of different types")
 	}
 
-	res, err := mathOperation(ct.Serialization, rhs.Serialization, uint8(ct.UintType), operation)
+	res, err := mathOperation(ct.Serialization, rhs.Serialization, uint8(ct.UintType), operation, gasBudget)
 	if err != nil {
 		return nil, err
 	}
@@ -179,8 +186,8 @@ func (ct *Ciphertext) performMathOperation(rhs *Ciphertext, operation uint32) (*
 	}, nil
 }
 
-func (ct *Ciphertext) performUnaryMathOperation(operation uint32) (*Ciphertext, error) {
-	res, err := unaryMathOperation(ct.Serialization, uint8(ct.UintType), operation)
+func (ct *Ciphertext) performUnaryMathOperation(operation uint32, gasBudget uint64) (*Ciphertext, error) {
+	res, err := unaryMathOperation(ct.Serialization, uint8(ct.UintType), operation, gasBudget)
 	if err != nil {
 		return nil, err
 	}
@@ -195,78 +202,78 @@ func (ct *Ciphertext) performUnaryMathOperation(operation uint32) (*Ciphertext,
 // Now you can use the above function to implement the original methods:
 
 // Add performs ciphertext addition.
-func (ct *Ciphertext) Add(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, add)
+func (ct *Ciphertext) Add(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, add, gasBudget)
 }
 
-func (ct *Ciphertext) Sub(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, sub)
+func (ct *Ciphertext) Sub(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, sub, gasBudget)
 }
 
-func (ct *Ciphertext) Mul(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, mul)
+func (ct *Ciphertext) Mul(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, mul, gasBudget)
 }
 
-func (ct *Ciphertext) Lt(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, lt)
+func (ct *Ciphertext) Lt(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, lt, gasBudget)
 }
 
-func (ct *Ciphertext) Lte(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, lte)
+func (ct *Ciphertext) Lte(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, lte, gasBudget)
 }
 
-func (ct *Ciphertext) Div(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, div)
+func (ct *Ciphertext) Div(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, div, gasBudget)
 }
 
-func (ct *Ciphertext) Gt(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, gt)
+func (ct *Ciphertext) Gt(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, gt, gasBudget)
 }
 
-func (ct *Ciphertext) Gte(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, gte)
+func (ct *Ciphertext) Gte(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, gte, gasBudget)
 }
 
-func (ct *Ciphertext) Rem(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, rem)
+func (ct *Ciphertext) Rem(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, rem, gasBudget)
 }
 
-func (ct *Ciphertext) And(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, and)
+func (ct *Ciphertext) And(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, and, gasBudget)
 }
 
-func (ct *Ciphertext) Or(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, or)
+func (ct *Ciphertext) Or(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, or, gasBudget)
 }
 
-func (ct *Ciphertext) Xor(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, xor)
+func (ct *Ciphertext) Xor(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, xor, gasBudget)
 }
 
-func (ct *Ciphertext) Eq(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, eq)
+func (ct *Ciphertext) Eq(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, eq, gasBudget)
 }
 
-func (ct *Ciphertext) Ne(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, ne)
+func (ct *Ciphertext) Ne(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, ne, gasBudget)
 }
 
-func (ct *Ciphertext) Min(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, min)
+func (ct *Ciphertext) Min(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, min, gasBudget)
 }
 
-func (ct *Ciphertext) Max(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, max)
+func (ct *Ciphertext) Max(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, max, gasBudget)
 }
 
-func (ct *Ciphertext) Shl(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, shl)
+func (ct *Ciphertext) Shl(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, shl, gasBudget)
 }
 
-func (ct *Ciphertext) Shr(rhs *Ciphertext) (*Ciphertext, error) {
-	return ct.performMathOperation(rhs, shr)
+func (ct *Ciphertext) Shr(rhs *Ciphertext, gasBudget uint64) (*Ciphertext, error) {
+	return ct.performMathOperation(rhs, shr, gasBudget)
 }
 
-func (ct *Ciphertext) Not() (*Ciphertext, error) {
-	return ct.performUnaryMathOperation(not)
+func (ct *Ciphertext) Not(gasBudget uint64) (*Ciphertext, error) {
+	return ct.performUnaryMathOperation(not, gasBudget)
 }
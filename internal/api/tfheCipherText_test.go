@@ -75,7 +75,7 @@ func TestCmux(t *testing.T) {
 			ifTrue, _ := api.NewCipherText(*tr, tt.uintType, false)
 			ifFalse, _ := api.NewCipherText(*fl, tt.uintType, false)
 
-			res, err := control.Cmux(ifTrue, ifFalse)
+			res, err := control.Cmux(ifTrue, ifFalse, api.UnlimitedGas)
 			if err != nil {
 				t.Fatalf("Got error %v", err)
 			}
@@ -128,7 +128,7 @@ func TestCiphertextCastOperations(t *testing.T) {
 	for _, tt := range testCases {
 		t.Run(tt.name, func(t *testing.T) {
 			ct, _ := api.NewCipherText(*val, tt.fromType, false)
-			res, err := ct.Cast(tt.toType)
+			res, err := ct.Cast(tt.toType, api.UnlimitedGas)
 			if err != nil {
 				t.Fatalf("Got error %v", err)
 			}
@@ -163,22 +163,22 @@ func TestCipherTextOperations(t *testing.T) {
 	type operationFunc func(a, b *api.Ciphertext) (*api.Ciphertext, error)
 
 	addOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Add(b)
+		return a.Add(b, api.UnlimitedGas)
 	}
 	addResultFunc := func(a, b *big.Int) *big.Int { return new(big.Int).Add(a, b) }
 	addResultFuncOverflow := func(a, b *big.Int) *big.Int { return new(big.Int).Mod(new(big.Int).Add(a, b), big.NewInt(256)) }
 
 	subOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Sub(b)
+		return a.Sub(b, api.UnlimitedGas)
 	}
 	subResultFunc := func(a, b *big.Int) *big.Int { return new(big.Int).Sub(a, b) }
 	mulOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Mul(b)
+		return a.Mul(b, api.UnlimitedGas)
 	}
 	mulResultFunc := func(a, b *big.Int) *big.Int { return new(big.Int).Mul(a, b) }
 
 	lteOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Lte(b)
+		return a.Lte(b, api.UnlimitedGas)
 	}
 	lteResultFunc := func(a, b *big.Int) *big.Int {
 		if a.Cmp(b) <= 0 {
@@ -188,7 +188,7 @@ func TestCipherTextOperations(t *testing.T) {
 	}
 
 	ltOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Lt(b)
+		return a.Lt(b, api.UnlimitedGas)
 	}
 	ltResultFunc := func(a, b *big.Int) *big.Int {
 		if a.Cmp(b) < 0 {
@@ -198,12 +198,12 @@ func TestCipherTextOperations(t *testing.T) {
 	}
 
 	divOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Div(b)
+		return a.Div(b, api.UnlimitedGas)
 	}
 	divResultFunc := func(a, b *big.Int) *big.Int { return new(big.Int).Div(a, b) }
 
 	gtOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Gt(b)
+		return a.Gt(b, api.UnlimitedGas)
 	}
 	gtResultFunc := func(a, b *big.Int) *big.Int {
 		if a.Cmp(b) > 0 {
@@ -213,7 +213,7 @@ func TestCipherTextOperations(t *testing.T) {
 	}
 
 	gteOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Gte(b)
+		return a.Gte(b, api.UnlimitedGas)
 	}
 	gteResultFunc := func(a, b *big.Int) *big.Int {
 		if a.Cmp(b) >= 0 {
@@ -223,27 +223,27 @@ func TestCipherTextOperations(t *testing.T) {
 	}
 
 	remOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Rem(b)
+		return a.Rem(b, api.UnlimitedGas)
 	}
 	remResultFunc := func(a, b *big.Int) *big.Int { return new(big.Int).Rem(a, b) }
 
 	andOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.And(b)
+		return a.And(b, api.UnlimitedGas)
 	}
 	andResultFunc := func(a, b *big.Int) *big.Int { return new(big.Int).And(a, b) }
 
 	orOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Or(b)
+		return a.Or(b, api.UnlimitedGas)
 	}
 	orResultFunc := func(a, b *big.Int) *big.Int { return new(big.Int).Or(a, b) }
 
 	xorOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Xor(b)
+		return a.Xor(b, api.UnlimitedGas)
 	}
 	xorResultFunc := func(a, b *big.Int) *big.Int { return new(big.Int).Xor(a, b) }
 
 	eqOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Eq(b)
+		return a.Eq(b, api.UnlimitedGas)
 	}
 	eqResultFunc := func(a, b *big.Int) *big.Int {
 		if a.Cmp(b) == 0 {
@@ -253,7 +253,7 @@ func TestCipherTextOperations(t *testing.T) {
 	}
 
 	neOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Ne(b)
+		return a.Ne(b, api.UnlimitedGas)
 	}
 	neResultFunc := func(a, b *big.Int) *big.Int {
 		if a.Cmp(b) != 0 {
@@ -263,7 +263,7 @@ func TestCipherTextOperations(t *testing.T) {
 	}
 
 	minOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Min(b)
+		return a.Min(b, api.UnlimitedGas)
 	}
 	minResultFunc := func(a, b *big.Int) *big.Int {
 		if a.Cmp(b) <= 0 {
@@ -273,7 +273,7 @@ func TestCipherTextOperations(t *testing.T) {
 	}
 
 	maxOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Max(b)
+		return a.Max(b, api.UnlimitedGas)
 	}
 	maxResultFunc := func(a, b *big.Int) *big.Int {
 		if a.Cmp(b) >= 0 {
@@ -283,12 +283,12 @@ func TestCipherTextOperations(t *testing.T) {
 	}
 
 	shlOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Shl(b)
+		return a.Shl(b, api.UnlimitedGas)
 	}
 	shlResultFunc := func(a, b *big.Int) *big.Int { return a.Lsh(a, uint(b.Uint64())) }
 
 	shrOp := func(a, b *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Shr(b)
+		return a.Shr(b, api.UnlimitedGas)
 	}
 	shrResultFunc := func(a, b *big.Int) *big.Int { return a.Rsh(a, uint(b.Uint64())) }
 
@@ -438,7 +438,7 @@ func TestCipherTextUnaryMathOperation(t *testing.T) {
 	}
 
 	notOp := func(a *api.Ciphertext) (*api.Ciphertext, error) {
-		return a.Not()
+		return a.Not(api.UnlimitedGas)
 	}
 	notResultFunc := func(a *big.Int, uintType api.UintType) *big.Int {
 		fmt.Println("received number:", a)
@@ -511,3 +511,31 @@ func TestCipherTextUnaryMathOperation(t *testing.T) {
 		})
 	}
 }
+
+func TestCipherTextOperationsRespectGasBudget(t *testing.T) {
+	err := setupKeysForTests()
+	if err != nil {
+		t.Fatalf("failed loading keys: %s", err)
+	}
+
+	a, err := api.NewCipherText(*big.NewInt(1), api.Uint8, false)
+	if err != nil {
+		t.Fatalf("failed to encrypt: %s", err)
+	}
+
+	_, err = a.Add(a, 0)
+	assert.Error(t, err)
+
+	_, err = a.Not(0)
+	assert.Error(t, err)
+
+	res, err := a.Add(a, api.UnlimitedGas)
+	if err != nil {
+		t.Fatalf("failed to add: %s", err)
+	}
+	resDec, err := res.Decrypt()
+	if err != nil {
+		t.Fatalf("failed to decrypt: %s", err)
+	}
+	assert.Equal(t, big.NewInt(2), resDec)
+}
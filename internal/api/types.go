@@ -10,6 +10,8 @@ const (
 	Uint8  UintType = 0
 	Uint16 UintType = 1
 	Uint32 UintType = 2
+	Uint64 UintType = 3
+	Bool   UintType = 4
 )
 
 // For now track this in a global, but this is kind of messy
@@ -46,6 +48,9 @@ const (
 	not uint32 = 0
 )
 
+// UnlimitedGas disables the gas budget check of the Ciphertext operations
+const UnlimitedGas uint64 = ^uint64(0)
+
 type UintType uint32
 
 const HashLength = 32
@@ -8,7 +8,7 @@ import (
 
 func setupKeysForTests() error {
 
-	pk, err := amd64.GetPublicKey()
+	pk, err := amd64.GetPublicKey(amd64.DefaultKeySet)
 	if pk != nil && err == nil {
 		return nil
 	}
@@ -22,7 +22,7 @@ func setupKeysForTests() error {
 	pksPath := path.Join(tmpDir, "pks")
 	sksPath := path.Join(tmpDir, "sks")
 
-	err = amd64.GenerateFheKeys(cksPath, sksPath, pksPath)
+	err = amd64.GenerateFheKeys(cksPath, sksPath, "", pksPath, amd64.DefaultParameterProfile, nil)
 	if err != nil {
 		return err
 	}
@@ -40,15 +40,15 @@ func setupKeysForTests() error {
 		return err
 	}
 
-	_, err = amd64.DeserializePublicKey(PKS)
+	_, err = amd64.DeserializePublicKey(amd64.DefaultKeySet, PKS)
 	if err != nil {
 		return err
 	}
-	_, err = amd64.DeserializeServerKey(SKS)
+	_, err = amd64.DeserializeServerKey(amd64.DefaultKeySet, SKS)
 	if err != nil {
 		return err
 	}
-	_, err = amd64.DeserializeClientKey(CKS)
+	_, err = amd64.DeserializeClientKey(amd64.DefaultKeySet, CKS)
 	if err != nil {
 		return err
 	}
@@ -54,7 +54,9 @@ func Encrypt(value big.Int, intType UintType) ([]byte, error) {
 	return CopyAndDestroyUnmanagedVector(returnValue), nil
 }
 
-func MathOperation(lhs []byte, rhs []byte, uintType uint8, op OperationType) ([]byte, error) {
+// MathOperation runs the operation on the wasm host. The host does not meter operations yet, so
+// gasBudget is not enforced here.
+func MathOperation(lhs []byte, rhs []byte, uintType uint8, op OperationType, gasBudget uint64) ([]byte, error) {
 	num1 := MakeView(lhs)
 	defer runtime.KeepAlive(num1)
 
@@ -73,15 +75,23 @@ func MathOperation(lhs []byte, rhs []byte, uintType uint8, op OperationType) ([]
 
 // ******************** HERE BE PLACEHOLDERS ********** //
 
-func UnaryMathOperation(lhs []byte, uintType uint8, op OperationType) ([]byte, error) {
+func ScalarMathOperation(lhs []byte, rhs uint64, uintType uint8, op OperationType, gasBudget uint64) ([]byte, error) {
 	return nil, nil
 }
 
-func CastOperation(val []byte, fromType uint8, toType uint8) ([]byte, error) {
+func UnaryMathOperation(lhs []byte, uintType uint8, op OperationType, gasBudget uint64) ([]byte, error) {
 	return nil, nil
 }
 
-func Cmux(control []byte, ifTrue []byte, ifFalse []byte, uintType uint8) ([]byte, error) {
+func CastOperation(val []byte, fromType uint8, toType uint8, gasBudget uint64) ([]byte, error) {
+	return nil, nil
+}
+
+func Cmux(control []byte, ifTrue []byte, ifFalse []byte, controlType uint8, uintType uint8, gasBudget uint64) ([]byte, error) {
+	return nil, nil
+}
+
+func ComparisonOperation(lhs []byte, rhs []byte, uintType uint8, op OperationType, gasBudget uint64) ([]byte, error) {
 	return nil, nil
 }
 
@@ -100,10 +110,22 @@ func ExpandCompressedValue(cipherText []byte, intType UintType) ([]byte, error)
 	return nil, nil
 }
 
+func EncryptList(values []uint64, intType UintType) ([]byte, error) {
+	return nil, nil
+}
+
+func ExpandList(list []byte, intType UintType, gasBudget uint64) ([][]byte, error) {
+	return nil, nil
+}
+
 func Decrypt(cipherText []byte, intType UintType) (uint64, error) {
 	return 0, nil
 }
 
+func SealOutput(cipherText []byte, intType UintType, userPublicKey []byte) ([]byte, error) {
+	return nil, nil
+}
+
 func SignRequire(ciphertext []byte, value bool) string {
 	return ""
 }
@@ -120,6 +142,14 @@ func GenerateFheKeys(clientKeyPath string, serverKeyPath string, publicKeyPath s
 	return nil
 }
 
+func SetMockBackend(enabled bool) {
+	// TODO: Implement the logic here
+}
+
+func ValidateKeys() error {
+	return nil
+}
+
 func DeserializeServerKey(serverKeyBytes []byte) (bool, error) {
 	// TODO: Implement the logic here
 	return false, nil
@@ -130,6 +160,11 @@ func DeserializeClientKey(clientKeyBytes []byte) (bool, error) {
 	return false, nil
 }
 
+func DeserializeEncryptedClientKey(clientKeyBytes []byte, passphrase []byte) (bool, error) {
+	// TODO: Implement the logic here
+	return false, nil
+}
+
 func DeserializePublicKey(publicKeyBytes []byte) (bool, error) {
 	// TODO: Implement the logic here
 	return false, nil
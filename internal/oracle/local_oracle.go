@@ -1,16 +1,12 @@
 package oracle
 
 import (
-	"crypto/rand"
-	"encoding/base64"
 	"encoding/hex"
 	"encoding/json"
 	"errors"
 	"fmt"
 	"github.com/fhenixprotocol/go-tfhe/internal/api"
 	"github.com/fhenixprotocol/go-tfhe/internal/oracle/memorydb"
-	"golang.org/x/crypto/nacl/box"
-	"math/big"
 	"strconv"
 )
 
@@ -42,6 +38,7 @@ func (o MemoryDb) Decrypt(ct *api.Ciphertext) (string, error) {
 	return resultAsString, nil
 }
 
+// EthEncryptedReturn is the layout of sealed outputs, as produced by the library
 type EthEncryptedReturn struct {
 	Version        string `json:"version"`
 	Nonce          string `json:"nonce"`
@@ -49,51 +46,13 @@ type EthEncryptedReturn struct {
 	Ciphertext     string `json:"ciphertext"`
 }
 
-func encryptForUser(value *big.Int, userPublicKey []byte) ([]byte, error) {
-	ephemeralPub, ephemeralPriv, err := box.GenerateKey(rand.Reader)
-	if err != nil {
-		return nil, err
-	}
-
-	nonce := make([]byte, 24)
-	_, err = rand.Read(nonce)
-	if err != nil {
-		return nil, err
-	}
-
-	encrypted := box.Seal(nil, value.Bytes(), (*[24]byte)(nonce), (*[32]byte)(userPublicKey), ephemeralPriv)
-	////encrypted, err := box.SealAnonymous(nil, value.Bytes(), (*[32]byte)(userPublicKey), rand.Reader)
-	//if err != nil {
-	//	return nil, err
-	//}
-
-	encryptedReturnValue := EthEncryptedReturn{
-		Version:        "x25519-xsalsa20-poly1305",
-		Nonce:          base64.StdEncoding.EncodeToString(nonce),
-		EphemPublicKey: base64.StdEncoding.EncodeToString(ephemeralPub[:]),
-		Ciphertext:     base64.StdEncoding.EncodeToString(encrypted),
-	}
-
-	return json.Marshal(&encryptedReturnValue)
-}
-
-func encryptToUserKey(value *big.Int, pubKey []byte) ([]byte, error) {
-	ct, err := encryptForUser(value, pubKey)
-	if err != nil {
-		return nil, err
-	}
-
-	return ct, nil
-}
-
 func (o MemoryDb) SealOutput(ct *api.Ciphertext, pubKey []byte) (string, error) {
-	result, err := ct.Decrypt()
+	// decrypted and sealed in one go, so the plaintext never reaches Go
+	sealedResult, err := ct.SealOutput(pubKey)
 	if err != nil {
 		return "", err
 	}
 
-	sealedResult, err := encryptToUserKey(result, pubKey)
-
 	// todo (eshel) should we cache something here?
 	sealedAsString := hex.EncodeToString(sealedResult)
 
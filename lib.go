@@ -1,6 +1,7 @@
 package tfhe
 
 import (
+	"bytes"
 	"crypto/ed25519"
 	"encoding/hex"
 	"fmt"
@@ -18,6 +19,33 @@ func LoadClientKey(clientKeyBytes []byte) (bool, error) {
 	return api.DeserializeClientKey(clientKeyBytes)
 }
 
+// LoadEncryptedClientKey loads a client key file that was encrypted with passphrase
+func LoadEncryptedClientKey(clientKeyBytes []byte, passphrase []byte) (bool, error) {
+	return api.DeserializeEncryptedClientKey(clientKeyBytes, passphrase)
+}
+
+// loadClientKeyFromConfig loads api.CKS, decrypting it with the passphrase file of the config if
+// one is set. A trailing newline in the passphrase file is ignored.
+func loadClientKeyFromConfig(config *Config) error {
+	if config.ClientKeyPassphrasePath == "" {
+		_, err := LoadClientKey(api.CKS)
+		return err
+	}
+
+	passphrase, err := os.ReadFile(filepath.Join(config.HomeDir, config.ClientKeyPassphrasePath))
+	if err != nil {
+		return err
+	}
+	defer func() {
+		for i := range passphrase {
+			passphrase[i] = 0
+		}
+	}()
+
+	_, err = LoadEncryptedClientKey(api.CKS, bytes.TrimRight(passphrase, "\r\n"))
+	return err
+}
+
 // LoadPublicKey loads the public key - public key is used by the client (or the library if exposed to the user)
 // to encrypt a plaintext
 func LoadPublicKey(publicKeyBytes []byte) (bool, error) {
@@ -30,6 +58,12 @@ func LoadServerKey(serverKeyBytes []byte) (bool, error) {
 	return api.DeserializeServerKey(serverKeyBytes)
 }
 
+// ValidateKeys checks that the loaded server, client and public keys belong together, so keys from
+// different generations are caught before results silently decrypt to garbage
+func ValidateKeys() error {
+	return api.ValidateKeys()
+}
+
 // Decrypt decrypts the given Ciphertext.
 // It checks if the keys are initialized before performing decryption
 func Decrypt(ciphertext Ciphertext) (uint64, error) {
@@ -165,7 +199,7 @@ func InitTfhe(config *Config) error {
 			return err
 		}
 
-		_, err = LoadClientKey(api.CKS)
+		err = loadClientKeyFromConfig(api.GetConfig())
 		if err != nil {
 			return err
 		}
@@ -209,7 +243,7 @@ func InitTfhe(config *Config) error {
 		return err
 	}
 
-	_, err = LoadClientKey(api.CKS)
+	err = loadClientKeyFromConfig(config)
 	if err != nil {
 		return err
 	}
@@ -219,6 +253,11 @@ func InitTfhe(config *Config) error {
 		return err
 	}
 
+	err = ValidateKeys()
+	if err != nil {
+		return err
+	}
+
 	if config.OracleType == "http" {
 		if config.IsOracle {
 			api.KeyRequirePrivate, err = os.ReadFile(oraclePrivatePath)
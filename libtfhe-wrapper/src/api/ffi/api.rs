@@ -1,20 +1,40 @@
 use crate::api::ffi::error::{handle_c_error_binary, handle_c_error_default, set_error};
 use crate::api::ffi::memory::{ByteSliceView, UnmanagedVector};
+use crate::batch::{batch_cost, batch_math_operation_safe, decode_batch};
+use crate::boolean;
 use crate::cast::*;
-use crate::encryption::{decrypt_safe, encrypt_safe, expand_compressed_safe, trivial_encrypt_safe};
-use crate::error::RustError;
-use crate::keys::GlobalKeys;
+use crate::circuit::{circuit_cost, decode_circuit, evaluate_circuit_safe};
+use crate::encryption::{
+    decrypt_safe, encrypt_list_safe, encrypt_safe, expand_compressed_safe, expand_list_safe,
+    trivial_encrypt_safe,
+};
+use crate::envelope::{open, seal};
+use crate::error::{KeyKind, RustError};
+use crate::gas::{
+    cast_cost, check_gas, cmux_cost, comparison_cost, op_cost, operation_cost_safe, scalar_op_cost,
+    unary_op_cost, CostedOperation, UNLIMITED_GAS,
+};
+use crate::handles::{free_value, get_value, insert_value, INVALID_HANDLE};
+use crate::key_encryption::encrypt_key;
 use crate::keys::{
-    deserialize_client_key_safe, deserialize_public_key_safe, generate_keys_safe,
-    load_server_key_safe,
+    deserialize_client_key_safe, deserialize_encrypted_client_key_safe,
+    deserialize_public_key_safe, generate_keys_safe, generate_seeded_keys_insecure, key_info_safe,
+    load_server_key_safe, validate_keys_safe, GeneratedKeys, GlobalKeys, KeySetId,
 };
+use crate::mock::{self, is_mock_backend};
+use crate::params::ParameterProfile;
+use crate::sealing::seal_output_safe;
 
 use crate::math::{
-    op_uint16, op_uint32, op_uint8, unary_op_uint16, unary_op_uint32, unary_op_uint8,
+    check_cmux_control, op_bool, op_uint16, op_uint32, op_uint64, op_uint8, scalar_op_bool,
+    scalar_op_uint16, scalar_op_uint32, scalar_op_uint64, scalar_op_uint8, unary_op_bool,
+    unary_op_uint16, unary_op_uint32, unary_op_uint64, unary_op_uint8,
 };
 
 use std::panic::catch_unwind;
 
+use crate::value::FheValue;
+
 #[cfg(target_arch = "wasm32")]
 use tfhe::{
     generate_keys, shortint::parameters::PARAM_MESSAGE_2_CARRY_2_COMPACT_PK as KEYGEN_PARAMS,
@@ -24,9 +44,12 @@ use tfhe::{
 #[cfg(target_arch = "wasm32")]
 use crate::imports::{console_log, wavm_halt_and_set_finished};
 
+#[cfg(target_arch = "wasm32")]
+use crate::keys::DEFAULT_KEY_SET;
+
 /// cbindgen:prefix-with-name
 #[repr(i32)]
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum Op {
     Add = 0,
     Sub = 1,
@@ -49,62 +72,85 @@ pub enum Op {
 }
 
 #[repr(i32)]
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum UnaryOp {
     Not = 0,
 }
 
-impl From<u32> for UnaryOp {
-    fn from(value: u32) -> Self {
+impl TryFrom<u32> for UnaryOp {
+    type Error = RustError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
         match value {
-            0 => UnaryOp::Not,
-            _ => UnaryOp::Not,
+            0 => Ok(UnaryOp::Not),
+            _ => Err(RustError::invalid_argument(format!(
+                "unknown unary operation {}",
+                value
+            ))),
         }
     }
 }
 
-impl From<u32> for Op {
-    fn from(value: u32) -> Self {
+impl TryFrom<u32> for Op {
+    type Error = RustError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
         match value {
-            0 => Op::Add,
-            1 => Op::Sub,
-            2 => Op::Mul,
-            3 => Op::Lt,
-            4 => Op::Lte,
-            5 => Op::Div,
-            6 => Op::Gt,
-            7 => Op::Gte,
-            8 => Op::Rem,
-            9 => Op::BitAnd,
-            10 => Op::BitOr,
-            11 => Op::BitXor,
-            12 => Op::Eq,
-            13 => Op::Ne,
-            14 => Op::Min,
-            15 => Op::Max,
-            16 => Op::Shl,
-            17 => Op::Shr,
-            _ => Op::Add,
+            0 => Ok(Op::Add),
+            1 => Ok(Op::Sub),
+            2 => Ok(Op::Mul),
+            3 => Ok(Op::Lt),
+            4 => Ok(Op::Lte),
+            5 => Ok(Op::Div),
+            6 => Ok(Op::Gt),
+            7 => Ok(Op::Gte),
+            8 => Ok(Op::Rem),
+            9 => Ok(Op::BitAnd),
+            10 => Ok(Op::BitOr),
+            11 => Ok(Op::BitXor),
+            12 => Ok(Op::Eq),
+            13 => Ok(Op::Ne),
+            14 => Ok(Op::Min),
+            15 => Ok(Op::Max),
+            16 => Ok(Op::Shl),
+            17 => Ok(Op::Shr),
+            _ => Err(RustError::invalid_argument(format!(
+                "unknown operation {}",
+                value
+            ))),
         }
     }
 }
 
 /// cbindgen:prefix-with-name
 #[repr(i32)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum FheUintType {
     Uint8 = 0,
     Uint16 = 1,
     Uint32 = 2,
+    Uint64 = 3,
+    /// A single radix block that holds either 0 or 1 - not tfhe's `FheBool`, which tfhe 0.3 only
+    /// offers through the boolean module under its own parameters, so it cannot be mixed with
+    /// integer ciphertexts. Logical operations on a `Bool` bootstrap that one block only. Compact
+    /// `Bool`s are encrypted as a `Uint8` and narrowed when expanded (see `boolean`).
+    Bool = 4,
 }
 
-impl From<u32> for FheUintType {
-    fn from(value: u32) -> Self {
+impl TryFrom<u32> for FheUintType {
+    type Error = RustError;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
         match value {
-            0 => FheUintType::Uint8,
-            1 => FheUintType::Uint16,
-            2 => FheUintType::Uint32,
-            _ => FheUintType::Uint32,
+            0 => Ok(FheUintType::Uint8),
+            1 => Ok(FheUintType::Uint16),
+            2 => Ok(FheUintType::Uint32),
+            3 => Ok(FheUintType::Uint64),
+            4 => Ok(FheUintType::Bool),
+            _ => Err(RustError::invalid_argument(format!(
+                "unknown uint type {}",
+                value
+            ))),
         }
     }
 }
@@ -114,7 +160,7 @@ impl From<u32> for FheUintType {
 pub struct c_void {}
 
 pub fn write_keys_to_file(
-    cks: Vec<u8>,
+    cks: &[u8],
     cks_path: &str,
     pks: Vec<u8>,
     pks_path: &str,
@@ -139,38 +185,172 @@ pub fn write_keys_to_file(
     true
 }
 
+/// Writes freshly generated keys to their paths. The compressed server key is only written if
+/// it was generated and a path for it is given.
+fn write_generated_keys(
+    keys: GeneratedKeys,
+    cks_path: &str,
+    sks_path: &str,
+    compressed_sks_path: Option<&str>,
+    pks_path: &str,
+    cks_passphrase: Option<&[u8]>,
+) -> bool {
+    let encrypted_cks =
+        match cks_passphrase.map(|passphrase| encrypt_key(&keys.client_key, passphrase)) {
+            Some(Ok(encrypted)) => Some(encrypted),
+            Some(Err(err)) => {
+                log::error!("failed encrypting client key: {}", err);
+                return false;
+            }
+            None => None,
+        };
+
+    if !write_keys_to_file(
+        encrypted_cks.as_deref().unwrap_or(&keys.client_key),
+        cks_path,
+        keys.public_key,
+        pks_path,
+        keys.server_key,
+        sks_path,
+    ) {
+        return false;
+    }
+
+    if let (Some(compressed_sks), Some(path)) = (keys.compressed_server_key, compressed_sks_path) {
+        if let Err(e) = std::fs::write(path, compressed_sks) {
+            log::error!(
+                "failed writing compressed sks to path: {:?}. Error: {:?}",
+                path,
+                e
+            );
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Reads a path argument of the key generation calls. Null pointers are read as `None`.
+unsafe fn read_path<'a>(path: *const std::ffi::c_char) -> Result<Option<&'a str>, ()> {
+    if path.is_null() {
+        return Ok(None);
+    }
+
+    std::ffi::CStr::from_ptr(path)
+        .to_str()
+        .map(Some)
+        .map_err(|err| log::error!("key path is not valid UTF-8: {:?}", err))
+}
+
+/// Reads the paths of the key generation calls. Only the compressed server key path may be null.
+#[allow(clippy::type_complexity)]
+unsafe fn read_key_paths<'a>(
+    path_to_cks: *const std::ffi::c_char,
+    path_to_sks: *const std::ffi::c_char,
+    path_to_compressed_sks: *const std::ffi::c_char,
+    path_to_pks: *const std::ffi::c_char,
+) -> Option<(&'a str, &'a str, Option<&'a str>, &'a str)> {
+    match (
+        read_path(path_to_cks),
+        read_path(path_to_sks),
+        read_path(path_to_compressed_sks),
+        read_path(path_to_pks),
+    ) {
+        (Ok(Some(cks)), Ok(Some(sks)), Ok(compressed_sks), Ok(Some(pks))) => {
+            Some((cks, sks, compressed_sks, pks))
+        }
+        _ => None,
+    }
+}
+
+/// Generates a key set with the parameters of `profile` and writes each key to its path. The
+/// profile and the key set fingerprint are recorded in the key files, so loading them into a key
+/// set of another profile or generation fails.
+///
+/// If `path_to_compressed_sks` is not null, the server key is also written there in tfhe's
+/// compressed form, which `load_server_key` accepts as well and decompresses on load. Both files
+/// hold the same key.
+///
+/// If `cks_passphrase` is not nil, the client key is encrypted with it before it is written, and
+/// has to be loaded with `load_encrypted_client_key`.
 #[no_mangle]
 pub unsafe extern "C" fn generate_full_keys(
     path_to_cks: *const std::ffi::c_char,
     path_to_sks: *const std::ffi::c_char,
+    path_to_compressed_sks: *const std::ffi::c_char,
     path_to_pks: *const std::ffi::c_char,
+    profile: ParameterProfile,
+    cks_passphrase: ByteSliceView,
 ) -> bool {
-    let (c_str_cks, c_str_sks, c_str_pks) = unsafe {
-        (
-            std::ffi::CStr::from_ptr(path_to_cks),
-            std::ffi::CStr::from_ptr(path_to_sks),
-            std::ffi::CStr::from_ptr(path_to_pks),
-        )
+    let (cks_path, sks_path, compressed_sks_path, pks_path) = match read_key_paths(
+        path_to_cks,
+        path_to_sks,
+        path_to_compressed_sks,
+        path_to_pks,
+    ) {
+        Some(paths) => paths,
+        None => return false,
     };
 
-    let cks_path_str = match c_str_cks.to_str() {
-        Err(_) => return false,
-        Ok(s) => s,
-    };
+    let keys = generate_keys_safe(profile, compressed_sks_path.is_some());
 
-    let sks_path_str = match c_str_sks.to_str() {
-        Err(_) => return false,
-        Ok(s) => s,
-    };
+    write_generated_keys(
+        keys,
+        cks_path,
+        sks_path,
+        compressed_sks_path,
+        pks_path,
+        cks_passphrase.read(),
+    )
+}
 
-    let pks_path_str = match c_str_pks.to_str() {
-        Err(_) => return false,
-        Ok(s) => s,
+/// Like `generate_full_keys`, but derives the keys from a 32 byte `seed`, so the same seed and
+/// profile always produce bit-identical key files.
+///
+/// INSECURE - anyone who knows the seed can rebuild the client key. Only meant for devnets and
+/// CI, never for production keys. `cks_passphrase` works as in `generate_full_keys`.
+#[no_mangle]
+pub unsafe extern "C" fn generate_full_keys_from_seed_insecure(
+    path_to_cks: *const std::ffi::c_char,
+    path_to_sks: *const std::ffi::c_char,
+    path_to_compressed_sks: *const std::ffi::c_char,
+    path_to_pks: *const std::ffi::c_char,
+    profile: ParameterProfile,
+    seed: ByteSliceView,
+    cks_passphrase: ByteSliceView,
+) -> bool {
+    let (cks_path, sks_path, compressed_sks_path, pks_path) = match read_key_paths(
+        path_to_cks,
+        path_to_sks,
+        path_to_compressed_sks,
+        path_to_pks,
+    ) {
+        Some(paths) => paths,
+        None => return false,
     };
 
-    let (cks, sks, pks) = generate_keys_safe();
+    let seed = match seed.read() {
+        Some(seed) => seed,
+        None => {
+            log::error!("failed reading key generation seed");
+            return false;
+        }
+    };
 
-    write_keys_to_file(cks, cks_path_str, pks, pks_path_str, sks, sks_path_str)
+    match generate_seeded_keys_insecure(profile, compressed_sks_path.is_some(), seed) {
+        Ok(keys) => write_generated_keys(
+            keys,
+            cks_path,
+            sks_path,
+            compressed_sks_path,
+            pks_path,
+            cks_passphrase.read(),
+        ),
+        Err(err) => {
+            log::error!("failed generating seeded keys: {}", err);
+            false
+        }
+    }
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -189,16 +369,20 @@ pub unsafe extern "C" fn math_operation_wasm(
 ) -> (*const u8, u64) {
     let lhs_slice: &[u8] = unsafe { std::slice::from_raw_parts(lhs, lhs_len as usize) };
     let rhs_slice: &[u8] = unsafe { std::slice::from_raw_parts(rhs, rhs_len as usize) };
-    let op_type = Op::from(operation);
-    let fhe_type = FheUintType::from(uint_type);
+    let (op_type, fhe_type) = match (Op::try_from(operation), FheUintType::try_from(uint_type)) {
+        (Ok(op_type), Ok(fhe_type)) => (op_type, fhe_type),
+        _ => return (std::ptr::null(), 0),
+    };
 
     let err: Option<&mut UnmanagedVector> = None;
 
     let x = math_operation(
+        DEFAULT_KEY_SET,
         ByteSliceView::new(lhs_slice),
         ByteSliceView::new(rhs_slice),
         op_type,
         fhe_type,
+        UNLIMITED_GAS,
         err,
     );
     // if err.is_none() {
@@ -207,16 +391,36 @@ pub unsafe extern "C" fn math_operation_wasm(
     return (x.ptr, x.len as u64);
 }
 
+/// Performs `operation` on two enveloped ciphertexts of type `uint_type`.
 pub fn math_operation_helper(
     lhs: &[u8],
     rhs: &[u8],
     operation: Op,
     uint_type: FheUintType,
+) -> Result<Vec<u8>, RustError> {
+    if is_mock_backend() {
+        return mock::math_operation(lhs, rhs, operation, uint_type);
+    }
+
+    let lhs = open(lhs, uint_type, false)?;
+    let rhs = open(rhs, uint_type, false)?;
+
+    let result = math_operation_unsealed(lhs, rhs, operation, uint_type)?;
+    Ok(seal(&result, uint_type, false))
+}
+
+fn math_operation_unsealed(
+    lhs: &[u8],
+    rhs: &[u8],
+    operation: Op,
+    uint_type: FheUintType,
 ) -> Result<Vec<u8>, RustError> {
     let result = catch_unwind(|| match uint_type {
         FheUintType::Uint8 => op_uint8(lhs, rhs, operation),
         FheUintType::Uint16 => op_uint16(lhs, rhs, operation),
         FheUintType::Uint32 => op_uint32(lhs, rhs, operation),
+        FheUintType::Uint64 => op_uint64(lhs, rhs, operation),
+        FheUintType::Bool => op_bool(lhs, rhs, operation),
     });
 
     match result {
@@ -229,7 +433,19 @@ pub fn math_operation_helper(
     }
 }
 
+/// Reads a ciphertext argument, naming it in the error if it is missing.
+fn read_ciphertext<'a>(view: &'a ByteSliceView, what: &str) -> Result<&'a [u8], RustError> {
+    view.read().ok_or_else(|| {
+        log::error!("failed reading {}", what);
+        RustError::invalid_argument(format!("failed reading {}", what))
+    })
+}
+
 fn check_and_refresh_server_key() -> Result<(), ()> {
+    // the mock backend needs no keys
+    if is_mock_backend() {
+        return Ok(());
+    }
     if !GlobalKeys::is_server_key_set() {
         return Err(());
     }
@@ -240,35 +456,51 @@ fn check_and_refresh_server_key() -> Result<(), ()> {
 
 macro_rules! check_and_refresh_server_key_macro {
     ($err_msg:expr) => {
+        check_and_refresh_server_key_macro!($err_msg, UnmanagedVector::none())
+    };
+    ($err_msg:expr, $on_error:expr) => {
         let result = check_and_refresh_server_key();
         if result.is_err() {
-            set_error(
-                RustError::generic_error("server key must be set for math operation"),
-                $err_msg,
-            );
-            return UnmanagedVector::none();
+            set_error(RustError::key_not_loaded(KeyKind::Server), $err_msg);
+            return $on_error;
         }
     };
 }
 
+/// Performs `operation` on two encrypted values of type `uint_type`.
+///
+/// Every call that touches keys takes the `key_set` to run under (see `load_server_key`), and
+/// rejects ciphertexts produced by a different key set.
+///
+/// Like all the math, cast and cmux calls, this fails with `OutOfGas` before doing any FHE work if
+/// the cost of the operation (see `operation_cost`) exceeds `gas_budget`. Pass `UNLIMITED_GAS` to
+/// skip the check.
 #[no_mangle]
 pub unsafe extern "C" fn math_operation(
+    key_set: KeySetId,
     lhs: ByteSliceView,
     rhs: ByteSliceView,
     operation: Op,
     uint_type: FheUintType,
+    gas_budget: u64,
     err_msg: Option<&mut UnmanagedVector>,
 ) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    if let Err(err) = check_gas(op_cost(operation, uint_type), gas_budget) {
+        set_error(err, err_msg);
+        return UnmanagedVector::none();
+    }
+
     check_and_refresh_server_key_macro!(err_msg);
 
-    let (lhs_slice, rhs_slice) = match (lhs.read(), rhs.read()) {
-        (Some(k1), Some(k2)) => (k1, k2),
-        _ => {
-            log::error!("failed decoding one or more inputs");
-            set_error(
-                RustError::generic_error("failed reading input server key"),
-                err_msg,
-            );
+    let (lhs_slice, rhs_slice) = match (
+        read_ciphertext(&lhs, "lhs ciphertext"),
+        read_ciphertext(&rhs, "rhs ciphertext"),
+    ) {
+        (Ok(l), Ok(r)) => (l, r),
+        (Err(err), _) | (_, Err(err)) => {
+            set_error(err, err_msg);
             return UnmanagedVector::none();
         }
     };
@@ -279,27 +511,231 @@ pub unsafe extern "C" fn math_operation(
     UnmanagedVector::new(Some(result))
 }
 
+pub fn scalar_math_operation_helper(
+    lhs: &[u8],
+    rhs: u64,
+    operation: Op,
+    uint_type: FheUintType,
+) -> Result<Vec<u8>, RustError> {
+    if is_mock_backend() {
+        return mock::scalar_math_operation(lhs, rhs, operation, uint_type);
+    }
+
+    let lhs = open(lhs, uint_type, false)?;
+
+    let result = catch_unwind(|| match uint_type {
+        FheUintType::Uint8 => scalar_op_uint8(lhs, rhs, operation),
+        FheUintType::Uint16 => scalar_op_uint16(lhs, rhs, operation),
+        FheUintType::Uint32 => scalar_op_uint32(lhs, rhs, operation),
+        FheUintType::Uint64 => scalar_op_uint64(lhs, rhs, operation),
+        FheUintType::Bool => scalar_op_bool(lhs, rhs, operation),
+    });
+
+    match result {
+        Ok(Ok(x)) => Ok(seal(&x, uint_type, false)),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(RustError::math_panic(format!(
+            "panic in scalar math operation: {:#?}",
+            e.downcast_ref::<&str>()
+        ))),
+    }
+}
+
+/// Performs `operation` on an encrypted value and a plaintext `u64`, which is truncated to the
+/// width of `uint_type`. This is much cheaper than trivially encrypting the plaintext and calling
+/// `math_operation`.
 #[no_mangle]
-pub unsafe extern "C" fn unary_math_operation(
+pub unsafe extern "C" fn scalar_math_operation(
+    key_set: KeySetId,
     lhs: ByteSliceView,
-    operation: UnaryOp,
+    rhs: u64,
+    operation: Op,
     uint_type: FheUintType,
+    gas_budget: u64,
     err_msg: Option<&mut UnmanagedVector>,
 ) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    if let Err(err) = check_gas(scalar_op_cost(operation, uint_type), gas_budget) {
+        set_error(err, err_msg);
+        return UnmanagedVector::none();
+    }
+
     check_and_refresh_server_key_macro!(err_msg);
 
-    let lhs_slice = match lhs.read() {
-        Some(k1) => k1,
-        _ => {
-            log::debug!("Failed to decode input");
+    let lhs_slice = match read_ciphertext(&lhs, "lhs ciphertext") {
+        Ok(l) => l,
+        Err(err) => {
+            set_error(err, err_msg);
+            return UnmanagedVector::none();
+        }
+    };
+
+    let inner_result = scalar_math_operation_helper(lhs_slice, rhs, operation, uint_type);
+
+    let result = handle_c_error_binary(inner_result, err_msg);
+    UnmanagedVector::new(Some(result))
+}
+
+/// Returns the gas cost of an operation without running it, so callers can check it against
+/// their budget up front.
+///
+/// `operation` is the numeric value of the `Op` (or `UnaryOp`) for `Math`, `ScalarMath`,
+/// `UnaryMath` and `Comparison`, and is ignored otherwise. `to_type` is the target type of a
+/// `Cast` and the control type of a `Cmux`, and is ignored otherwise. An unknown `operation`
+/// costs `u64::MAX`.
+#[no_mangle]
+pub extern "C" fn operation_cost(
+    kind: CostedOperation,
+    operation: u32,
+    uint_type: FheUintType,
+    to_type: FheUintType,
+) -> u64 {
+    operation_cost_safe(kind, operation, uint_type, to_type)
+}
+
+/// Runs many independent `math_operation`s in parallel on a thread pool, all under `key_set`.
+///
+/// `operations` is a bincode encoded `Vec<BatchOperation>`: a u64 (LE) count followed by, for
+/// every operation, the u64-length-prefixed `lhs` and `rhs` ciphertexts and then `operation` and
+/// `uint_type` as u32 (LE).
+///
+/// The result is a bincode encoded `Vec<BatchResult>` in the same order: a u64 count followed by,
+/// for every operation, a u32 tag (0 = Ok, 1 = Err) and a u64-length-prefixed payload holding
+/// either the result ciphertext or the UTF-8 error message.
+///
+/// The batch costs the sum of its operations (see `operation_cost`), and fails with `OutOfGas`
+/// before any of them runs if that exceeds `gas_budget`. Entries with an unknown tag are free, as
+/// they fail without running.
+#[no_mangle]
+pub unsafe extern "C" fn batch_math_operation(
+    key_set: KeySetId,
+    operations: ByteSliceView,
+    gas_budget: u64,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    let operations_slice = match operations.read() {
+        Some(o) => o,
+        None => {
+            log::error!("batch cannot be empty");
+            set_error(
+                RustError::invalid_argument("batch cannot be empty"),
+                err_msg,
+            );
+            return UnmanagedVector::none();
+        }
+    };
+
+    let operations = match decode_batch(operations_slice) {
+        Ok(o) => o,
+        Err(err) => {
+            set_error(err, err_msg);
+            return UnmanagedVector::none();
+        }
+    };
+
+    if let Err(err) = check_gas(batch_cost(&operations), gas_budget) {
+        set_error(err, err_msg);
+        return UnmanagedVector::none();
+    }
+
+    check_and_refresh_server_key_macro!(err_msg);
+
+    let r = batch_math_operation_safe(operations);
+
+    let result = handle_c_error_binary(r, err_msg);
+    UnmanagedVector::new(Some(result))
+}
+
+/// Evaluates a circuit of FHE operations, computing independent nodes in parallel.
+///
+/// `circuit` is a bincode encoded `Circuit` (see `crate::circuit`). Each node produces a new
+/// value that later nodes and the outputs reference by id, so shared intermediates are computed
+/// only once.
+///
+/// Circuits with more than `MAX_CIRCUIT_NODES` (1024) nodes, `MAX_CIRCUIT_INPUTS` (256) inputs or
+/// `MAX_CIRCUIT_OUTPUTS` (256) outputs, or with an unknown operation or type tag, fail with
+/// `InvalidArgument` before any node is evaluated. So do circuits costing more than
+/// `gas_budget` - the sum of their nodes - with `OutOfGas`.
+///
+/// The result is a bincode encoded `Vec<CircuitOutput>`: a u64 (LE) count followed by, for every
+/// output, the u64-length-prefixed ciphertext and its `uint_type` as u32 (LE).
+#[no_mangle]
+pub unsafe extern "C" fn evaluate_circuit(
+    key_set: KeySetId,
+    circuit: ByteSliceView,
+    gas_budget: u64,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    let circuit_slice = match circuit.read() {
+        Some(c) => c,
+        None => {
+            log::error!("circuit cannot be empty");
             set_error(
-                RustError::generic_error("failed to read input server key"),
+                RustError::invalid_argument("circuit cannot be empty"),
                 err_msg,
             );
             return UnmanagedVector::none();
         }
     };
 
+    let circuit = match decode_circuit(circuit_slice) {
+        Ok(c) => c,
+        Err(err) => {
+            set_error(err, err_msg);
+            return UnmanagedVector::none();
+        }
+    };
+
+    if let Err(err) = circuit_cost(&circuit).and_then(|cost| check_gas(cost, gas_budget)) {
+        set_error(err, err_msg);
+        return UnmanagedVector::none();
+    }
+
+    check_and_refresh_server_key_macro!(err_msg);
+
+    let r = match catch_unwind(move || evaluate_circuit_safe(circuit)) {
+        Ok(x) => x,
+        Err(e) => Err(RustError::math_panic(format!(
+            "panic in circuit evaluation: {:#?}",
+            e.downcast_ref::<&str>()
+        ))),
+    };
+
+    let result = handle_c_error_binary(r, err_msg);
+    UnmanagedVector::new(Some(result))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn unary_math_operation(
+    key_set: KeySetId,
+    lhs: ByteSliceView,
+    operation: UnaryOp,
+    uint_type: FheUintType,
+    gas_budget: u64,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    if let Err(err) = check_gas(unary_op_cost(operation, uint_type), gas_budget) {
+        set_error(err, err_msg);
+        return UnmanagedVector::none();
+    }
+
+    check_and_refresh_server_key_macro!(err_msg);
+
+    let lhs_slice = match read_ciphertext(&lhs, "operand ciphertext") {
+        Ok(l) => l,
+        Err(err) => {
+            set_error(err, err_msg);
+            return UnmanagedVector::none();
+        }
+    };
+
     let result = unary_operation_helper(lhs_slice, operation, uint_type);
 
     let result = handle_c_error_binary(result, err_msg);
@@ -310,11 +746,28 @@ fn unary_operation_helper(
     lhs_slice: &[u8],
     operation: UnaryOp,
     uint_type: FheUintType,
+) -> Result<Vec<u8>, RustError> {
+    if is_mock_backend() {
+        return mock::unary_math_operation(lhs_slice, operation, uint_type);
+    }
+
+    let lhs_slice = open(lhs_slice, uint_type, false)?;
+
+    let result = unary_operation_unsealed(lhs_slice, operation, uint_type)?;
+    Ok(seal(&result, uint_type, false))
+}
+
+fn unary_operation_unsealed(
+    lhs_slice: &[u8],
+    operation: UnaryOp,
+    uint_type: FheUintType,
 ) -> Result<Vec<u8>, RustError> {
     let result_may_panic = catch_unwind(|| match uint_type {
         FheUintType::Uint8 => unary_op_uint8(lhs_slice, operation),
         FheUintType::Uint16 => unary_op_uint16(lhs_slice, operation),
         FheUintType::Uint32 => unary_op_uint32(lhs_slice, operation),
+        FheUintType::Uint64 => unary_op_uint64(lhs_slice, operation),
+        FheUintType::Bool => unary_op_bool(lhs_slice, operation),
     });
 
     let result = match result_may_panic {
@@ -330,21 +783,26 @@ fn unary_operation_helper(
 
 #[no_mangle]
 pub unsafe extern "C" fn cast_operation(
+    key_set: KeySetId,
     val: ByteSliceView,
     from_type: FheUintType,
     to_type: FheUintType,
+    gas_budget: u64,
     err_msg: Option<&mut UnmanagedVector>,
 ) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    if let Err(err) = check_gas(cast_cost(from_type, to_type), gas_budget) {
+        set_error(err, err_msg);
+        return UnmanagedVector::none();
+    }
+
     check_and_refresh_server_key_macro!(err_msg);
 
-    let val_slice = match val.read() {
-        Some(v1) => v1,
-        _ => {
-            log::error!("failed decoding an input");
-            set_error(
-                RustError::generic_error("failed reading input server key"),
-                err_msg,
-            );
+    let val_slice = match read_ciphertext(&val, "ciphertext to cast") {
+        Ok(v) => v,
+        Err(err) => {
+            set_error(err, err_msg);
             return UnmanagedVector::none();
         }
     };
@@ -370,50 +828,100 @@ fn cast_helper(
     val_slice: &[u8],
     from_type: FheUintType,
     to_type: FheUintType,
+) -> Result<Vec<u8>, RustError> {
+    if is_mock_backend() {
+        return mock::cast(val_slice, from_type, to_type);
+    }
+
+    let val_slice = open(val_slice, from_type, false)?;
+
+    let result = cast_unsealed(val_slice, from_type, to_type)?;
+    Ok(seal(&result, to_type, false))
+}
+
+fn cast_unsealed(
+    val_slice: &[u8],
+    from_type: FheUintType,
+    to_type: FheUintType,
 ) -> Result<Vec<u8>, RustError> {
     match from_type {
         FheUintType::Uint8 => match to_type {
             FheUintType::Uint8 => Ok(val_slice.to_vec()),
             FheUintType::Uint16 => cast_from_uint8_to_uint16(val_slice),
             FheUintType::Uint32 => cast_from_uint8_to_uint32(val_slice),
+            FheUintType::Uint64 => cast_from_uint8_to_uint64(val_slice),
+            FheUintType::Bool => cast_from_uint8_to_bool(val_slice),
         },
         FheUintType::Uint16 => match to_type {
             FheUintType::Uint8 => cast_from_uint16_to_uint8(val_slice),
             FheUintType::Uint16 => Ok(val_slice.to_vec()),
             FheUintType::Uint32 => cast_from_uint16_to_uint32(val_slice),
+            FheUintType::Uint64 => cast_from_uint16_to_uint64(val_slice),
+            FheUintType::Bool => cast_from_uint16_to_bool(val_slice),
         },
         FheUintType::Uint32 => match to_type {
             FheUintType::Uint8 => cast_from_uint32_to_uint8(val_slice),
             FheUintType::Uint16 => cast_from_uint32_to_uint16(val_slice),
             FheUintType::Uint32 => Ok(val_slice.to_vec()),
+            FheUintType::Uint64 => cast_from_uint32_to_uint64(val_slice),
+            FheUintType::Bool => cast_from_uint32_to_bool(val_slice),
+        },
+        FheUintType::Uint64 => match to_type {
+            FheUintType::Uint8 => cast_from_uint64_to_uint8(val_slice),
+            FheUintType::Uint16 => cast_from_uint64_to_uint16(val_slice),
+            FheUintType::Uint32 => cast_from_uint64_to_uint32(val_slice),
+            FheUintType::Uint64 => Ok(val_slice.to_vec()),
+            FheUintType::Bool => cast_from_uint64_to_bool(val_slice),
+        },
+        FheUintType::Bool => match to_type {
+            FheUintType::Uint8 => cast_from_bool_to_uint8(val_slice),
+            FheUintType::Uint16 => cast_from_bool_to_uint16(val_slice),
+            FheUintType::Uint32 => cast_from_bool_to_uint32(val_slice),
+            FheUintType::Uint64 => cast_from_bool_to_uint64(val_slice),
+            FheUintType::Bool => Ok(val_slice.to_vec()),
         },
     }
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn cmux(
+    key_set: KeySetId,
     control: ByteSliceView,
     if_true: ByteSliceView,
     if_false: ByteSliceView,
+    control_type: FheUintType,
     uint_type: FheUintType,
+    gas_budget: u64,
     err_msg: Option<&mut UnmanagedVector>,
 ) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    if let Err(err) = check_gas(cmux_cost(control_type, uint_type), gas_budget) {
+        set_error(err, err_msg);
+        return UnmanagedVector::none();
+    }
+
     check_and_refresh_server_key_macro!(err_msg);
 
-    let (control_slice, if_true_slice, if_false_slice) =
-        match (control.read(), if_true.read(), if_false.read()) {
-            (Some(k1), Some(k2), Some(k3)) => (k1, k2, k3),
-            _ => {
-                log::error!("failed decoding an input");
-                set_error(
-                    RustError::generic_error("failed reading input server key"),
-                    err_msg,
-                );
-                return UnmanagedVector::none();
-            }
-        };
+    let (control_slice, if_true_slice, if_false_slice) = match (
+        read_ciphertext(&control, "control ciphertext"),
+        read_ciphertext(&if_true, "if_true ciphertext"),
+        read_ciphertext(&if_false, "if_false ciphertext"),
+    ) {
+        (Ok(c), Ok(t), Ok(f)) => (c, t, f),
+        (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
+            set_error(err, err_msg);
+            return UnmanagedVector::none();
+        }
+    };
 
-    let result = perform_cmux(uint_type, control_slice, if_true_slice, if_false_slice);
+    let result = perform_cmux(
+        control_type,
+        uint_type,
+        control_slice,
+        if_true_slice,
+        if_false_slice,
+    );
     if result.is_err() {
         let result = handle_c_error_binary(result, err_msg);
         return UnmanagedVector::new(Some(result));
@@ -422,117 +930,302 @@ pub unsafe extern "C" fn cmux(
     UnmanagedVector::new(Some(result.unwrap()))
 }
 
-/// Perform a conditional multiplexer (cmux) operation on Fully Homomorphic Encryption (FHE) data.
-///
-/// The cmux operation selects between `if_true_slice` and `if_false_slice` based on the `control_slice`.
-/// If the `control_slice` is `1`, `if_true_slice` is selected; otherwise, `if_false_slice` is selected.
-///
-/// Basically the logic is:
-///     let mask = if control == 0 { zero() } else { max_value() };
-//      return (if_true & mask) | (if_false & !mask);
-///
-/// # Parameters
-/// - `uint_type`: The FHE uint type indicating the size and properties of the encrypted data.
-/// - `control_slice`: A slice representing the control bit in encrypted form.
-/// - `if_true_slice`: A slice representing the 'true' option in encrypted form.
-/// - `if_false_slice`: A slice representing the 'false' option in encrypted form.
-///
-/// # Returns
-/// A `Result` containing either the encrypted result of the cmux operation or a `RustError`.
-///
+/// Compares two encrypted x-bit unsigned integers and returns the result as a
+/// `FheUintType::Bool`, which can be used directly as the control of `cmux`.
+#[no_mangle]
+pub unsafe extern "C" fn comparison_operation(
+    key_set: KeySetId,
+    lhs: ByteSliceView,
+    rhs: ByteSliceView,
+    operation: Op,
+    uint_type: FheUintType,
+    gas_budget: u64,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    if let Err(err) = check_gas(comparison_cost(operation, uint_type), gas_budget) {
+        set_error(err, err_msg);
+        return UnmanagedVector::none();
+    }
+
+    check_and_refresh_server_key_macro!(err_msg);
+
+    let (lhs_slice, rhs_slice) = match (
+        read_ciphertext(&lhs, "lhs ciphertext"),
+        read_ciphertext(&rhs, "rhs ciphertext"),
+    ) {
+        (Ok(l), Ok(r)) => (l, r),
+        (Err(err), _) | (_, Err(err)) => {
+            set_error(err, err_msg);
+            return UnmanagedVector::none();
+        }
+    };
+
+    let inner_result = comparison_operation_helper(lhs_slice, rhs_slice, operation, uint_type);
+
+    let result = handle_c_error_binary(inner_result, err_msg);
+    UnmanagedVector::new(Some(result))
+}
+
+pub fn comparison_operation_helper(
+    lhs: &[u8],
+    rhs: &[u8],
+    operation: Op,
+    uint_type: FheUintType,
+) -> Result<Vec<u8>, RustError> {
+    match operation {
+        Op::Lt | Op::Lte | Op::Gt | Op::Gte | Op::Eq | Op::Ne => {}
+        _ => {
+            return Err(RustError::unsupported_operation(format!(
+                "{:?} is not a comparison operation",
+                operation
+            )))
+        }
+    }
+
+    if is_mock_backend() {
+        return mock::comparison_operation(lhs, rhs, operation, uint_type);
+    }
+
+    let lhs = open(lhs, uint_type, false)?;
+    let rhs = open(rhs, uint_type, false)?;
+
+    let result = math_operation_unsealed(lhs, rhs, operation, uint_type)?;
+
+    // comparisons produce 0 or 1 in the lowest block, which is all a bool keeps
+    let result = boolean::narrow_serialized(&result)?;
+
+    Ok(seal(&result, FheUintType::Bool, false))
+}
+
+/// Perform a conditional multiplexer (cmux) operation on Fully Homomorphic Encryption (FHE) data.
+///
+/// The cmux operation selects between `if_true_slice` and `if_false_slice` based on the `control_slice`.
+/// If the `control_slice` is `1`, `if_true_slice` is selected; otherwise, `if_false_slice` is selected.
+///
+/// Basically the logic is:
+///     let mask = if control == 0 { zero() } else { max_value() };
+//      return (if_true & mask) | (if_false & !mask);
+///
+/// The control is usually a `FheUintType::Bool` (as returned by `comparison_operation`), which is
+/// widened to `uint_type` before building the mask, see `FheValue::cmux`. A control of the same
+/// type as the options is used as is; any other control type is rejected with `TypeMismatch`, as
+/// casting it down would truncate it.
+///
+/// # Parameters
+/// - `control_type`: The FHE uint type of the control.
+/// - `uint_type`: The FHE uint type indicating the size and properties of the encrypted data.
+/// - `control_slice`: A slice representing the control bit in encrypted form.
+/// - `if_true_slice`: A slice representing the 'true' option in encrypted form.
+/// - `if_false_slice`: A slice representing the 'false' option in encrypted form.
+///
+/// # Returns
+/// A `Result` containing either the encrypted result of the cmux operation or a `RustError`.
+///
 /// # Errors
-/// Returns an error if any of the FHE operations fail.
+/// Returns an error if the control type is not accepted or any of the FHE operations fail.
 fn perform_cmux(
+    control_type: FheUintType,
     uint_type: FheUintType,
     control_slice: &[u8],
     if_true_slice: &[u8],
     if_false_slice: &[u8],
 ) -> Result<Vec<u8>, RustError> {
-    // Encrypt a 0 value as a base for creating a mask.
-    let mut mask = trivial_encrypt_safe(0, uint_type)?;
-    // Subtract the control slice from the mask, effectively creating an encryption of (0 - control).
-    mask = math_operation_helper(mask.as_slice(), control_slice, Op::Sub, uint_type)?;
+    check_cmux_control(control_type, uint_type)?;
+
+    if is_mock_backend() {
+        return mock::cmux(
+            control_type,
+            uint_type,
+            control_slice,
+            if_true_slice,
+            if_false_slice,
+        );
+    }
 
-    // Invert the mask - either 0 or 0xFFFF....
-    let inv_mask = unary_operation_helper(mask.as_slice(), UnaryOp::Not, uint_type)?;
+    let control = FheValue::from_bytes(control_slice, control_type)?;
+    let if_true = FheValue::from_bytes(if_true_slice, uint_type)?;
+    let if_false = FheValue::from_bytes(if_false_slice, uint_type)?;
 
-    // Perform a bitwise AND operation on the mask and the if_true_slice.
-    let left = math_operation_helper(mask.as_slice(), if_true_slice, Op::BitAnd, uint_type)?;
+    let result = catch_unwind(|| FheValue::cmux(&control, &if_true, &if_false));
 
-    // Perform a bitwise AND operation on the inverted mask and the if_false_slice.
-    let right = math_operation_helper(inv_mask.as_slice(), if_false_slice, Op::BitAnd, uint_type)?;
+    match result {
+        Ok(x) => x?.to_bytes(),
+        Err(e) => Err(RustError::math_panic(format!(
+            "panic in cmux operation: {:#?}",
+            e.downcast_ref::<&str>()
+        ))),
+    }
+}
 
-    // Perform a bitwise OR operation on the two intermediate results.
-    math_operation_helper(left.as_slice(), right.as_slice(), Op::BitOr, uint_type)
+/// Switches between the tfhe backend and the plaintext mock backend (see `mock`).
+///
+/// Ciphertexts created under one backend are rejected by the other. For tests only - the mock
+/// backend does not encrypt anything, so this is only exported by builds with the `mock-backend`
+/// feature.
+#[cfg(feature = "mock-backend")]
+#[no_mangle]
+pub extern "C" fn set_mock_backend(enabled: bool) {
+    mock::set_mock_backend(enabled);
 }
 
+/// Loads a server key into `key_set`, creating the key set if needed, and returns the epoch of
+/// the key (0 on error).
+///
+/// Key sets are independent of each other, so one process can serve several networks or keep an
+/// old key set around during a migration. Reloading the client or public key a key set already
+/// holds is a no-op, while loading a different one fails with `AlreadyLoaded` - unload the client
+/// key first to replace it.
+///
+/// Loading a different server key into a key set rotates it: the epoch is bumped and every call
+/// that starts afterwards runs under the new key, while calls already in flight finish under the
+/// old one. Reloading the current server key leaves the epoch unchanged.
 #[no_mangle]
 pub unsafe extern "C" fn load_server_key(
+    key_set: KeySetId,
     key: ByteSliceView,
     err_msg: Option<&mut UnmanagedVector>,
-) {
+) -> u64 {
     if let Some(server_key_slice) = key.read() {
-        let r = load_server_key_safe(server_key_slice);
+        let r = load_server_key_safe(key_set, server_key_slice);
 
         handle_c_error_default(r, err_msg)
     } else {
         log::error!("failed reading input server key");
         set_error(
-            RustError::generic_error("failed reading input server key"),
+            RustError::invalid_argument("failed reading input server key"),
             err_msg,
         );
+        0
     }
 }
 
+/// Returns the current server key epoch of `key_set`, or 0 if it has no server key.
+#[no_mangle]
+pub extern "C" fn server_key_epoch(key_set: KeySetId) -> u64 {
+    GlobalKeys::server_key_epoch(key_set).unwrap_or(0)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn load_client_key(
+    key_set: KeySetId,
     key: ByteSliceView,
     err_msg: Option<&mut UnmanagedVector>,
 ) {
     if let Some(client_key_slice) = key.read() {
-        let r = deserialize_client_key_safe(client_key_slice);
+        let r = deserialize_client_key_safe(key_set, client_key_slice);
 
         handle_c_error_default(r, err_msg)
     } else {
         log::error!("failed reading input client key");
         set_error(
-            RustError::generic_error("failed reading input client key"),
+            RustError::invalid_argument("failed reading input client key"),
             err_msg,
         );
     };
 }
 
+/// Like `load_client_key`, for a client key file encrypted with `passphrase`, e.g. by
+/// `generate_full_keys`. Fails with `DeserializationFailed` if the passphrase is wrong.
+#[no_mangle]
+pub unsafe extern "C" fn load_encrypted_client_key(
+    key_set: KeySetId,
+    key: ByteSliceView,
+    passphrase: ByteSliceView,
+    err_msg: Option<&mut UnmanagedVector>,
+) {
+    if let (Some(client_key_slice), Some(passphrase)) = (key.read(), passphrase.read()) {
+        let r = deserialize_encrypted_client_key_safe(key_set, client_key_slice, passphrase);
+
+        handle_c_error_default(r, err_msg)
+    } else {
+        log::error!("failed reading input client key or passphrase");
+        set_error(
+            RustError::invalid_argument("failed reading input client key or passphrase"),
+            err_msg,
+        );
+    };
+}
+
+/// Drops the client key of `key_set`, e.g. once a node is done decrypting. Decryptions that
+/// are already running finish first. Returns false if no client key was loaded.
+///
+/// The key set keeps the key in a buffer of its own, which is wiped here.
+#[no_mangle]
+pub extern "C" fn unload_client_key(key_set: KeySetId) -> bool {
+    GlobalKeys::unload_client_key(key_set)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn load_public_key(
+    key_set: KeySetId,
     key: ByteSliceView,
     err_msg: Option<&mut UnmanagedVector>,
 ) {
     if let Some(public_key_slice) = key.read() {
-        let r = deserialize_public_key_safe(public_key_slice);
+        let r = deserialize_public_key_safe(key_set, public_key_slice);
 
         handle_c_error_default(r, err_msg)
     } else {
         log::error!("failed reading input public key");
         set_error(
-            RustError::generic_error("failed reading public key"),
+            RustError::invalid_argument("failed reading public key"),
             err_msg,
         );
     }
 }
 
+/// Checks that the keys loaded into `key_set` belong together, by running a probe through the
+/// public, server and (if loaded) client key. Meant to be called once all keys are loaded.
+///
+/// Returns false and sets `KeyMismatch` if the keys are from different generations, or
+/// `KeyNotLoaded` if the server or public key is missing.
+#[no_mangle]
+pub unsafe extern "C" fn validate_keys(
+    key_set: KeySetId,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> bool {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    let r = validate_keys_safe().map(|_| true);
+
+    handle_c_error_default(r, err_msg)
+}
+
+/// Returns metadata about the keys loaded into `key_set`: the bincode encoded `Vec<KeyInfo>`,
+/// one entry per loaded key in server, client, public order. Each entry holds the key kind, its
+/// 8 byte fingerprint, the parameter profile it was generated with (if recorded), the supported
+/// integer widths and the serialized size, so nodes can compare keys without hashing them.
+#[no_mangle]
+pub unsafe extern "C" fn key_info(
+    key_set: KeySetId,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> UnmanagedVector {
+    let r = key_info_safe(key_set);
+
+    let result = handle_c_error_binary(r, err_msg);
+    UnmanagedVector::new(Some(result))
+}
+
 #[no_mangle]
-pub unsafe extern "C" fn get_public_key(err_msg: Option<&mut UnmanagedVector>) -> UnmanagedVector {
+pub unsafe extern "C" fn get_public_key(
+    key_set: KeySetId,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
     let public_key = GlobalKeys::get_public_key();
 
     if public_key.is_none() {
         log::error!("public key not set");
-        set_error(RustError::generic_error("public key not set"), err_msg);
+        set_error(RustError::key_not_loaded(KeyKind::Public), err_msg);
         return UnmanagedVector::none();
     }
 
-    let serialized = bincode::serialize(public_key.unwrap()).map_err(|err| {
+    let serialized = bincode::serialize(&*public_key.unwrap()).map_err(|err| {
         log::error!("failed serializing public key: {:?}", err);
-        RustError::generic_error("public key not set")
+        RustError::key_not_loaded(KeyKind::Public)
     });
 
     let result = handle_c_error_binary(serialized, err_msg);
@@ -541,10 +1234,13 @@ pub unsafe extern "C" fn get_public_key(err_msg: Option<&mut UnmanagedVector>) -
 
 #[no_mangle]
 pub unsafe extern "C" fn expand_compressed(
+    key_set: KeySetId,
     ciphertext: ByteSliceView,
     int_type: FheUintType,
     err_msg: Option<&mut UnmanagedVector>,
 ) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
     check_and_refresh_server_key_macro!(err_msg);
 
     let ciphertext_slice = ciphertext.read();
@@ -552,7 +1248,7 @@ pub unsafe extern "C" fn expand_compressed(
     if ciphertext_slice.is_none() {
         log::error!("ciphertext cannot be empty");
         set_error(
-            RustError::generic_error("ciphertext cannot be empty"),
+            RustError::invalid_argument("ciphertext cannot be empty"),
             err_msg,
         );
         return UnmanagedVector::none();
@@ -575,12 +1271,62 @@ pub unsafe extern "C" fn expand_compressed(
     UnmanagedVector::new(Some(result))
 }
 
+/// Expands a compact list made by `encrypt_list` into one expanded ciphertext per value.
+///
+/// The result is a bincode encoded `Vec<Vec<u8>>`: a u64 (LE) count followed by the
+/// u64-length-prefixed ciphertexts, in the order the values were encrypted.
+///
+/// Lists of more than `MAX_LIST_LEN` (256) values fail with `InvalidCiphertext`. Expanding costs
+/// the `Expand` cost of `int_type` per value, and fails with `OutOfGas` if the list costs more
+/// than `gas_budget`. Both are checked before any value is expanded.
+#[no_mangle]
+pub unsafe extern "C" fn expand_list(
+    key_set: KeySetId,
+    list: ByteSliceView,
+    int_type: FheUintType,
+    gas_budget: u64,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    check_and_refresh_server_key_macro!(err_msg);
+
+    let list_slice = match list.read() {
+        Some(l) => l,
+        None => {
+            log::error!("list cannot be empty");
+            set_error(RustError::invalid_argument("list cannot be empty"), err_msg);
+            return UnmanagedVector::none();
+        }
+    };
+
+    let expand_list_result = catch_unwind(|| expand_list_safe(list_slice, int_type, gas_budget));
+
+    let r = match expand_list_result {
+        Ok(Ok(expanded)) => bincode::serialize(&expanded).map_err(|err| {
+            log::error!("failed serializing expanded list: {:?}", err);
+            RustError::serialization_failed("expanded list")
+        }),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(RustError::expand_compressed_panic(format!(
+            "panic in expand list operation: {:#?}",
+            e.downcast_ref::<&str>()
+        ))),
+    };
+
+    let result = handle_c_error_binary(r, err_msg);
+    UnmanagedVector::new(Some(result))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn trivial_encrypt(
+    key_set: KeySetId,
     msg: u64,
     int_type: FheUintType,
     err_msg: Option<&mut UnmanagedVector>,
 ) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
     check_and_refresh_server_key_macro!(err_msg);
 
     let r = trivial_encrypt_safe(msg, int_type);
@@ -591,10 +1337,13 @@ pub unsafe extern "C" fn trivial_encrypt(
 
 #[no_mangle]
 pub unsafe extern "C" fn encrypt(
+    key_set: KeySetId,
     msg: u64,
     int_type: FheUintType,
     err_msg: Option<&mut UnmanagedVector>,
 ) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
     let encrypt_result = catch_unwind(||
         encrypt_safe(msg, int_type)
     );
@@ -612,18 +1361,74 @@ pub unsafe extern "C" fn encrypt(
     UnmanagedVector::new(Some(result))
 }
 
+/// Encrypts several values of one type into a single compact list, see `encrypt_list_safe`.
+///
+/// `values` holds the values as consecutive little-endian u64s.
+#[no_mangle]
+pub unsafe extern "C" fn encrypt_list(
+    key_set: KeySetId,
+    values: ByteSliceView,
+    int_type: FheUintType,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    let values: Vec<u64> = match values.read() {
+        Some(v) if v.len() % 8 == 0 => v
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect(),
+        Some(v) => {
+            log::error!("values must be little-endian u64s, got {} bytes", v.len());
+            set_error(
+                RustError::invalid_argument(format!(
+                    "values must be little-endian u64s, got {} bytes",
+                    v.len()
+                )),
+                err_msg,
+            );
+            return UnmanagedVector::none();
+        }
+        None => {
+            log::error!("values cannot be empty");
+            set_error(
+                RustError::invalid_argument("values cannot be empty"),
+                err_msg,
+            );
+            return UnmanagedVector::none();
+        }
+    };
+
+    let encrypt_result = catch_unwind(|| encrypt_list_safe(&values, int_type));
+
+    let r = match encrypt_result {
+        Ok(Ok(x)) => Ok(x),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(RustError::encrypt_panic(format!(
+            "panic in encrypt list operation: {:#?}",
+            e.downcast_ref::<&str>()
+        ))),
+    };
+
+    let result = handle_c_error_binary(r, err_msg);
+    UnmanagedVector::new(Some(result))
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn decrypt(
+    key_set: KeySetId,
     ciphertext: ByteSliceView,
     int_type: FheUintType,
     err_msg: Option<&mut UnmanagedVector>,
 ) -> u64 {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
     let ciphertext_slice = ciphertext.read();
 
     if ciphertext_slice.is_none() {
         log::error!("ciphertext cannot be empty");
         set_error(
-            RustError::generic_error("ciphertext cannot be empty"),
+            RustError::invalid_argument("ciphertext cannot be empty"),
             err_msg,
         );
         return 0;
@@ -645,6 +1450,192 @@ pub unsafe extern "C" fn decrypt(
     handle_c_error_default(r, err_msg)
 }
 
+/// Decrypts `ciphertext` and seals the value to the 32 byte X25519 `user_public_key` in a NaCl
+/// box, without the plaintext ever leaving the library. Returns the sealed value in the JSON
+/// layout of MetaMask's `eth_decrypt` (see `sealing`).
+#[no_mangle]
+pub unsafe extern "C" fn seal_output(
+    key_set: KeySetId,
+    ciphertext: ByteSliceView,
+    int_type: FheUintType,
+    user_public_key: ByteSliceView,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    let (ciphertext_slice, user_public_key_slice) =
+        match (ciphertext.read(), user_public_key.read()) {
+            (Some(ciphertext), Some(user_public_key)) => (ciphertext, user_public_key),
+            _ => {
+                log::error!("ciphertext and user public key cannot be empty");
+                set_error(
+                    RustError::invalid_argument("ciphertext and user public key cannot be empty"),
+                    err_msg,
+                );
+                return UnmanagedVector::none();
+            }
+        };
+
+    let seal_result =
+        catch_unwind(|| seal_output_safe(ciphertext_slice, int_type, user_public_key_slice));
+
+    let r = match seal_result {
+        Ok(r) => r,
+        Err(e) => Err(RustError::decrypt_panic(format!(
+            "panic in seal output operation: {:#?}",
+            e.downcast_ref::<&str>()
+        ))),
+    };
+
+    let result = handle_c_error_binary(r, err_msg);
+    UnmanagedVector::new(Some(result))
+}
+
+/// Runs a handle operation, turning a panic into a `RustError::math_panic`.
+fn handle_operation_helper<F>(f: F) -> Result<u64, RustError>
+where
+    F: FnOnce() -> Result<FheValue, RustError> + std::panic::UnwindSafe,
+{
+    match catch_unwind(f) {
+        Ok(Ok(value)) => Ok(insert_value(value)),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(RustError::math_panic(format!(
+            "panic in handle operation: {:#?}",
+            e.downcast_ref::<&str>()
+        ))),
+    }
+}
+
+/// Deserializes an expanded ciphertext and keeps it alive on the Rust side.
+///
+/// Returns a handle that can be passed to the other `handle_` functions, or `INVALID_HANDLE` (0)
+/// on error. Every handle must eventually be released with `free_handle`.
+#[no_mangle]
+pub unsafe extern "C" fn handle_from_bytes(
+    key_set: KeySetId,
+    ciphertext: ByteSliceView,
+    uint_type: FheUintType,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> u64 {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    let ciphertext_slice = match ciphertext.read() {
+        Some(c) => c,
+        None => {
+            log::error!("ciphertext cannot be empty");
+            set_error(
+                RustError::invalid_argument("ciphertext cannot be empty"),
+                err_msg,
+            );
+            return INVALID_HANDLE;
+        }
+    };
+
+    let r = handle_operation_helper(|| FheValue::from_bytes(ciphertext_slice, uint_type));
+
+    handle_c_error_default(r, err_msg)
+}
+
+/// Performs `operation` on two live ciphertexts of the same type and returns a handle to the
+/// result. The operands stay alive.
+///
+/// Like `math_operation`, this fails with `OutOfGas` before doing any FHE work if the operation
+/// costs more than `gas_budget`. The same goes for the other handle operations.
+#[no_mangle]
+pub unsafe extern "C" fn handle_math_operation(
+    key_set: KeySetId,
+    lhs: u64,
+    rhs: u64,
+    operation: Op,
+    gas_budget: u64,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> u64 {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    let cost = get_value(lhs).map(|value| op_cost(operation, value.uint_type()));
+    if let Err(err) = cost.and_then(|cost| check_gas(cost, gas_budget)) {
+        set_error(err, err_msg);
+        return INVALID_HANDLE;
+    }
+
+    check_and_refresh_server_key_macro!(err_msg, INVALID_HANDLE);
+
+    let r = handle_operation_helper(|| get_value(lhs)?.op(&get_value(rhs)?, operation));
+
+    handle_c_error_default(r, err_msg)
+}
+
+/// The handle counterpart of `scalar_math_operation`.
+#[no_mangle]
+pub unsafe extern "C" fn handle_scalar_math_operation(
+    key_set: KeySetId,
+    lhs: u64,
+    rhs: u64,
+    operation: Op,
+    gas_budget: u64,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> u64 {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    let cost = get_value(lhs).map(|value| scalar_op_cost(operation, value.uint_type()));
+    if let Err(err) = cost.and_then(|cost| check_gas(cost, gas_budget)) {
+        set_error(err, err_msg);
+        return INVALID_HANDLE;
+    }
+
+    check_and_refresh_server_key_macro!(err_msg, INVALID_HANDLE);
+
+    let r = handle_operation_helper(|| get_value(lhs)?.scalar_op(rhs, operation));
+
+    handle_c_error_default(r, err_msg)
+}
+
+/// The handle counterpart of `unary_math_operation`.
+#[no_mangle]
+pub unsafe extern "C" fn handle_unary_math_operation(
+    key_set: KeySetId,
+    lhs: u64,
+    operation: UnaryOp,
+    gas_budget: u64,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> u64 {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    let cost = get_value(lhs).map(|value| unary_op_cost(operation, value.uint_type()));
+    if let Err(err) = cost.and_then(|cost| check_gas(cost, gas_budget)) {
+        set_error(err, err_msg);
+        return INVALID_HANDLE;
+    }
+
+    check_and_refresh_server_key_macro!(err_msg, INVALID_HANDLE);
+
+    let r = handle_operation_helper(|| get_value(lhs)?.unary_op(operation));
+
+    handle_c_error_default(r, err_msg)
+}
+
+/// Serializes a live ciphertext into the same format `math_operation` returns.
+/// The handle stays alive.
+#[no_mangle]
+pub unsafe extern "C" fn handle_to_bytes(
+    key_set: KeySetId,
+    handle: u64,
+    err_msg: Option<&mut UnmanagedVector>,
+) -> UnmanagedVector {
+    let _key_set = GlobalKeys::use_key_set(key_set);
+
+    let r = get_value(handle).and_then(|value| value.to_bytes());
+
+    let result = handle_c_error_binary(r, err_msg);
+    UnmanagedVector::new(Some(result))
+}
+
+/// Releases a live ciphertext. Returns false if the handle was not live.
+#[no_mangle]
+pub extern "C" fn free_handle(handle: u64) -> bool {
+    free_value(handle)
+}
+
 // #[cfg(target_arch = "wasm32")]
 // #[no_mangle]
 // pub unsafe extern "C" fn banana() {
@@ -741,3 +1732,279 @@ pub unsafe extern "C" fn decrypt(
 //
 //     wavm_halt_and_set_finished();
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::DEFAULT_KEY_SET;
+    use crate::test_utils::setup_keys;
+
+    fn encrypt_expanded(msg: u64, uint_type: FheUintType) -> Vec<u8> {
+        expand_compressed_safe(&encrypt_safe(msg, uint_type).unwrap(), uint_type).unwrap()
+    }
+
+    #[test]
+    fn uint64_math_operations() {
+        setup_keys();
+
+        let big = (u32::MAX as u64) + 10;
+        let lhs = encrypt_expanded(big, FheUintType::Uint64);
+        let rhs = encrypt_expanded(3, FheUintType::Uint64);
+
+        let cases = [
+            (Op::Add, big + 3),
+            (Op::Sub, big - 3),
+            (Op::Mul, big.wrapping_mul(3)),
+            (Op::Div, big / 3),
+            (Op::Rem, big % 3),
+            (Op::Lt, 0),
+            (Op::Gte, 1),
+            (Op::Eq, 0),
+            (Op::Min, 3),
+            (Op::Max, big),
+            (Op::BitXor, big ^ 3),
+            (Op::Shl, big << 3),
+        ];
+
+        for (op, expected) in cases {
+            let debug = format!("{:?}", op);
+            let result = math_operation_helper(&lhs, &rhs, op, FheUintType::Uint64).unwrap();
+            assert_eq!(
+                decrypt_safe(&result, FheUintType::Uint64).unwrap(),
+                expected,
+                "{}",
+                debug
+            );
+        }
+
+        let not = unary_operation_helper(&rhs, UnaryOp::Not, FheUintType::Uint64).unwrap();
+        assert_eq!(decrypt_safe(&not, FheUintType::Uint64).unwrap(), !3u64);
+    }
+
+    #[test]
+    fn uint64_overflow_wraps() {
+        setup_keys();
+
+        let lhs = encrypt_expanded(u64::MAX, FheUintType::Uint64);
+        let rhs = encrypt_expanded(2, FheUintType::Uint64);
+
+        let result = math_operation_helper(&lhs, &rhs, Op::Add, FheUintType::Uint64).unwrap();
+        assert_eq!(decrypt_safe(&result, FheUintType::Uint64).unwrap(), 1);
+    }
+
+    #[test]
+    fn uint64_cmux() {
+        setup_keys();
+
+        let if_true = encrypt_expanded(u64::MAX - 1, FheUintType::Uint64);
+        let if_false = encrypt_expanded(7, FheUintType::Uint64);
+
+        for (control, expected) in [(1, u64::MAX - 1), (0, 7)] {
+            let control = encrypt_expanded(control, FheUintType::Uint64);
+            let result = perform_cmux(
+                FheUintType::Uint64,
+                FheUintType::Uint64,
+                &control,
+                &if_true,
+                &if_false,
+            )
+            .unwrap();
+            assert_eq!(
+                decrypt_safe(&result, FheUintType::Uint64).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn unary_operations_report_malformed_input() {
+        for uint_type in [
+            FheUintType::Uint8,
+            FheUintType::Uint16,
+            FheUintType::Uint32,
+            FheUintType::Uint64,
+        ] {
+            assert!(matches!(
+                unary_operation_unsealed(&[1, 2, 3], UnaryOp::Not, uint_type),
+                Err(RustError::DeserializationFailed { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn casts_to_and_from_uint64() {
+        setup_keys();
+
+        let wide = encrypt_expanded(0x1_0000_0102, FheUintType::Uint64);
+        for (to_type, expected) in [
+            (FheUintType::Uint8, 0x02),
+            (FheUintType::Uint16, 0x0102),
+            (FheUintType::Uint32, 0x0102),
+        ] {
+            let result = cast_helper(&wide, FheUintType::Uint64, to_type).unwrap();
+            assert_eq!(decrypt_safe(&result, to_type).unwrap(), expected);
+        }
+
+        for from_type in [FheUintType::Uint8, FheUintType::Uint16, FheUintType::Uint32] {
+            let narrow = encrypt_expanded(200, from_type);
+            let result = cast_helper(&narrow, from_type, FheUintType::Uint64).unwrap();
+            assert_eq!(decrypt_safe(&result, FheUintType::Uint64).unwrap(), 200);
+        }
+    }
+
+    #[test]
+    fn bool_logical_operations() {
+        setup_keys();
+
+        let yes = encrypt_expanded(1, FheUintType::Bool);
+        let no = encrypt_expanded(0, FheUintType::Bool);
+
+        let cases = [
+            (Op::BitAnd, &yes, &no, 0),
+            (Op::BitOr, &yes, &no, 1),
+            (Op::BitXor, &yes, &yes, 0),
+            (Op::Eq, &no, &no, 1),
+        ];
+        for (op, lhs, rhs, expected) in cases {
+            let result = math_operation_helper(lhs, rhs, op, FheUintType::Bool).unwrap();
+            assert_eq!(decrypt_safe(&result, FheUintType::Bool).unwrap(), expected);
+        }
+
+        let not = unary_operation_helper(&yes, UnaryOp::Not, FheUintType::Bool).unwrap();
+        assert_eq!(decrypt_safe(&not, FheUintType::Bool).unwrap(), 0);
+
+        assert!(math_operation_helper(&yes, &no, Op::Add, FheUintType::Bool).is_err());
+    }
+
+    #[test]
+    fn comparison_result_drives_cmux() {
+        setup_keys();
+
+        let a = encrypt_expanded(1000, FheUintType::Uint32);
+        let b = encrypt_expanded(5, FheUintType::Uint32);
+
+        let a_lt_b = comparison_operation_helper(&a, &b, Op::Lt, FheUintType::Uint32).unwrap();
+        assert_eq!(decrypt_safe(&a_lt_b, FheUintType::Bool).unwrap(), 0);
+
+        let min = perform_cmux(FheUintType::Bool, FheUintType::Uint32, &a_lt_b, &a, &b).unwrap();
+        assert_eq!(decrypt_safe(&min, FheUintType::Uint32).unwrap(), 5);
+
+        assert!(comparison_operation_helper(&a, &b, Op::Add, FheUintType::Uint32).is_err());
+    }
+
+    #[test]
+    fn cmux_rejects_wider_controls() {
+        setup_keys();
+
+        // 256 would truncate to 0 as a Uint8 control
+        let control = encrypt_expanded(256, FheUintType::Uint16);
+        let a = encrypt_expanded(1, FheUintType::Uint8);
+        let b = encrypt_expanded(2, FheUintType::Uint8);
+
+        assert!(matches!(
+            perform_cmux(FheUintType::Uint16, FheUintType::Uint8, &control, &a, &b),
+            Err(RustError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn casts_to_bool() {
+        setup_keys();
+
+        for (value, expected) in [(0, 0), (1, 1), (0x1_0000_0000, 1)] {
+            let ciphertext = encrypt_expanded(value, FheUintType::Uint64);
+            let result = cast_helper(&ciphertext, FheUintType::Uint64, FheUintType::Bool).unwrap();
+            assert_eq!(decrypt_safe(&result, FheUintType::Bool).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn scalar_operations_match_ciphertext_operations() {
+        setup_keys();
+
+        let lhs = encrypt_expanded(1234, FheUintType::Uint16);
+
+        for scalar in [0u64, 7, 0x1_0003] {
+            let trivial = trivial_encrypt_safe(scalar, FheUintType::Uint16).unwrap();
+
+            for op in 0..18u32 {
+                let op = Op::try_from(op).unwrap();
+                let scalar_result =
+                    scalar_math_operation_helper(&lhs, scalar, op, FheUintType::Uint16).unwrap();
+                let full_result =
+                    math_operation_helper(&lhs, &trivial, op, FheUintType::Uint16).unwrap();
+
+                assert_eq!(
+                    decrypt_safe(&scalar_result, FheUintType::Uint16).unwrap(),
+                    decrypt_safe(&full_result, FheUintType::Uint16).unwrap(),
+                    "{:?} with scalar {}",
+                    op,
+                    scalar
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn scalar_operations_on_uint64() {
+        setup_keys();
+
+        let big = u64::MAX - 100;
+        let lhs = encrypt_expanded(big, FheUintType::Uint64);
+
+        for (op, expected) in [(Op::Add, big + 50), (Op::Gt, 1), (Op::Rem, big % 50)] {
+            let result = scalar_math_operation_helper(&lhs, 50, op, FheUintType::Uint64).unwrap();
+            assert_eq!(
+                decrypt_safe(&result, FheUintType::Uint64).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn operations_over_budget_are_rejected() {
+        setup_keys();
+
+        let lhs = encrypt_expanded(3, FheUintType::Uint32);
+        let rhs = encrypt_expanded(4, FheUintType::Uint32);
+        let cost = operation_cost(
+            CostedOperation::Math,
+            Op::Mul as u32,
+            FheUintType::Uint32,
+            FheUintType::Uint32,
+        );
+
+        let mut err_msg = UnmanagedVector::default();
+        let result = unsafe {
+            math_operation(
+                DEFAULT_KEY_SET,
+                ByteSliceView::new(&lhs),
+                ByteSliceView::new(&rhs),
+                Op::Mul,
+                FheUintType::Uint32,
+                cost - 1,
+                Some(&mut err_msg),
+            )
+        };
+        assert!(result.is_none());
+        assert!(err_msg.is_some());
+        assert_eq!(
+            errno::errno().0,
+            crate::api::ffi::error::ErrnoValue::OutOfGas as i32
+        );
+
+        let result = unsafe {
+            math_operation(
+                DEFAULT_KEY_SET,
+                ByteSliceView::new(&lhs),
+                ByteSliceView::new(&rhs),
+                Op::Mul,
+                FheUintType::Uint32,
+                cost,
+                None,
+            )
+        };
+        let result = result.consume().unwrap();
+        assert_eq!(decrypt_safe(&result, FheUintType::Uint32).unwrap(), 12);
+    }
+}
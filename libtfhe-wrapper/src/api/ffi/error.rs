@@ -3,14 +3,55 @@ use crate::error::RustError;
 #[cfg(not(target_arch = "wasm32"))]
 use errno::{set_errno, Errno};
 
+/// Stable numeric error codes, set as errno by every FFI call alongside the error message.
+///
+/// The values are part of the FFI contract - never renumber them, only append new ones.
+///
 /// cbindgen:prefix-with-name
 #[repr(i32)]
-#[cfg(not(target_arch = "wasm32"))]
-enum ErrnoValue {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrnoValue {
     Success = 0,
     Other = 1,
-    #[allow(dead_code)]
     OutOfGas = 2,
+    KeyNotLoaded = 3,
+    DeserializationFailed = 4,
+    SerializationFailed = 5,
+    TypeMismatch = 6,
+    /// Reserved - dividing by an encrypted (or plaintext) zero is defined and yields the maximum
+    /// value of the type, so nothing returns this yet.
+    DivisionByZero = 7,
+    Panic = 8,
+    InvalidCiphertext = 9,
+    InvalidArgument = 10,
+    UnsupportedOperation = 11,
+    KeyMismatch = 12,
+    /// A different key of that kind is already loaded into the key set.
+    AlreadyLoaded = 13,
+}
+
+impl RustError {
+    pub fn errno(&self) -> ErrnoValue {
+        match self {
+            RustError::GenericError { .. } => ErrnoValue::Other,
+            RustError::MathPanic { .. }
+            | RustError::CastPanic { .. }
+            | RustError::EncryptPanic { .. }
+            | RustError::DecryptPanic { .. }
+            | RustError::TrivialEncryptPanic { .. }
+            | RustError::ExpandPanic { .. } => ErrnoValue::Panic,
+            RustError::InvalidCiphertext { .. } => ErrnoValue::InvalidCiphertext,
+            RustError::KeyNotLoaded { .. } => ErrnoValue::KeyNotLoaded,
+            RustError::DeserializationFailed { .. } => ErrnoValue::DeserializationFailed,
+            RustError::SerializationFailed { .. } => ErrnoValue::SerializationFailed,
+            RustError::TypeMismatch { .. } => ErrnoValue::TypeMismatch,
+            RustError::UnsupportedOperation { .. } => ErrnoValue::UnsupportedOperation,
+            RustError::InvalidArgument { .. } => ErrnoValue::InvalidArgument,
+            RustError::KeyMismatch { .. } => ErrnoValue::KeyMismatch,
+            RustError::AlreadyLoaded { .. } => ErrnoValue::AlreadyLoaded,
+            RustError::OutOfGas { .. } => ErrnoValue::OutOfGas,
+        }
+    }
 }
 
 pub fn clear_error() {
@@ -19,6 +60,9 @@ pub fn clear_error() {
 }
 
 pub fn set_error(err: RustError, error_msg: Option<&mut UnmanagedVector>) {
+    #[cfg(not(target_arch = "wasm32"))]
+    let errno = err.errno() as i32;
+
     if let Some(error_msg) = error_msg {
         let msg: Vec<u8> = err.to_string().into();
         *error_msg = UnmanagedVector::new(Some(msg));
@@ -28,14 +72,12 @@ pub fn set_error(err: RustError, error_msg: Option<&mut UnmanagedVector>) {
     }
 
     #[cfg(not(target_arch = "wasm32"))]
-    {
-        let errno = ErrnoValue::Other as i32;
-        set_errno(Errno(errno));
-    }
+    set_errno(Errno(errno));
 }
 
 /// If `result` is Ok, this returns the Ok value and clears [errno].
-/// Otherwise it returns a null pointer, writes the error message to `error_msg` and sets [errno].
+/// Otherwise it returns a null pointer, writes the error message to `error_msg` and sets [errno]
+/// to the error's `ErrnoValue`.
 ///
 /// [errno]: https://utcc.utoronto.ca/~cks/space/blog/programming/GoCgoErrorReturns
 #[allow(dead_code)]
@@ -56,7 +98,8 @@ pub fn handle_c_error_ptr<T>(
 }
 
 /// If `result` is Ok, this returns the binary representation of the Ok value and clears [errno].
-/// Otherwise it returns an empty vector, writes the error message to `error_msg` and sets [errno].
+/// Otherwise it returns an empty vector, writes the error message to `error_msg` and sets [errno]
+/// to the error's `ErrnoValue`.
 ///
 /// [errno]: https://utcc.utoronto.ca/~cks/space/blog/programming/GoCgoErrorReturns
 pub fn handle_c_error_binary<T>(
@@ -79,7 +122,8 @@ where
 }
 
 /// If `result` is Ok, this returns the Ok value and clears [errno].
-/// Otherwise it returns the default value, writes the error message to `error_msg` and sets [errno].
+/// Otherwise it returns the default value, writes the error message to `error_msg` and sets [errno]
+/// to the error's `ErrnoValue`.
 ///
 /// [errno]: https://utcc.utoronto.ca/~cks/space/blog/programming/GoCgoErrorReturns
 pub fn handle_c_error_default<T>(
@@ -100,3 +144,44 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::KeyKind;
+
+    #[test]
+    fn errors_map_to_stable_codes() {
+        let cases = [
+            (RustError::generic_error("x"), 1),
+            (RustError::out_of_gas(10, 5), 2),
+            (RustError::key_not_loaded(KeyKind::Server), 3),
+            (RustError::deserialization_failed("lhs", "bad"), 4),
+            (RustError::serialization_failed("result"), 5),
+            (RustError::type_mismatch("x"), 6),
+            (RustError::math_panic("x"), 8),
+            (RustError::decrypt_panic("x"), 8),
+            (RustError::invalid_ciphertext("x"), 9),
+            (RustError::invalid_argument("x"), 10),
+            (RustError::unsupported_operation("x"), 11),
+            (RustError::key_mismatch("x"), 12),
+            (RustError::already_loaded(KeyKind::Public, "x"), 13),
+        ];
+
+        for (err, code) in cases {
+            assert_eq!(err.errno() as i32, code, "{}", err);
+        }
+    }
+
+    #[test]
+    fn set_error_writes_message_and_code() {
+        let mut msg = UnmanagedVector::default();
+        set_error(RustError::key_not_loaded(KeyKind::Client), Some(&mut msg));
+
+        let msg = String::from_utf8(msg.consume().unwrap()).unwrap();
+        assert_eq!(msg, "The client key is not loaded");
+
+        #[cfg(not(target_arch = "wasm32"))]
+        assert_eq!(errno::errno().0, ErrnoValue::KeyNotLoaded as i32);
+    }
+}
@@ -0,0 +1,235 @@
+use once_cell::sync::OnceCell;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::api::{math_operation_helper, FheUintType, Op};
+use crate::error::RustError;
+use crate::gas::op_cost;
+use crate::keys::GlobalKeys;
+
+/// A single entry of a `batch_math_operation` call.
+///
+/// `operation` and `uint_type` hold the numeric values of `Op` and `FheUintType`, so the batch
+/// can be encoded without knowing anything about the Rust types.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BatchOperation {
+    pub lhs: Vec<u8>,
+    pub rhs: Vec<u8>,
+    pub operation: u32,
+    pub uint_type: u32,
+}
+
+/// The outcome of a single `BatchOperation` - either the serialized result or an error message.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum BatchResult {
+    Ok(Vec<u8>),
+    Err(String),
+}
+
+/// Thread pool used for batches and circuits. Workers install a server key the first time they
+/// run an operation under a key set (or epoch), so later `refresh_server_key_for_thread` calls
+/// are a cheap lookup rather than a key clone.
+static BATCH_POOL: OnceCell<ThreadPool> = OnceCell::new();
+
+pub(crate) fn batch_pool() -> Result<&'static ThreadPool, RustError> {
+    BATCH_POOL.get_or_try_init(|| {
+        ThreadPoolBuilder::new()
+            .thread_name(|i| format!("tfhe-batch-{}", i))
+            .build()
+            .map_err(|err| {
+                log::error!("failed building batch thread pool: {:?}", err);
+                RustError::generic_error("failed building batch thread pool")
+            })
+    })
+}
+
+/// Runs every operation of the batch in parallel.
+///
+/// The results are returned in the same order as the operations. A failing operation does not
+/// affect the others - its slot holds a `BatchResult::Err` instead. This includes entries with an
+/// unknown `operation` or `uint_type`, which are rejected rather than run as something else.
+///
+/// Every operation runs under the key set and server key pinned on the calling thread, so a key
+/// rotation in the middle of a batch does not mix epochs.
+pub fn run_batch(operations: Vec<BatchOperation>) -> Result<Vec<BatchResult>, RustError> {
+    let pool = batch_pool()?;
+    let key_set = GlobalKeys::pinned_key_set();
+
+    let results = pool.install(|| {
+        operations
+            .into_par_iter()
+            .map(|op| {
+                let _key_set = GlobalKeys::use_pinned_key_set(key_set.clone());
+                let result = Op::try_from(op.operation).and_then(|operation| {
+                    let uint_type = FheUintType::try_from(op.uint_type)?;
+                    math_operation_helper(&op.lhs, &op.rhs, operation, uint_type)
+                });
+
+                match result {
+                    Ok(result) => BatchResult::Ok(result),
+                    Err(err) => BatchResult::Err(err.to_string()),
+                }
+            })
+            .collect()
+    });
+
+    Ok(results)
+}
+
+/// Decodes a bincode encoded `Vec<BatchOperation>`.
+pub fn decode_batch(encoded_operations: &[u8]) -> Result<Vec<BatchOperation>, RustError> {
+    bincode::deserialize(encoded_operations).map_err(|err| {
+        log::error!("failed deserializing batch: {:?}", err);
+        RustError::deserialization_failed("batch", err)
+    })
+}
+
+/// The gas cost of the whole batch - the sum of its operations. Entries with an unknown
+/// `operation` or `uint_type` are free, as they fail without running.
+pub fn batch_cost(operations: &[BatchOperation]) -> u64 {
+    operations
+        .iter()
+        .map(|op| {
+            match (
+                Op::try_from(op.operation),
+                FheUintType::try_from(op.uint_type),
+            ) {
+                (Ok(operation), Ok(uint_type)) => op_cost(operation, uint_type),
+                _ => 0,
+            }
+        })
+        .fold(0, u64::saturating_add)
+}
+
+/// Runs the batch and returns the bincode encoded `Vec<BatchResult>`.
+pub fn batch_math_operation_safe(operations: Vec<BatchOperation>) -> Result<Vec<u8>, RustError> {
+    let results = run_batch(operations)?;
+
+    bincode::serialize(&results).map_err(|err| {
+        log::error!("failed serializing batch results: {:?}", err);
+        RustError::serialization_failed("batch results")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::{decrypt_safe, encrypt_safe, expand_compressed_safe};
+    use crate::test_utils::setup_keys;
+
+    fn encrypt_expanded(msg: u64, uint_type: FheUintType) -> Vec<u8> {
+        expand_compressed_safe(&encrypt_safe(msg, uint_type).unwrap(), uint_type).unwrap()
+    }
+
+    #[test]
+    fn batch_results_keep_their_order() {
+        setup_keys();
+
+        let mut operations = vec![];
+        for i in 0..8u64 {
+            operations.push(BatchOperation {
+                lhs: encrypt_expanded(i, FheUintType::Uint16),
+                rhs: encrypt_expanded(100, FheUintType::Uint16),
+                operation: Op::Add as u32,
+                uint_type: FheUintType::Uint16 as u32,
+            });
+        }
+        // a broken entry in the middle must not affect its neighbours
+        operations.insert(
+            4,
+            BatchOperation {
+                lhs: vec![1, 2, 3],
+                rhs: vec![4, 5, 6],
+                operation: Op::Mul as u32,
+                uint_type: FheUintType::Uint16 as u32,
+            },
+        );
+
+        // as must unknown operations and types, instead of running as an Add or on a Uint32
+        let valid = encrypt_expanded(1, FheUintType::Uint16);
+        for (operation, uint_type) in [(18, FheUintType::Uint16 as u32), (Op::Add as u32, 5)] {
+            operations.push(BatchOperation {
+                lhs: valid.clone(),
+                rhs: valid.clone(),
+                operation,
+                uint_type,
+            });
+        }
+
+        let encoded = bincode::serialize(&operations).unwrap();
+        let results: Vec<BatchResult> = bincode::deserialize(
+            &batch_math_operation_safe(decode_batch(&encoded).unwrap()).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(results.len(), 11);
+        for index in [4, 9, 10] {
+            assert!(matches!(results[index], BatchResult::Err(_)));
+        }
+
+        let expected = (0..8u64).map(|i| i + 100);
+        let ok_results = results.iter().filter_map(|r| match r {
+            BatchResult::Ok(ciphertext) => Some(ciphertext),
+            BatchResult::Err(_) => None,
+        });
+        for (ciphertext, expected) in ok_results.zip(expected) {
+            assert_eq!(
+                decrypt_safe(ciphertext, FheUintType::Uint16).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn batch_cost_sums_its_operations() {
+        let operation = |operation: u32, uint_type: u32| BatchOperation {
+            lhs: vec![],
+            rhs: vec![],
+            operation,
+            uint_type,
+        };
+
+        let operations = vec![
+            operation(Op::Add as u32, FheUintType::Uint8 as u32),
+            operation(Op::Mul as u32, FheUintType::Uint64 as u32),
+            operation(18, FheUintType::Uint8 as u32),
+        ];
+
+        assert_eq!(
+            batch_cost(&operations),
+            op_cost(Op::Add, FheUintType::Uint8) + op_cost(Op::Mul, FheUintType::Uint64)
+        );
+        assert_eq!(batch_cost(&[]), 0);
+    }
+
+    #[test]
+    fn free_entries_are_never_run() {
+        setup_keys();
+
+        let valid = encrypt_expanded(1, FheUintType::Uint8);
+        let operations: Vec<_> = [(18, FheUintType::Uint8 as u32), (Op::Add as u32, 5)]
+            .into_iter()
+            .map(|(operation, uint_type)| BatchOperation {
+                lhs: valid.clone(),
+                rhs: valid.clone(),
+                operation,
+                uint_type,
+            })
+            .collect();
+
+        // unknown entries are priced at 0 only because they are rejected without running
+        assert_eq!(batch_cost(&operations), 0);
+        let results = run_batch(operations).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| matches!(r, BatchResult::Err(_))));
+    }
+
+    #[test]
+    fn malformed_batch_is_rejected() {
+        assert!(matches!(
+            decode_batch(&[0xff; 3]),
+            Err(RustError::DeserializationFailed { .. })
+        ));
+    }
+}
@@ -0,0 +1,179 @@
+//! `Bool`s are stored as a single radix block.
+//!
+//! tfhe 0.3 only offers `FheBool` through its boolean module, under keys of its own, so it cannot
+//! be mixed with integer ciphertexts. A `Bool` is instead an `FheUint8` whose radix ciphertext
+//! keeps only its lowest block, which holds 0 or 1. tfhe's integer operations work on however many
+//! blocks a ciphertext has, so a logical operation on a `Bool` bootstraps a single block rather
+//! than every block of a `Uint8`, and the ciphertext is as much smaller.
+//!
+//! The high level API keeps the blocks private, so they are reached through the serialized form
+//! (see `serialization::HighLevelCiphertext`), which costs a bincode round trip but no bootstrap.
+//! Values are narrowed where they become `Bool`s - comparisons, casts and the expansion of a
+//! compact `Bool` - and widened where a `Bool` is cast to, or selects between, wider values.
+
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tfhe::integer::{IntegerCiphertext, RadixCiphertextBig, RadixCiphertextSmall};
+use tfhe::prelude::*;
+use tfhe::FheUint8;
+
+use crate::error::RustError;
+use crate::serialization::{ciphertext_options, HighLevelCiphertext, RadixCiphertextDyn};
+
+/// Keeps only the lowest block of `value`, which must hold either 0 or 1.
+pub(crate) fn narrow<T: Serialize>(value: &T) -> Result<FheUint8, RustError> {
+    from_radix(lowest_block(to_radix(value)?))
+}
+
+/// `narrow` on a serialized (but not enveloped) value, returning the serialized `Bool`.
+pub(crate) fn narrow_serialized(payload: &[u8]) -> Result<Vec<u8>, RustError> {
+    serialize_radix(lowest_block(deserialize_radix(payload)?))
+}
+
+/// Widens `value` to the type of `zero`, a trivial encryption of 0, by replacing its lowest block.
+/// The other blocks of `zero` are left as they are, so this never bootstraps.
+pub(crate) fn widen<T: Serialize + DeserializeOwned>(
+    value: &FheUint8,
+    zero: &T,
+) -> Result<T, RustError> {
+    macro_rules! splice {
+        ($radix:ident, $low:expr, $high:expr) => {
+            $radix::from_blocks(
+                $low.blocks()
+                    .iter()
+                    .take(1)
+                    .chain($high.blocks().iter().skip(1))
+                    .cloned()
+                    .collect(),
+            )
+        };
+    }
+
+    let widened = match (to_radix(value)?, to_radix(zero)?) {
+        (RadixCiphertextDyn::Big(low), RadixCiphertextDyn::Big(high)) => {
+            RadixCiphertextDyn::Big(splice!(RadixCiphertextBig, low, high))
+        }
+        (RadixCiphertextDyn::Small(low), RadixCiphertextDyn::Small(high)) => {
+            RadixCiphertextDyn::Small(splice!(RadixCiphertextSmall, low, high))
+        }
+        _ => {
+            log::error!("cannot widen a bool encrypted under a different key");
+            return Err(RustError::type_mismatch(
+                "bool and target ciphertexts use different keys",
+            ));
+        }
+    };
+
+    from_radix(widened)
+}
+
+/// The `Bool` that is 1 when `value` is non-zero. `zero` is a trivial encryption of 0.
+pub(crate) fn is_non_zero<T: FheEq<T, Output = T> + Serialize>(
+    value: &T,
+    zero: T,
+) -> Result<FheUint8, RustError> {
+    narrow(&value.ne(zero))
+}
+
+/// Trivially encrypts `value` as a `Bool`. The caller is responsible for installing the server
+/// key on the current thread.
+pub(crate) fn trivial(value: bool) -> Result<FheUint8, RustError> {
+    narrow(&FheUint8::encrypt_trivial(value as u8))
+}
+
+fn lowest_block(radix: RadixCiphertextDyn) -> RadixCiphertextDyn {
+    match radix {
+        RadixCiphertextDyn::Big(radix) => RadixCiphertextDyn::Big(RadixCiphertextBig::from_blocks(
+            radix.blocks().iter().take(1).cloned().collect(),
+        )),
+        RadixCiphertextDyn::Small(radix) => RadixCiphertextDyn::Small(
+            RadixCiphertextSmall::from_blocks(radix.blocks().iter().take(1).cloned().collect()),
+        ),
+    }
+}
+
+fn to_radix<T: Serialize>(value: &T) -> Result<RadixCiphertextDyn, RustError> {
+    let serialized = bincode::serialize(value).map_err(|err| {
+        log::error!("failed serializing value: {:?}", err);
+        RustError::serialization_failed("bool blocks")
+    })?;
+
+    deserialize_radix(&serialized)
+}
+
+fn from_radix<T: DeserializeOwned>(radix: RadixCiphertextDyn) -> Result<T, RustError> {
+    ciphertext_options()
+        .deserialize(&serialize_radix(radix)?)
+        .map_err(|err| {
+            log::error!("failed rebuilding a value from its blocks: {:?}", err);
+            RustError::deserialization_failed("bool blocks", err)
+        })
+}
+
+fn deserialize_radix(payload: &[u8]) -> Result<RadixCiphertextDyn, RustError> {
+    let radix: HighLevelCiphertext<RadixCiphertextDyn> =
+        ciphertext_options().deserialize(payload).map_err(|err| {
+            log::error!("failed reading the blocks of a value: {:?}", err);
+            RustError::deserialization_failed("bool blocks", err)
+        })?;
+
+    Ok(radix.ciphertext)
+}
+
+fn serialize_radix(radix: RadixCiphertextDyn) -> Result<Vec<u8>, RustError> {
+    bincode::serialize(&HighLevelCiphertext { ciphertext: radix }).map_err(|err| {
+        log::error!("failed serializing blocks: {:?}", err);
+        RustError::serialization_failed("bool blocks")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::FheUintType;
+    use crate::encryption::{decrypt_safe, encrypt_safe, expand_compressed_safe};
+    use crate::envelope::{open, seal};
+    use crate::keys::GlobalKeys;
+    use crate::serialization::deserialize_fhe_bool;
+    use crate::test_utils::setup_keys;
+    use tfhe::FheUint16;
+
+    fn expanded(msg: u64, uint_type: FheUintType) -> Vec<u8> {
+        expand_compressed_safe(&encrypt_safe(msg, uint_type).unwrap(), uint_type).unwrap()
+    }
+
+    fn decrypt<T: Serialize>(value: &T, uint_type: FheUintType) -> u64 {
+        decrypt_safe(
+            &seal(&bincode::serialize(value).unwrap(), uint_type, false),
+            uint_type,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn bools_are_a_single_block() {
+        setup_keys();
+        GlobalKeys::refresh_server_key_for_thread();
+
+        let yes = expanded(7, FheUintType::Bool);
+        assert!(yes.len() < expanded(1, FheUintType::Uint8).len());
+        assert_eq!(decrypt_safe(&yes, FheUintType::Bool).unwrap(), 1);
+
+        let yes = deserialize_fhe_bool(open(&yes, FheUintType::Bool, false).unwrap()).unwrap();
+        let no = trivial(false).unwrap();
+        assert_eq!(decrypt(&(yes.clone() & no.clone()), FheUintType::Bool), 0);
+        assert_eq!(decrypt(&(yes.clone() ^ no), FheUintType::Bool), 1);
+
+        let widened = widen(&yes, &FheUint16::encrypt_trivial(0u16)).unwrap();
+        let sum = widened + FheUint16::encrypt_trivial(300u16);
+        assert_eq!(decrypt(&sum, FheUintType::Uint16), 301);
+
+        let non_zero = is_non_zero(
+            &FheUint16::encrypt_trivial(256u16),
+            FheUint16::encrypt_trivial(0u16),
+        )
+        .unwrap();
+        assert_eq!(decrypt(&non_zero, FheUintType::Bool), 1);
+    }
+}
@@ -1,7 +1,9 @@
 #[macro_use]
 use crate::error::RustError;
+use crate::boolean;
 use crate::serialization::*;
-use tfhe::{FheUint16, FheUint32, FheUint8};
+use tfhe::prelude::*;
+use tfhe::{FheUint16, FheUint32, FheUint64, FheUint8};
 
 macro_rules! define_cast_fn {
     ($func_name:ident, $deserialize_func:ident, $from_type:ty, $to_type:ty) => {
@@ -12,15 +14,12 @@ macro_rules! define_cast_fn {
                     let out = <$to_type>::cast_from(v);
                     bincode::serialize(&out).map_err(|err| {
                         log::error!("failed serializing value: {:?}", err);
-                        RustError::generic_error(format!("failed serializing result for cast"))
+                        RustError::serialization_failed("cast result")
                     })
                 }
                 Err(e) => {
                     log::error!("failed deserializing value: {:?}", e);
-                    Err(RustError::generic_error(format!(
-                        "failed deserializing value: {:?}",
-                        e
-                    )))
+                    Err(RustError::deserialization_failed("value", e))
                 }
             }
         }
@@ -41,26 +40,135 @@ define_cast_fn!(
     FheUint32
 );
 define_cast_fn!(
-    cast_from_uint16_to_uint8,
+    cast_from_uint8_to_uint64,
     deserialize_fhe_uint8,
     FheUint8,
+    FheUint64
+);
+define_cast_fn!(
+    cast_from_uint16_to_uint8,
+    deserialize_fhe_uint16,
+    FheUint16,
     FheUint8
 );
 define_cast_fn!(
     cast_from_uint16_to_uint32,
-    deserialize_fhe_uint8,
-    FheUint8,
+    deserialize_fhe_uint16,
+    FheUint16,
     FheUint32
 );
+define_cast_fn!(
+    cast_from_uint16_to_uint64,
+    deserialize_fhe_uint16,
+    FheUint16,
+    FheUint64
+);
 define_cast_fn!(
     cast_from_uint32_to_uint8,
-    deserialize_fhe_uint8,
-    FheUint8,
+    deserialize_fhe_uint32,
+    FheUint32,
     FheUint8
 );
 define_cast_fn!(
     cast_from_uint32_to_uint16,
+    deserialize_fhe_uint32,
+    FheUint32,
+    FheUint16
+);
+define_cast_fn!(
+    cast_from_uint32_to_uint64,
+    deserialize_fhe_uint32,
+    FheUint32,
+    FheUint64
+);
+define_cast_fn!(
+    cast_from_uint64_to_uint8,
+    deserialize_fhe_uint64,
+    FheUint64,
+    FheUint8
+);
+define_cast_fn!(
+    cast_from_uint64_to_uint16,
+    deserialize_fhe_uint64,
+    FheUint64,
+    FheUint16
+);
+define_cast_fn!(
+    cast_from_uint64_to_uint32,
+    deserialize_fhe_uint64,
+    FheUint64,
+    FheUint32
+);
+
+/// Casts an encrypted integer to an encrypted boolean, which is 1 for any non-zero value.
+macro_rules! define_cast_to_bool_fn {
+    ($func_name:ident, $deserialize_func:ident, $from_type:ty, $zero:expr) => {
+        pub(crate) fn $func_name(val: &[u8]) -> Result<Vec<u8>, RustError> {
+            match $deserialize_func(val, false) {
+                Ok(v) => {
+                    let out = boolean::is_non_zero(&v, <$from_type>::encrypt_trivial($zero))?;
+                    bincode::serialize(&out).map_err(|err| {
+                        log::error!("failed serializing value: {:?}", err);
+                        RustError::serialization_failed("cast result")
+                    })
+                }
+                Err(e) => {
+                    log::error!("failed deserializing value: {:?}", e);
+                    Err(RustError::deserialization_failed("value", e))
+                }
+            }
+        }
+    };
+}
+
+define_cast_to_bool_fn!(
+    cast_from_uint8_to_bool,
     deserialize_fhe_uint8,
     FheUint8,
-    FheUint16
+    0u8
+);
+define_cast_to_bool_fn!(
+    cast_from_uint16_to_bool,
+    deserialize_fhe_uint16,
+    FheUint16,
+    0u16
 );
+define_cast_to_bool_fn!(
+    cast_from_uint32_to_bool,
+    deserialize_fhe_uint32,
+    FheUint32,
+    0u32
+);
+define_cast_to_bool_fn!(
+    cast_from_uint64_to_bool,
+    deserialize_fhe_uint64,
+    FheUint64,
+    0u64
+);
+
+/// Casts an encrypted boolean to an encrypted integer by padding its single block with trivial
+/// zeros (see `boolean::widen`), which needs no bootstrap.
+macro_rules! define_cast_from_bool_fn {
+    ($func_name:ident, $to_type:ty, $zero:expr) => {
+        pub(crate) fn $func_name(val: &[u8]) -> Result<Vec<u8>, RustError> {
+            match deserialize_fhe_bool(val) {
+                Ok(v) => {
+                    let out = boolean::widen(&v, &<$to_type>::encrypt_trivial($zero))?;
+                    bincode::serialize(&out).map_err(|err| {
+                        log::error!("failed serializing value: {:?}", err);
+                        RustError::serialization_failed("cast result")
+                    })
+                }
+                Err(e) => {
+                    log::error!("failed deserializing value: {:?}", e);
+                    Err(RustError::deserialization_failed("value", e))
+                }
+            }
+        }
+    };
+}
+
+define_cast_from_bool_fn!(cast_from_bool_to_uint8, FheUint8, 0u8);
+define_cast_from_bool_fn!(cast_from_bool_to_uint16, FheUint16, 0u16);
+define_cast_from_bool_fn!(cast_from_bool_to_uint32, FheUint32, 0u32);
+define_cast_from_bool_fn!(cast_from_bool_to_uint64, FheUint64, 0u64);
@@ -0,0 +1,594 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::api::{FheUintType, Op, UnaryOp};
+use crate::batch::batch_pool;
+use crate::error::RustError;
+use crate::gas::{
+    cast_cost, cmux_cost, op_cost, scalar_op_cost, trivial_encrypt_cost, unary_op_cost,
+};
+use crate::keys::GlobalKeys;
+use crate::mock::is_mock_backend;
+use crate::value::FheValue;
+
+/// The most nodes a circuit may have. Larger circuits are rejected before anything is evaluated.
+pub const MAX_CIRCUIT_NODES: usize = 1024;
+
+/// The most inputs a circuit may have.
+pub const MAX_CIRCUIT_INPUTS: usize = 256;
+
+/// The most outputs a circuit may request.
+pub const MAX_CIRCUIT_OUTPUTS: usize = 256;
+
+/// An input ciphertext of a circuit. `uint_type` holds the numeric value of `FheUintType`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CircuitInput {
+    pub ciphertext: Vec<u8>,
+    pub uint_type: u32,
+}
+
+/// A single gate of a circuit.
+///
+/// Operands are value ids: the inputs take ids `0..inputs.len()`, and node `i` produces the value
+/// with id `inputs.len() + i`. A node may only reference values produced before it, which keeps the
+/// graph acyclic and the node list in topological order.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum CircuitNode {
+    /// A trivially encrypted constant.
+    Constant {
+        value: u64,
+        uint_type: u32,
+    },
+    Op {
+        operation: u32,
+        lhs: u32,
+        rhs: u32,
+    },
+    ScalarOp {
+        operation: u32,
+        lhs: u32,
+        rhs: u64,
+    },
+    UnaryOp {
+        operation: u32,
+        operand: u32,
+    },
+    Cast {
+        operand: u32,
+        to_type: u32,
+    },
+    Cmux {
+        control: u32,
+        if_true: u32,
+        if_false: u32,
+    },
+}
+
+/// A graph of FHE operations, evaluated by `evaluate_circuit_safe`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Circuit {
+    pub inputs: Vec<CircuitInput>,
+    pub nodes: Vec<CircuitNode>,
+    /// Value ids to return, in order. The same id may appear more than once.
+    pub outputs: Vec<u32>,
+}
+
+/// A serialized output ciphertext of a circuit.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CircuitOutput {
+    pub ciphertext: Vec<u8>,
+    pub uint_type: u32,
+}
+
+impl CircuitNode {
+    fn operands(&self) -> Vec<u32> {
+        match *self {
+            CircuitNode::Constant { .. } => vec![],
+            CircuitNode::Op { lhs, rhs, .. } => vec![lhs, rhs],
+            CircuitNode::ScalarOp { lhs, .. } => vec![lhs],
+            CircuitNode::UnaryOp { operand, .. } => vec![operand],
+            CircuitNode::Cast { operand, .. } => vec![operand],
+            CircuitNode::Cmux {
+                control,
+                if_true,
+                if_false,
+            } => vec![control, if_true, if_false],
+        }
+    }
+
+    /// Checks that every operation and type tag of the node is known.
+    fn check_tags(&self) -> Result<(), RustError> {
+        match *self {
+            CircuitNode::Constant { uint_type, .. } => FheUintType::try_from(uint_type).map(drop),
+            CircuitNode::Op { operation, .. } | CircuitNode::ScalarOp { operation, .. } => {
+                Op::try_from(operation).map(drop)
+            }
+            CircuitNode::UnaryOp { operation, .. } => UnaryOp::try_from(operation).map(drop),
+            CircuitNode::Cast { to_type, .. } => FheUintType::try_from(to_type).map(drop),
+            CircuitNode::Cmux { .. } => Ok(()),
+        }
+    }
+
+    /// The gas cost of the node and the type of the value it produces, given the types of the
+    /// values before it.
+    fn cost(&self, types: &[FheUintType]) -> Result<(u64, FheUintType), RustError> {
+        let value_type = |id: u32| types[id as usize];
+
+        let priced = match *self {
+            CircuitNode::Constant { uint_type, .. } => {
+                let uint_type = FheUintType::try_from(uint_type)?;
+                (trivial_encrypt_cost(uint_type), uint_type)
+            }
+            CircuitNode::Op { operation, lhs, .. } => {
+                let uint_type = value_type(lhs);
+                (op_cost(Op::try_from(operation)?, uint_type), uint_type)
+            }
+            CircuitNode::ScalarOp { operation, lhs, .. } => {
+                let uint_type = value_type(lhs);
+                (
+                    scalar_op_cost(Op::try_from(operation)?, uint_type),
+                    uint_type,
+                )
+            }
+            CircuitNode::UnaryOp { operation, operand } => {
+                let uint_type = value_type(operand);
+                (
+                    unary_op_cost(UnaryOp::try_from(operation)?, uint_type),
+                    uint_type,
+                )
+            }
+            CircuitNode::Cast { operand, to_type } => {
+                let to_type = FheUintType::try_from(to_type)?;
+                (cast_cost(value_type(operand), to_type), to_type)
+            }
+            CircuitNode::Cmux {
+                control, if_true, ..
+            } => {
+                let uint_type = value_type(if_true);
+                (cmux_cost(value_type(control), uint_type), uint_type)
+            }
+        };
+
+        Ok(priced)
+    }
+
+    fn evaluate(&self, values: &[Option<FheValue>]) -> Result<FheValue, RustError> {
+        // operands and tags are validated before evaluation, and operands always belong to an
+        // earlier level
+        let value = |id: u32| values[id as usize].as_ref().unwrap();
+
+        match *self {
+            CircuitNode::Constant { value, uint_type } => {
+                FheValue::trivial(value, FheUintType::try_from(uint_type)?)
+            }
+            CircuitNode::Op {
+                operation,
+                lhs,
+                rhs,
+            } => value(lhs).op(value(rhs), Op::try_from(operation)?),
+            CircuitNode::ScalarOp {
+                operation,
+                lhs,
+                rhs,
+            } => value(lhs).scalar_op(rhs, Op::try_from(operation)?),
+            CircuitNode::UnaryOp { operation, operand } => {
+                value(operand).unary_op(UnaryOp::try_from(operation)?)
+            }
+            CircuitNode::Cast { operand, to_type } => {
+                value(operand).cast(FheUintType::try_from(to_type)?)
+            }
+            CircuitNode::Cmux {
+                control,
+                if_true,
+                if_false,
+            } => FheValue::cmux(value(control), value(if_true), value(if_false)),
+        }
+    }
+}
+
+/// Checks the size of the circuit and every input and node tag, so a malformed circuit is rejected
+/// as a whole instead of running as some other operation or failing halfway through.
+fn validate(circuit: &Circuit) -> Result<(), RustError> {
+    if circuit.nodes.len() > MAX_CIRCUIT_NODES {
+        log::error!("circuit has {} nodes", circuit.nodes.len());
+        return Err(RustError::invalid_argument(format!(
+            "circuits are limited to {} nodes, got {}",
+            MAX_CIRCUIT_NODES,
+            circuit.nodes.len()
+        )));
+    }
+
+    if circuit.inputs.len() > MAX_CIRCUIT_INPUTS {
+        log::error!("circuit has {} inputs", circuit.inputs.len());
+        return Err(RustError::invalid_argument(format!(
+            "circuits are limited to {} inputs, got {}",
+            MAX_CIRCUIT_INPUTS,
+            circuit.inputs.len()
+        )));
+    }
+
+    if circuit.outputs.len() > MAX_CIRCUIT_OUTPUTS {
+        log::error!("circuit has {} outputs", circuit.outputs.len());
+        return Err(RustError::invalid_argument(format!(
+            "circuits are limited to {} outputs, got {}",
+            MAX_CIRCUIT_OUTPUTS,
+            circuit.outputs.len()
+        )));
+    }
+
+    for (index, input) in circuit.inputs.iter().enumerate() {
+        FheUintType::try_from(input.uint_type).map_err(|err| {
+            log::error!("circuit input {}: {}", index, err);
+            RustError::invalid_argument(format!("circuit input {}: {}", index, err))
+        })?;
+    }
+
+    for (index, node) in circuit.nodes.iter().enumerate() {
+        node.check_tags().map_err(|err| {
+            log::error!("circuit node {}: {}", index, err);
+            RustError::invalid_argument(format!("circuit node {}: {}", index, err))
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Checks that every node only references earlier values, and groups the nodes into levels.
+///
+/// A node's level is one more than the deepest of its operands (inputs are level 0), so all the
+/// nodes of a level are independent of each other and can run in parallel.
+fn schedule(circuit: &Circuit) -> Result<Vec<Vec<usize>>, RustError> {
+    let input_count = circuit.inputs.len();
+    let value_count = input_count + circuit.nodes.len();
+    let mut depths = vec![0usize; value_count];
+    let mut levels: Vec<Vec<usize>> = vec![];
+
+    for (index, node) in circuit.nodes.iter().enumerate() {
+        let id = input_count + index;
+        let mut depth = 0;
+        for operand in node.operands() {
+            let operand = operand as usize;
+            if operand >= id {
+                log::error!("circuit node {} references value {}", index, operand);
+                return Err(RustError::invalid_argument(format!(
+                    "circuit node {} references value {} which is not computed before it",
+                    index, operand
+                )));
+            }
+            depth = depth.max(depths[operand]);
+        }
+
+        depths[id] = depth + 1;
+        if levels.len() < depth + 1 {
+            levels.resize(depth + 1, vec![]);
+        }
+        levels[depth].push(index);
+    }
+
+    if let Some(&output) = circuit
+        .outputs
+        .iter()
+        .find(|&&output| output as usize >= value_count)
+    {
+        return Err(RustError::invalid_argument(format!(
+            "circuit output references unknown value {}",
+            output
+        )));
+    }
+
+    Ok(levels)
+}
+
+/// Runs the circuit and returns the requested outputs.
+///
+/// Every node is evaluated exactly once, so an intermediate referenced by several nodes (or
+/// outputs) is only computed once. Independent nodes run in parallel on the batch thread pool.
+///
+/// Every node runs under the key set and server key pinned on the calling thread. Circuits with
+/// more than `MAX_CIRCUIT_NODES` nodes, `MAX_CIRCUIT_INPUTS` inputs, `MAX_CIRCUIT_OUTPUTS` outputs
+/// or an unknown tag are rejected up front.
+pub fn evaluate_circuit(circuit: Circuit) -> Result<Vec<CircuitOutput>, RustError> {
+    validate(&circuit)?;
+    let levels = schedule(&circuit)?;
+    let pool = batch_pool()?;
+    let key_set = GlobalKeys::pinned_key_set();
+
+    pool.install(|| {
+        let inputs = circuit
+            .inputs
+            .par_iter()
+            .map(|input| {
+                let _key_set = GlobalKeys::use_pinned_key_set(key_set.clone());
+                let uint_type = FheUintType::try_from(input.uint_type)?;
+                FheValue::from_bytes(&input.ciphertext, uint_type).map(Some)
+            })
+            .collect::<Result<Vec<_>, RustError>>()?;
+
+        let mut values = inputs;
+        values.resize(values.len() + circuit.nodes.len(), None);
+        let input_count = circuit.inputs.len();
+
+        for level in levels {
+            let results = level
+                .par_iter()
+                .map(|&index| {
+                    let _key_set = GlobalKeys::use_pinned_key_set(key_set.clone());
+                    circuit.nodes[index]
+                        .evaluate(&values)
+                        .map(|value| (index, value))
+                })
+                .collect::<Result<Vec<_>, RustError>>()?;
+
+            for (index, value) in results {
+                values[input_count + index] = Some(value);
+            }
+        }
+
+        circuit
+            .outputs
+            .par_iter()
+            .map(|&output| {
+                let _key_set = GlobalKeys::use_pinned_key_set(key_set.clone());
+                let value = values[output as usize].as_ref().unwrap();
+                Ok(CircuitOutput {
+                    ciphertext: value.to_bytes()?,
+                    uint_type: value.uint_type() as u32,
+                })
+            })
+            .collect()
+    })
+}
+
+/// Decodes a bincode encoded `Circuit`.
+pub fn decode_circuit(encoded_circuit: &[u8]) -> Result<Circuit, RustError> {
+    bincode::deserialize(encoded_circuit).map_err(|err| {
+        log::error!("failed deserializing circuit: {:?}", err);
+        RustError::deserialization_failed("circuit", err)
+    })
+}
+
+/// The gas cost of the whole circuit - the sum of its nodes, priced on the types that flow
+/// through it. Fails like `evaluate_circuit` on a malformed circuit, without evaluating anything.
+pub fn circuit_cost(circuit: &Circuit) -> Result<u64, RustError> {
+    validate(circuit)?;
+    schedule(circuit)?;
+
+    let mut types = circuit
+        .inputs
+        .iter()
+        .map(|input| FheUintType::try_from(input.uint_type))
+        .collect::<Result<Vec<_>, RustError>>()?;
+
+    let mut total: u64 = 0;
+    for node in &circuit.nodes {
+        let (cost, uint_type) = node.cost(&types)?;
+        total = total.saturating_add(cost);
+        types.push(uint_type);
+    }
+
+    Ok(total)
+}
+
+/// Evaluates the circuit and returns the bincode encoded `Vec<CircuitOutput>`.
+pub fn evaluate_circuit_safe(circuit: Circuit) -> Result<Vec<u8>, RustError> {
+    if is_mock_backend() {
+        return Err(RustError::unsupported_operation(
+            "circuits are not supported by the mock backend",
+        ));
+    }
+
+    let outputs = evaluate_circuit(circuit)?;
+
+    bincode::serialize(&outputs).map_err(|err| {
+        log::error!("failed serializing circuit outputs: {:?}", err);
+        RustError::serialization_failed("circuit outputs")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::{decrypt_safe, encrypt_safe, expand_compressed_safe};
+    use crate::test_utils::setup_keys;
+
+    fn input(msg: u64, uint_type: FheUintType) -> CircuitInput {
+        let compact = encrypt_safe(msg, uint_type).unwrap();
+        CircuitInput {
+            ciphertext: expand_compressed_safe(&compact, uint_type).unwrap(),
+            uint_type: uint_type as u32,
+        }
+    }
+
+    fn decrypt_output(output: &CircuitOutput) -> u64 {
+        decrypt_safe(
+            &output.ciphertext,
+            FheUintType::try_from(output.uint_type).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn evaluates_shared_intermediates_and_branches() {
+        setup_keys();
+
+        // max(a, b) > 10 ? (a + b) * 3 : (a + b) - 1, plus (a + b) cast to uint8
+        let circuit = Circuit {
+            inputs: vec![
+                input(7, FheUintType::Uint16),
+                input(300, FheUintType::Uint16),
+            ],
+            nodes: vec![
+                // 2: a + b
+                CircuitNode::Op {
+                    operation: Op::Add as u32,
+                    lhs: 0,
+                    rhs: 1,
+                },
+                // 3: max(a, b)
+                CircuitNode::Op {
+                    operation: Op::Max as u32,
+                    lhs: 0,
+                    rhs: 1,
+                },
+                // 4: 10
+                CircuitNode::Constant {
+                    value: 10,
+                    uint_type: FheUintType::Uint16 as u32,
+                },
+                // 5: max(a, b) > 10
+                CircuitNode::Op {
+                    operation: Op::Gt as u32,
+                    lhs: 3,
+                    rhs: 4,
+                },
+                // 6: (a + b) * 3
+                CircuitNode::ScalarOp {
+                    operation: Op::Mul as u32,
+                    lhs: 2,
+                    rhs: 3,
+                },
+                // 7: (a + b) - 1
+                CircuitNode::ScalarOp {
+                    operation: Op::Sub as u32,
+                    lhs: 2,
+                    rhs: 1,
+                },
+                // 8: select
+                CircuitNode::Cmux {
+                    control: 5,
+                    if_true: 6,
+                    if_false: 7,
+                },
+                // 9: uint8(a + b)
+                CircuitNode::Cast {
+                    operand: 2,
+                    to_type: FheUintType::Uint8 as u32,
+                },
+            ],
+            outputs: vec![8, 9, 2],
+        };
+
+        let uint16 = FheUintType::Uint16;
+        let expected_cost = op_cost(Op::Add, uint16)
+            + op_cost(Op::Max, uint16)
+            + trivial_encrypt_cost(uint16)
+            + op_cost(Op::Gt, uint16)
+            + scalar_op_cost(Op::Mul, uint16)
+            + scalar_op_cost(Op::Sub, uint16)
+            + cmux_cost(uint16, uint16)
+            + cast_cost(uint16, FheUintType::Uint8);
+        assert_eq!(circuit_cost(&circuit).unwrap(), expected_cost);
+
+        let encoded = bincode::serialize(&circuit).unwrap();
+        let outputs: Vec<CircuitOutput> = bincode::deserialize(
+            &evaluate_circuit_safe(decode_circuit(&encoded).unwrap()).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(decrypt_output(&outputs[0]), 307 * 3);
+        assert_eq!(outputs[1].uint_type, FheUintType::Uint8 as u32);
+        assert_eq!(decrypt_output(&outputs[1]), 307 % 256);
+        assert_eq!(decrypt_output(&outputs[2]), 307);
+    }
+
+    #[test]
+    fn forward_references_are_rejected() {
+        let circuit = Circuit {
+            inputs: vec![],
+            nodes: vec![CircuitNode::UnaryOp {
+                operation: UnaryOp::Not as u32,
+                operand: 0,
+            }],
+            outputs: vec![0],
+        };
+        assert!(evaluate_circuit(circuit).is_err());
+
+        let circuit = Circuit {
+            inputs: vec![],
+            nodes: vec![CircuitNode::Constant {
+                value: 1,
+                uint_type: FheUintType::Uint8 as u32,
+            }],
+            outputs: vec![1],
+        };
+        assert!(evaluate_circuit(circuit).is_err());
+    }
+
+    #[test]
+    fn unknown_tags_and_oversized_circuits_are_rejected() {
+        let constant = CircuitNode::Constant {
+            value: 1,
+            uint_type: FheUintType::Uint8 as u32,
+        };
+
+        let bad_nodes = [
+            CircuitNode::Constant {
+                value: 1,
+                uint_type: 5,
+            },
+            CircuitNode::Op {
+                operation: 18,
+                lhs: 0,
+                rhs: 0,
+            },
+            CircuitNode::ScalarOp {
+                operation: 18,
+                lhs: 0,
+                rhs: 1,
+            },
+            CircuitNode::UnaryOp {
+                operation: 1,
+                operand: 0,
+            },
+            CircuitNode::Cast {
+                operand: 0,
+                to_type: 5,
+            },
+        ];
+        for node in bad_nodes {
+            let circuit = Circuit {
+                inputs: vec![],
+                nodes: vec![constant.clone(), node],
+                outputs: vec![1],
+            };
+            assert!(matches!(
+                evaluate_circuit(circuit),
+                Err(RustError::InvalidArgument { .. })
+            ));
+        }
+
+        let circuit = Circuit {
+            inputs: vec![CircuitInput {
+                ciphertext: vec![],
+                uint_type: 5,
+            }],
+            nodes: vec![],
+            outputs: vec![0],
+        };
+        assert!(matches!(
+            evaluate_circuit(circuit),
+            Err(RustError::InvalidArgument { .. })
+        ));
+
+        let circuit = Circuit {
+            inputs: vec![],
+            nodes: vec![constant; MAX_CIRCUIT_NODES + 1],
+            outputs: vec![0],
+        };
+        assert!(matches!(
+            evaluate_circuit(circuit),
+            Err(RustError::InvalidArgument { .. })
+        ));
+
+        let circuit = Circuit {
+            inputs: vec![],
+            nodes: vec![],
+            outputs: vec![0; MAX_CIRCUIT_OUTPUTS + 1],
+        };
+        assert!(matches!(
+            evaluate_circuit(circuit),
+            Err(RustError::InvalidArgument { .. })
+        ));
+    }
+}
@@ -1,80 +1,246 @@
 use std::panic::catch_unwind;
 use crate::api::FheUintType;
-use crate::error::RustError;
+use crate::boolean;
+use crate::envelope::{open, open_list, seal, seal_list};
+use crate::error::{KeyKind, RustError};
+use crate::gas::{check_gas, expand_list_cost};
 use crate::keys::GlobalKeys;
-use crate::serialization::{deserialize_fhe_uint16, deserialize_fhe_uint32, deserialize_fhe_uint8};
+use crate::mock::{self, is_mock_backend};
+use crate::serialization::{
+    deserialize_fhe_bool, deserialize_fhe_bool_list, deserialize_fhe_uint16,
+    deserialize_fhe_uint16_list, deserialize_fhe_uint32, deserialize_fhe_uint32_list,
+    deserialize_fhe_uint64, deserialize_fhe_uint64_list, deserialize_fhe_uint8,
+    deserialize_fhe_uint8_list,
+};
 
 use tfhe::prelude::FheTrivialEncrypt;
 
 use tfhe::{
-    CompactFheUint16, CompactFheUint32, CompactFheUint8, CompactPublicKey, FheUint16, FheUint32,
-    FheUint8,
+    CompactFheUint16, CompactFheUint16List, CompactFheUint32, CompactFheUint32List,
+    CompactFheUint64, CompactFheUint64List, CompactFheUint8, CompactFheUint8List, CompactPublicKey,
+    FheUint16, FheUint32, FheUint64, FheUint8,
 };
 
 use tfhe::prelude::*;
 use tfhe::ClientKey;
 
+/// The most values a single compact list may hold.
+pub const MAX_LIST_LEN: usize = 256;
+
 pub fn expand_compressed_safe(
     ciphertext: &[u8],
     int_type: FheUintType,
 ) -> Result<Vec<u8>, RustError> {
-    match int_type {
+    if is_mock_backend() {
+        return mock::expand_compressed(ciphertext, int_type);
+    }
+
+    let ciphertext = open(ciphertext, int_type, true)?;
+
+    let expanded = match int_type {
         FheUintType::Uint8 => {
-            let value: FheUint8 = deserialize_fhe_uint8(ciphertext, true).map_err(|e| {
-                RustError::generic_error(format!("failed deserializing compressed u8: {:?}", e))
-            })?;
+            let value: FheUint8 = deserialize_fhe_uint8(ciphertext, true)
+                .map_err(|e| RustError::deserialization_failed("compressed u8", e))?;
 
-            bincode::serialize(&value).map_err(|e| {
-                RustError::generic_error(format!("failed serializing compressed value: {:?}", e))
-            })
+            bincode::serialize(&value)
+                .map_err(|e| RustError::serialization_failed(format!("expanded value: {:?}", e)))
         }
         FheUintType::Uint16 => {
-            let value: FheUint16 = deserialize_fhe_uint16(ciphertext, true).map_err(|e| {
-                RustError::generic_error(format!("failed deserializing compressed u16: {:?}", e))
-            })?;
+            let value: FheUint16 = deserialize_fhe_uint16(ciphertext, true)
+                .map_err(|e| RustError::deserialization_failed("compressed u16", e))?;
 
-            bincode::serialize(&value).map_err(|e| {
-                RustError::generic_error(format!("failed serializing compressed value: {:?}", e))
-            })
+            bincode::serialize(&value)
+                .map_err(|e| RustError::serialization_failed(format!("expanded value: {:?}", e)))
         }
         FheUintType::Uint32 => {
-            let value: FheUint32 = deserialize_fhe_uint32(ciphertext, true).map_err(|e| {
-                RustError::generic_error(format!("failed deserializing compressed u32: {:?}", e))
-            })?;
+            let value: FheUint32 = deserialize_fhe_uint32(ciphertext, true)
+                .map_err(|e| RustError::deserialization_failed("compressed u32", e))?;
 
-            bincode::serialize(&value).map_err(|e| {
-                RustError::generic_error(format!("failed serializing compressed value: {:?}", e))
-            })
+            bincode::serialize(&value)
+                .map_err(|e| RustError::serialization_failed(format!("expanded value: {:?}", e)))
         }
-    }
+        FheUintType::Uint64 => {
+            let value: FheUint64 = deserialize_fhe_uint64(ciphertext, true)
+                .map_err(|e| RustError::deserialization_failed("compressed u64", e))?;
+
+            bincode::serialize(&value)
+                .map_err(|e| RustError::serialization_failed(format!("expanded value: {:?}", e)))
+        }
+        FheUintType::Bool => {
+            let value: FheUint8 = deserialize_fhe_uint8(ciphertext, true)
+                .map_err(|e| RustError::deserialization_failed("compressed bool", e))?;
+
+            bincode::serialize(&_normalize_bool(&value)?)
+                .map_err(|e| RustError::serialization_failed(format!("expanded value: {:?}", e)))
+        }
+    }?;
+
+    Ok(seal(&expanded, int_type, false))
 }
 
 pub fn encrypt_safe(msg: u64, int_type: FheUintType) -> Result<Vec<u8>, RustError> {
+    if is_mock_backend() {
+        return Ok(mock::encrypt(msg, int_type));
+    }
+
     let public_key = match GlobalKeys::get_public_key() {
         Some(key) => Ok(key),
-        None => Err(RustError::generic_error("public key not set")), // Return an error or handle this case appropriately.
+        None => Err(RustError::key_not_loaded(KeyKind::Public)),
     }?;
 
-    match int_type {
+    let compact = match int_type {
         FheUintType::Uint8 => {
-            _encrypt_impl::<_, CompactFheUint8, FheUint8>(msg as u8, true, public_key)
+            _encrypt_impl::<_, CompactFheUint8, FheUint8>(msg as u8, true, &public_key)
         }
         FheUintType::Uint16 => {
-            _encrypt_impl::<_, CompactFheUint16, FheUint16>(msg as u16, true, public_key)
+            _encrypt_impl::<_, CompactFheUint16, FheUint16>(msg as u16, true, &public_key)
         }
         FheUintType::Uint32 => {
-            _encrypt_impl::<_, CompactFheUint32, FheUint32>(msg as u32, true, public_key)
+            _encrypt_impl::<_, CompactFheUint32, FheUint32>(msg as u32, true, &public_key)
+        }
+        FheUintType::Uint64 => {
+            _encrypt_impl::<_, CompactFheUint64, FheUint64>(msg, true, &public_key)
         }
+        FheUintType::Bool => {
+            _encrypt_impl::<_, CompactFheUint8, FheUint8>((msg != 0) as u8, true, &public_key)
+        }
+    }?;
+
+    Ok(seal(&compact, int_type, true))
+}
+
+/// Encrypts `values` into a single compact list of `int_type` values, which is much smaller than
+/// the same values encrypted one by one. Values are truncated to the width of the type like in
+/// `encrypt_safe`.
+pub fn encrypt_list_safe(values: &[u64], int_type: FheUintType) -> Result<Vec<u8>, RustError> {
+    if values.is_empty() || values.len() > MAX_LIST_LEN {
+        log::error!("cannot encrypt a list of {} values", values.len());
+        return Err(RustError::invalid_argument(format!(
+            "lists hold between 1 and {} values, got {}",
+            MAX_LIST_LEN,
+            values.len()
+        )));
+    }
+
+    if is_mock_backend() {
+        return Ok(mock::encrypt_list(values, int_type));
     }
+
+    let public_key = match GlobalKeys::get_public_key() {
+        Some(key) => Ok(key),
+        None => Err(RustError::key_not_loaded(KeyKind::Public)),
+    }?;
+
+    let list = match int_type {
+        FheUintType::Uint8 => _encrypt_list_impl::<_, CompactFheUint8List>(
+            &values.iter().map(|v| *v as u8).collect::<Vec<_>>(),
+            &public_key,
+        ),
+        FheUintType::Uint16 => _encrypt_list_impl::<_, CompactFheUint16List>(
+            &values.iter().map(|v| *v as u16).collect::<Vec<_>>(),
+            &public_key,
+        ),
+        FheUintType::Uint32 => _encrypt_list_impl::<_, CompactFheUint32List>(
+            &values.iter().map(|v| *v as u32).collect::<Vec<_>>(),
+            &public_key,
+        ),
+        FheUintType::Uint64 => _encrypt_list_impl::<_, CompactFheUint64List>(values, &public_key),
+        FheUintType::Bool => _encrypt_list_impl::<_, CompactFheUint8List>(
+            &values.iter().map(|v| (*v != 0) as u8).collect::<Vec<_>>(),
+            &public_key,
+        ),
+    }?;
+
+    Ok(seal_list(&list, int_type))
+}
+
+/// Expands a list made by `encrypt_list_safe`, returning each of its values as an expanded
+/// ciphertext - the same as `expand_compressed_safe` returns for a single value.
+///
+/// Lists of more than `MAX_LIST_LEN` values are rejected, and expanding costs `expand_cost` gas
+/// per value, which is checked against `gas_budget`. Both happen once the length of the list is
+/// known and before any value is expanded.
+pub fn expand_list_safe(
+    list: &[u8],
+    int_type: FheUintType,
+    gas_budget: u64,
+) -> Result<Vec<Vec<u8>>, RustError> {
+    if is_mock_backend() {
+        let expanded = mock::expand_list(list, int_type)?;
+        check_gas(expand_list_cost(int_type, expanded.len()), gas_budget)?;
+        return Ok(expanded);
+    }
+
+    let list = open_list(list, int_type)?;
+
+    let expanded = match int_type {
+        FheUintType::Uint8 => {
+            let list = deserialize_fhe_uint8_list(list)
+                .map_err(|e| RustError::deserialization_failed("u8 list", e))?;
+            _expand_list_impl(list.len(), || Ok(list.expand()), int_type, gas_budget)
+        }
+        FheUintType::Uint16 => {
+            let list = deserialize_fhe_uint16_list(list)
+                .map_err(|e| RustError::deserialization_failed("u16 list", e))?;
+            _expand_list_impl(list.len(), || Ok(list.expand()), int_type, gas_budget)
+        }
+        FheUintType::Uint32 => {
+            let list = deserialize_fhe_uint32_list(list)
+                .map_err(|e| RustError::deserialization_failed("u32 list", e))?;
+            _expand_list_impl(list.len(), || Ok(list.expand()), int_type, gas_budget)
+        }
+        FheUintType::Uint64 => {
+            let list = deserialize_fhe_uint64_list(list)
+                .map_err(|e| RustError::deserialization_failed("u64 list", e))?;
+            _expand_list_impl(list.len(), || Ok(list.expand()), int_type, gas_budget)
+        }
+        FheUintType::Bool => {
+            let list = deserialize_fhe_bool_list(list)
+                .map_err(|e| RustError::deserialization_failed("bool list", e))?;
+            _expand_list_impl(
+                list.len(),
+                || list.expand().iter().map(_normalize_bool).collect(),
+                int_type,
+                gas_budget,
+            )
+        }
+    }?;
+
+    Ok(expanded
+        .iter()
+        .map(|value| seal(value, int_type, false))
+        .collect())
 }
 
 pub fn trivial_encrypt_safe(msg: u64, int_type: FheUintType) -> Result<Vec<u8>, RustError> {
+    if is_mock_backend() {
+        return Ok(mock::trivial_encrypt(msg, int_type));
+    }
+
+    let payload = trivial_encrypt_unsealed(msg, int_type)?;
+
+    Ok(seal(&payload, int_type, false))
+}
+
+/// Trivially encrypts `msg` without wrapping the result in an envelope, for callers that keep
+/// working on the raw ciphertext.
+pub(crate) fn trivial_encrypt_unsealed(
+    msg: u64,
+    int_type: FheUintType,
+) -> Result<Vec<u8>, RustError> {
     let trivial_encrypt_result = catch_unwind(|| {
         GlobalKeys::refresh_server_key_for_thread();
         match int_type {
             FheUintType::Uint8 => _encrypt_trivial_impl::<_, FheUint8>(msg as u8),
             FheUintType::Uint16 => _encrypt_trivial_impl::<_, FheUint16>(msg as u16),
             FheUintType::Uint32 => _encrypt_trivial_impl::<_, FheUint32>(msg as u32),
+            FheUintType::Uint64 => _encrypt_trivial_impl::<_, FheUint64>(msg),
+            FheUintType::Bool => boolean::trivial(msg != 0).and_then(|value| {
+                bincode::serialize(&value).map_err(|err| {
+                    log::error!("failed serializing trivial encryption: {:?}", err);
+                    RustError::serialization_failed("trivial encryption")
+                })
+            }),
         }
     });
 
@@ -89,36 +255,56 @@ pub fn trivial_encrypt_safe(msg: u64, int_type: FheUintType) -> Result<Vec<u8>,
 }
 
 pub fn decrypt_safe(ciphertext: &[u8], int_type: FheUintType) -> Result<u64, RustError> {
+    if is_mock_backend() {
+        return mock::decrypt(ciphertext, int_type);
+    }
+
     let client_key = match GlobalKeys::get_client_key() {
         Some(ck) => Ok(ck),
-        None => Err(RustError::generic_error("client key not set")),
+        None => Err(RustError::key_not_loaded(KeyKind::Client)),
     }?;
 
-    let res = match int_type {
-        FheUintType::Uint8 => _impl_decrypt_u8(
-            deserialize_fhe_uint8(ciphertext, false).map_err(|err| {
-                log::error!("failed decrypting u8: {:?}", err);
-                RustError::generic_error("Failed decrypting u8")
-            })?,
-            client_key,
-        ),
-        FheUintType::Uint16 => _impl_decrypt_u16(
-            deserialize_fhe_uint16(ciphertext, false).map_err(|err| {
-                log::error!("failed decrypting u16: {:?}", err);
-                RustError::generic_error("Failed decrypting u16")
-            })?,
-            client_key,
-        ),
-        FheUintType::Uint32 => _impl_decrypt_u32(
-            deserialize_fhe_uint32(ciphertext, false).map_err(|err| {
-                log::error!("failed decrypting u32: {:?}", err);
-                RustError::generic_error("Failed decrypting u32")
-            })?,
-            client_key,
-        ),
-    };
+    let ciphertext = open(ciphertext, int_type, false)?;
 
-    Ok(res)
+    client_key.with_key(|client_key| {
+        Ok(match int_type {
+            FheUintType::Uint8 => _impl_decrypt_u8(
+                deserialize_fhe_uint8(ciphertext, false).map_err(|err| {
+                    log::error!("failed decrypting u8: {:?}", err);
+                    RustError::deserialization_failed("u8 ciphertext", err)
+                })?,
+                client_key,
+            ),
+            FheUintType::Uint16 => _impl_decrypt_u16(
+                deserialize_fhe_uint16(ciphertext, false).map_err(|err| {
+                    log::error!("failed decrypting u16: {:?}", err);
+                    RustError::deserialization_failed("u16 ciphertext", err)
+                })?,
+                client_key,
+            ),
+            FheUintType::Uint32 => _impl_decrypt_u32(
+                deserialize_fhe_uint32(ciphertext, false).map_err(|err| {
+                    log::error!("failed decrypting u32: {:?}", err);
+                    RustError::deserialization_failed("u32 ciphertext", err)
+                })?,
+                client_key,
+            ),
+            FheUintType::Uint64 => _impl_decrypt_u64(
+                deserialize_fhe_uint64(ciphertext, false).map_err(|err| {
+                    log::error!("failed decrypting u64: {:?}", err);
+                    RustError::deserialization_failed("u64 ciphertext", err)
+                })?,
+                client_key,
+            ),
+            FheUintType::Bool => _impl_decrypt_u8(
+                deserialize_fhe_bool(ciphertext).map_err(|err| {
+                    log::error!("failed decrypting bool: {:?}", err);
+                    RustError::deserialization_failed("bool ciphertext", err)
+                })?,
+                client_key,
+            ),
+        })
+    })
 }
 
 fn _encrypt_trivial_impl<T, Expanded>(value: T) -> Result<Vec<u8>, RustError>
@@ -130,7 +316,7 @@ where
     // todo: separate serialization from encryption so we can change it on-the-fly
     bincode::serialize(&Expanded::encrypt_trivial(value)).map_err(|err| {
         log::error!("failed serializing trivial encryption: {:?}", err);
-        RustError::generic_error("failed serializing trivial encryption")
+        RustError::serialization_failed("trivial encryption")
     })
 }
 
@@ -149,7 +335,7 @@ where
     if !compact {
         bincode::serialize(&Expanded::encrypt(value, public_key)).map_err(|err| {
             log::error!("failed serializing value: {:?}", err);
-            RustError::generic_error("ciphertext serialization error")
+            RustError::serialization_failed("ciphertext")
         })
     } else {
         let encrypted = &Compact::try_encrypt(value, public_key).map_err(|err| {
@@ -158,11 +344,88 @@ where
         })?;
         bincode::serialize(encrypted).map_err(|err| {
             log::error!("failed serializing value: {:?}", err);
-            RustError::generic_error("ciphertext serialization error")
+            RustError::serialization_failed("ciphertext")
         })
     }
 }
 
+fn _encrypt_list_impl<T, List>(
+    values: &[T],
+    public_key: &CompactPublicKey,
+) -> Result<Vec<u8>, RustError>
+where
+    List: for<'a> FheTryEncrypt<&'a [T], CompactPublicKey>,
+    List: serde::Serialize,
+{
+    let encrypted = &List::try_encrypt(values, public_key).map_err(|err| {
+        log::error!("failed encrypting list: {:?}", err);
+        RustError::generic_error("encrypt list failed")
+    })?;
+    bincode::serialize(encrypted).map_err(|err| {
+        log::error!("failed serializing list: {:?}", err);
+        RustError::serialization_failed("ciphertext list")
+    })
+}
+
+/// Fails with `RustError::InvalidCiphertext` unless a list of `len` values can be expanded, i.e.
+/// holds between 1 and `MAX_LIST_LEN` of them.
+pub(crate) fn check_expand_list_len(len: usize) -> Result<(), RustError> {
+    if len == 0 || len > MAX_LIST_LEN {
+        log::error!("cannot expand a list of {} values", len);
+        return Err(RustError::invalid_ciphertext(format!(
+            "lists hold between 1 and {} values, got {}",
+            MAX_LIST_LEN, len
+        )));
+    }
+
+    Ok(())
+}
+
+/// Checks the length of a list of `len` values and charges for expanding it, then expands and
+/// serializes the values.
+fn _expand_list_impl<Expanded>(
+    len: usize,
+    expand: impl FnOnce() -> Result<Vec<Expanded>, RustError>,
+    int_type: FheUintType,
+    gas_budget: u64,
+) -> Result<Vec<Vec<u8>>, RustError>
+where
+    Expanded: serde::Serialize,
+{
+    check_expand_list_len(len)?;
+    check_gas(expand_list_cost(int_type, len), gas_budget)?;
+
+    _serialize_expanded(expand()?)
+}
+
+/// Narrows an expanded compact `Bool` to its single block (see `boolean`). Compact ciphertexts come
+/// from users, so the value is compared against zero first rather than trusted to be 0 or 1.
+fn _normalize_bool(value: &FheUint8) -> Result<FheUint8, RustError> {
+    GlobalKeys::refresh_server_key_for_thread();
+
+    boolean::is_non_zero(value, FheUint8::encrypt_trivial(0u8))
+}
+
+fn _serialize_expanded<Expanded>(values: Vec<Expanded>) -> Result<Vec<Vec<u8>>, RustError>
+where
+    Expanded: serde::Serialize,
+{
+    values
+        .iter()
+        .map(|value| {
+            bincode::serialize(value)
+                .map_err(|e| RustError::serialization_failed(format!("expanded value: {:?}", e)))
+        })
+        .collect()
+}
+
+// the way this is implemented cannot be parsed generically :(
+fn _impl_decrypt_u64(ciphertext: FheUint64, client_key: &ClientKey) -> u64 {
+    let decrypt_value: u64 = ciphertext.decrypt(client_key);
+
+    decrypt_value
+}
+
 // the way this is implemented cannot be parsed generically :(
 fn _impl_decrypt_u32(ciphertext: FheUint32, client_key: &ClientKey) -> u64 {
     // let now = std::time::Instant::now();
@@ -192,3 +455,172 @@ fn _impl_decrypt_u8(ciphertext: FheUint8, client_key: &ClientKey) -> u64 {
 
     decrypt_value as u64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gas::{expand_cost, UNLIMITED_GAS};
+    use crate::test_utils::setup_keys;
+
+    const ALL_TYPES: [(FheUintType, u64); 5] = [
+        (FheUintType::Uint8, u8::MAX as u64),
+        (FheUintType::Uint16, u16::MAX as u64),
+        (FheUintType::Uint32, u32::MAX as u64),
+        (FheUintType::Uint64, u64::MAX),
+        (FheUintType::Bool, 1),
+    ];
+
+    #[test]
+    fn encrypt_expand_decrypt_round_trip() {
+        setup_keys();
+
+        for (int_type, max) in ALL_TYPES {
+            for msg in [0, 1, max / 3, max] {
+                let compact = encrypt_safe(msg, int_type).unwrap();
+                let expanded = expand_compressed_safe(&compact, int_type).unwrap();
+                let decrypted = decrypt_safe(&expanded, int_type).unwrap();
+                assert_eq!(decrypted, msg, "{:?}", int_type);
+            }
+        }
+    }
+
+    #[test]
+    fn trivial_encrypt_decrypt_round_trip() {
+        setup_keys();
+
+        for (int_type, max) in ALL_TYPES {
+            let ciphertext = trivial_encrypt_safe(max, int_type).unwrap();
+            let decrypted = decrypt_safe(&ciphertext, int_type).unwrap();
+            assert_eq!(decrypted, max, "{:?}", int_type);
+        }
+    }
+
+    #[test]
+    fn encrypt_truncates_to_type_width() {
+        setup_keys();
+
+        let compact = encrypt_safe(u64::MAX, FheUintType::Uint32).unwrap();
+        let expanded = expand_compressed_safe(&compact, FheUintType::Uint32).unwrap();
+        assert_eq!(
+            decrypt_safe(&expanded, FheUintType::Uint32).unwrap(),
+            u32::MAX as u64
+        );
+    }
+
+    #[test]
+    fn encrypt_bool_normalizes_to_one() {
+        setup_keys();
+
+        let compact = encrypt_safe(42, FheUintType::Bool).unwrap();
+        let expanded = expand_compressed_safe(&compact, FheUintType::Bool).unwrap();
+        assert_eq!(decrypt_safe(&expanded, FheUintType::Bool).unwrap(), 1);
+    }
+
+    #[test]
+    fn mismatched_envelopes_are_rejected() {
+        setup_keys();
+
+        let compact = encrypt_safe(7, FheUintType::Uint16).unwrap();
+        // compact ciphertexts must be expanded first
+        assert!(matches!(
+            decrypt_safe(&compact, FheUintType::Uint16),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+        assert!(matches!(
+            expand_compressed_safe(&compact, FheUintType::Uint32),
+            Err(RustError::TypeMismatch { .. })
+        ));
+
+        let expanded = expand_compressed_safe(&compact, FheUintType::Uint16).unwrap();
+        assert!(matches!(
+            decrypt_safe(&expanded, FheUintType::Uint8),
+            Err(RustError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn encrypt_list_expand_decrypt_round_trip() {
+        setup_keys();
+
+        for (int_type, max) in ALL_TYPES {
+            let values = [0, 1, max / 3, max];
+            let list = encrypt_list_safe(&values, int_type).unwrap();
+            let expanded = expand_list_safe(&list, int_type, UNLIMITED_GAS).unwrap();
+            assert_eq!(expanded.len(), values.len());
+
+            for (ciphertext, value) in expanded.iter().zip(values) {
+                assert_eq!(decrypt_safe(ciphertext, int_type).unwrap(), value);
+            }
+        }
+
+        // a list is smaller than the values encrypted one by one
+        let values = [7; 16];
+        let list = encrypt_list_safe(&values, FheUintType::Uint32).unwrap();
+        let singles: usize = values
+            .iter()
+            .map(|v| encrypt_safe(*v, FheUintType::Uint32).unwrap().len())
+            .sum();
+        assert!(list.len() < singles);
+    }
+
+    #[test]
+    fn expanding_a_list_is_charged_per_value() {
+        setup_keys();
+
+        let list = encrypt_list_safe(&[1, 2, 3], FheUintType::Uint16).unwrap();
+        let cost = 3 * expand_cost(FheUintType::Uint16);
+
+        assert!(matches!(
+            expand_list_safe(&list, FheUintType::Uint16, cost - 1),
+            Err(RustError::OutOfGas { .. })
+        ));
+        assert_eq!(
+            expand_list_safe(&list, FheUintType::Uint16, cost)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn lists_are_rejected_where_single_values_are_expected() {
+        setup_keys();
+
+        let list = encrypt_list_safe(&[1, 2], FheUintType::Uint8).unwrap();
+        assert!(matches!(
+            expand_compressed_safe(&list, FheUintType::Uint8),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+        assert!(expand_list_safe(&list, FheUintType::Uint16, UNLIMITED_GAS).is_err());
+
+        let compact = encrypt_safe(1, FheUintType::Uint8).unwrap();
+        assert!(matches!(
+            expand_list_safe(&compact, FheUintType::Uint8, UNLIMITED_GAS),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+
+        assert!(matches!(
+            encrypt_list_safe(&[], FheUintType::Uint8),
+            Err(RustError::InvalidArgument { .. })
+        ));
+        assert!(encrypt_list_safe(&[0; MAX_LIST_LEN + 1], FheUintType::Uint8).is_err());
+    }
+
+    #[test]
+    fn oversized_lists_are_not_expanded() {
+        setup_keys();
+
+        let public_key = GlobalKeys::get_public_key().unwrap();
+        let list =
+            _encrypt_list_impl::<_, CompactFheUint8List>(&[0u8; MAX_LIST_LEN + 1], &public_key)
+                .unwrap();
+        assert!(matches!(
+            expand_list_safe(
+                &seal_list(&list, FheUintType::Uint8),
+                FheUintType::Uint8,
+                UNLIMITED_GAS
+            ),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+    }
+}
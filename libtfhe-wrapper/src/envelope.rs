@@ -0,0 +1,390 @@
+//! Self-describing wrapper around serialized ciphertexts.
+//!
+//! Every ciphertext that leaves the library is prefixed with a small header:
+//!
+//! | bytes  | field                                                    |
+//! |--------|----------------------------------------------------------|
+//! | 0..4   | magic, `b"FHCT"`                                         |
+//! | 4      | format version                                           |
+//! | 5      | `FheUintType` tag                                        |
+//! | 6      | flags - bit 0: compact, bit 1: mock backend, bit 2: list |
+//! | 7..15  | fingerprint of the key set that produced the value       |
+//! | 15..   | bincode encoded ciphertext                               |
+//!
+//! The fingerprint identifies the generation of the active key set (see
+//! `GlobalKeys::get_key_set_fingerprint`), and is the same whether the key set holds a public key
+//! or only a server key, so a ciphertext is rejected by any other key set. Only a key set without
+//! keys writes an all-zero fingerprint, and such ciphertexts are rejected by every key set that
+//! knows its fingerprint.
+//!
+//! A compact ciphertext list (see `encryption::encrypt_list_safe`) is sealed the same way, with
+//! the list flag set and the type of its elements. It can only be opened as a list.
+
+use crate::api::FheUintType;
+use crate::error::RustError;
+use crate::keys::{GlobalKeys, KeyFingerprint};
+use crate::mock::is_mock_backend;
+use crate::validation::{check_ciphertext, check_ciphertext_len};
+
+pub const ENVELOPE_MAGIC: [u8; 4] = *b"FHCT";
+pub const ENVELOPE_VERSION: u8 = 1;
+pub const ENVELOPE_HEADER_LEN: usize = 15;
+
+const FLAG_COMPACT: u8 = 0b01;
+const FLAG_MOCK: u8 = 0b10;
+const FLAG_LIST: u8 = 0b100;
+const UNKNOWN_FINGERPRINT: KeyFingerprint = [0u8; 8];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EnvelopeHeader {
+    pub version: u8,
+    pub uint_type: FheUintType,
+    pub compact: bool,
+    /// Set for the tagged plaintexts of the mock backend, which must never be mixed with real
+    /// ciphertexts.
+    pub mock: bool,
+    /// Set for compact lists of `uint_type` values.
+    pub list: bool,
+    pub key_fingerprint: KeyFingerprint,
+}
+
+impl EnvelopeHeader {
+    /// Parses the header of `bytes`, returning it together with the ciphertext payload.
+    pub fn parse(bytes: &[u8]) -> Result<(EnvelopeHeader, &[u8]), RustError> {
+        if bytes.len() < ENVELOPE_HEADER_LEN || bytes[0..4] != ENVELOPE_MAGIC {
+            return Err(RustError::invalid_ciphertext(
+                "missing ciphertext envelope header",
+            ));
+        }
+
+        let version = bytes[4];
+        if version != ENVELOPE_VERSION {
+            return Err(RustError::invalid_ciphertext(format!(
+                "unsupported ciphertext envelope version {}",
+                version
+            )));
+        }
+
+        let uint_type = uint_type_from_tag(bytes[5]).ok_or_else(|| {
+            RustError::invalid_ciphertext(format!("unknown ciphertext type tag {}", bytes[5]))
+        })?;
+
+        let mut key_fingerprint = UNKNOWN_FINGERPRINT;
+        key_fingerprint.copy_from_slice(&bytes[7..ENVELOPE_HEADER_LEN]);
+
+        let header = EnvelopeHeader {
+            version,
+            uint_type,
+            compact: bytes[6] & FLAG_COMPACT != 0,
+            mock: bytes[6] & FLAG_MOCK != 0,
+            list: bytes[6] & FLAG_LIST != 0,
+            key_fingerprint,
+        };
+
+        Ok((header, &bytes[ENVELOPE_HEADER_LEN..]))
+    }
+}
+
+fn uint_type_from_tag(tag: u8) -> Option<FheUintType> {
+    match tag {
+        0 => Some(FheUintType::Uint8),
+        1 => Some(FheUintType::Uint16),
+        2 => Some(FheUintType::Uint32),
+        3 => Some(FheUintType::Uint64),
+        4 => Some(FheUintType::Bool),
+        _ => None,
+    }
+}
+
+/// Wraps a bincode encoded ciphertext in an envelope stamped with the loaded key set.
+pub fn seal(payload: &[u8], uint_type: FheUintType, compact: bool) -> Vec<u8> {
+    seal_for_backend(payload, uint_type, compact, is_mock_backend())
+}
+
+/// `seal` for an explicitly chosen backend rather than the active one.
+pub(crate) fn seal_for_backend(
+    payload: &[u8],
+    uint_type: FheUintType,
+    compact: bool,
+    mock: bool,
+) -> Vec<u8> {
+    seal_envelope(payload, uint_type, compact, false, mock)
+}
+
+/// Wraps a bincode encoded compact ciphertext list of `uint_type` values in an envelope.
+pub fn seal_list(payload: &[u8], uint_type: FheUintType) -> Vec<u8> {
+    seal_list_for_backend(payload, uint_type, is_mock_backend())
+}
+
+/// `seal_list` for an explicitly chosen backend rather than the active one.
+pub(crate) fn seal_list_for_backend(payload: &[u8], uint_type: FheUintType, mock: bool) -> Vec<u8> {
+    seal_envelope(payload, uint_type, true, true, mock)
+}
+
+fn seal_envelope(
+    payload: &[u8],
+    uint_type: FheUintType,
+    compact: bool,
+    list: bool,
+    mock: bool,
+) -> Vec<u8> {
+    let fingerprint = GlobalKeys::get_key_set_fingerprint().unwrap_or(UNKNOWN_FINGERPRINT);
+
+    let mut sealed = Vec::with_capacity(ENVELOPE_HEADER_LEN + payload.len());
+    sealed.extend_from_slice(&ENVELOPE_MAGIC);
+    sealed.push(ENVELOPE_VERSION);
+    sealed.push(uint_type as u8);
+    let mut flags = 0;
+    if compact {
+        flags |= FLAG_COMPACT;
+    }
+    if mock {
+        flags |= FLAG_MOCK;
+    }
+    if list {
+        flags |= FLAG_LIST;
+    }
+    sealed.push(flags);
+    sealed.extend_from_slice(&fingerprint);
+    sealed.extend_from_slice(payload);
+    sealed
+}
+
+/// Validates the envelope of `bytes` against what the caller expects and returns the payload.
+///
+/// Fails with `RustError::TypeMismatch` if the ciphertext is of another type than `uint_type`, and
+/// with `RustError::InvalidCiphertext` if the header is missing or malformed, if the compactness,
+/// backend or key set of the ciphertext do not match, or if a tfhe payload does not have the shape
+/// of a ciphertext of the active key set (see `validation`).
+pub fn open(bytes: &[u8], uint_type: FheUintType, compact: bool) -> Result<&[u8], RustError> {
+    open_for_backend(bytes, uint_type, compact, is_mock_backend())
+}
+
+/// `open` for an explicitly chosen backend rather than the active one.
+pub(crate) fn open_for_backend(
+    bytes: &[u8],
+    uint_type: FheUintType,
+    compact: bool,
+    mock: bool,
+) -> Result<&[u8], RustError> {
+    open_envelope(bytes, uint_type, compact, false, mock)
+}
+
+/// Like `open`, for a compact ciphertext list of `uint_type` values.
+///
+/// Only the size of list payloads is validated, as it depends on the number of values.
+pub fn open_list(bytes: &[u8], uint_type: FheUintType) -> Result<&[u8], RustError> {
+    open_list_for_backend(bytes, uint_type, is_mock_backend())
+}
+
+/// `open_list` for an explicitly chosen backend rather than the active one.
+pub(crate) fn open_list_for_backend(
+    bytes: &[u8],
+    uint_type: FheUintType,
+    mock: bool,
+) -> Result<&[u8], RustError> {
+    open_envelope(bytes, uint_type, true, true, mock)
+}
+
+fn open_envelope(
+    bytes: &[u8],
+    uint_type: FheUintType,
+    compact: bool,
+    list: bool,
+    mock: bool,
+) -> Result<&[u8], RustError> {
+    let (header, payload) = EnvelopeHeader::parse(bytes).map_err(|err| {
+        log::error!("failed opening ciphertext envelope: {}", err);
+        err
+    })?;
+
+    if header.uint_type != uint_type {
+        log::error!(
+            "ciphertext type mismatch: expected {:?}, got {:?}",
+            uint_type,
+            header.uint_type
+        );
+        return Err(RustError::type_mismatch(format!(
+            "expected a {:?} ciphertext, got {:?}",
+            uint_type, header.uint_type
+        )));
+    }
+
+    if header.compact != compact {
+        let describe = |compact: bool| if compact { "compact" } else { "expanded" };
+        log::error!(
+            "ciphertext form mismatch: expected {}, got {}",
+            describe(compact),
+            describe(header.compact)
+        );
+        return Err(RustError::invalid_ciphertext(format!(
+            "expected an {} ciphertext, got a {} one",
+            describe(compact),
+            describe(header.compact)
+        )));
+    }
+
+    if header.list != list {
+        let describe = |list: bool| {
+            if list {
+                "ciphertext list"
+            } else {
+                "single ciphertext"
+            }
+        };
+        log::error!(
+            "ciphertext shape mismatch: expected a {}, got a {}",
+            describe(list),
+            describe(header.list)
+        );
+        return Err(RustError::invalid_ciphertext(format!(
+            "expected a {}, got a {}",
+            describe(list),
+            describe(header.list)
+        )));
+    }
+
+    if header.mock != mock {
+        let describe = |mock: bool| if mock { "mock" } else { "tfhe" };
+        log::error!(
+            "ciphertext backend mismatch: expected {}, got {}",
+            describe(mock),
+            describe(header.mock)
+        );
+        return Err(RustError::invalid_ciphertext(format!(
+            "expected a {} ciphertext, got a {} one",
+            describe(mock),
+            describe(header.mock)
+        )));
+    }
+
+    if let Some(fingerprint) = GlobalKeys::get_key_set_fingerprint() {
+        if header.key_fingerprint == UNKNOWN_FINGERPRINT {
+            log::error!("ciphertext carries no key set fingerprint");
+            return Err(RustError::invalid_ciphertext(format!(
+                "ciphertext was produced without a key set, but key set {} is loaded",
+                hex::encode(fingerprint)
+            )));
+        }
+        if header.key_fingerprint != fingerprint {
+            log::error!(
+                "ciphertext key set mismatch: expected {}, got {}",
+                hex::encode(fingerprint),
+                hex::encode(header.key_fingerprint)
+            );
+            return Err(RustError::invalid_ciphertext(format!(
+                "ciphertext was produced by key set {}, but key set {} is loaded",
+                hex::encode(header.key_fingerprint),
+                hex::encode(fingerprint)
+            )));
+        }
+    }
+
+    if list {
+        check_ciphertext_len(payload)?;
+    } else if !mock {
+        check_ciphertext(payload, uint_type, compact)?;
+    }
+
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{KeySetId, DEFAULT_KEY_SET};
+    use crate::test_utils::setup_keys;
+
+    /// The payloads below are not real ciphertexts, so they are opened under a key set without
+    /// keys, where only the envelope is checked.
+    const NO_KEYS: KeySetId = 99;
+
+    #[test]
+    fn seal_and_open_round_trip() {
+        let _key_set = GlobalKeys::use_key_set(NO_KEYS);
+        let sealed = seal(&[1, 2, 3], FheUintType::Uint16, true);
+        assert_eq!(sealed.len(), ENVELOPE_HEADER_LEN + 3);
+
+        let (header, payload) = EnvelopeHeader::parse(&sealed).unwrap();
+        assert_eq!(header.version, ENVELOPE_VERSION);
+        assert_eq!(header.uint_type, FheUintType::Uint16);
+        assert!(header.compact);
+        assert_eq!(payload, &[1, 2, 3]);
+
+        assert_eq!(
+            open(&sealed, FheUintType::Uint16, true).unwrap(),
+            &[1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn mismatches_are_rejected() {
+        let _key_set = GlobalKeys::use_key_set(NO_KEYS);
+        let sealed = seal(&[1, 2, 3], FheUintType::Uint16, false);
+
+        assert!(matches!(
+            open(&sealed, FheUintType::Uint32, false),
+            Err(RustError::TypeMismatch { .. })
+        ));
+        assert!(matches!(
+            open(&sealed, FheUintType::Uint16, true),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+
+        let mut bad_version = sealed.clone();
+        bad_version[4] = ENVELOPE_VERSION + 1;
+        assert!(open(&bad_version, FheUintType::Uint16, false).is_err());
+
+        let mut bad_tag = sealed;
+        bad_tag[5] = 42;
+        assert!(open(&bad_tag, FheUintType::Uint16, false).is_err());
+
+        let mock = seal_for_backend(&[1, 2, 3], FheUintType::Uint16, false, true);
+        assert!(EnvelopeHeader::parse(&mock).unwrap().0.mock);
+        assert!(open_for_backend(&mock, FheUintType::Uint16, false, false).is_err());
+        assert!(open_for_backend(&mock, FheUintType::Uint16, false, true).is_ok());
+
+        assert!(open(&[1, 2, 3], FheUintType::Uint16, false).is_err());
+    }
+
+    #[test]
+    fn lists_and_single_ciphertexts_are_not_mixed() {
+        let _key_set = GlobalKeys::use_key_set(NO_KEYS);
+        let list = seal_list(&[1, 2, 3], FheUintType::Uint32);
+        let (header, _) = EnvelopeHeader::parse(&list).unwrap();
+        assert!(header.list && header.compact);
+
+        assert_eq!(open_list(&list, FheUintType::Uint32).unwrap(), &[1, 2, 3]);
+        assert!(matches!(
+            open(&list, FheUintType::Uint32, true),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+        assert!(open_list(&list, FheUintType::Uint8).is_err());
+
+        let single = seal(&[1, 2, 3], FheUintType::Uint32, true);
+        assert!(matches!(
+            open_list(&single, FheUintType::Uint32),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+    }
+
+    #[test]
+    fn unstamped_ciphertexts_are_rejected_by_loaded_key_sets() {
+        setup_keys();
+
+        let unstamped = {
+            let _key_set = GlobalKeys::use_key_set(NO_KEYS);
+            seal(&[1, 2, 3], FheUintType::Uint8, false)
+        };
+        assert_eq!(
+            EnvelopeHeader::parse(&unstamped).unwrap().0.key_fingerprint,
+            UNKNOWN_FINGERPRINT
+        );
+
+        let _key_set = GlobalKeys::use_key_set(DEFAULT_KEY_SET);
+        assert!(matches!(
+            open(&unstamped, FheUintType::Uint8, false),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+    }
+}
@@ -1,8 +1,29 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[cfg(feature = "backtraces")]
 use std::backtrace::Backtrace;
 
+/// The keys `GlobalKeys` can hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyKind {
+    Server,
+    Client,
+    Public,
+}
+
+impl fmt::Display for KeyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyKind::Server => write!(f, "server"),
+            KeyKind::Client => write!(f, "client"),
+            KeyKind::Public => write!(f, "public"),
+        }
+    }
+}
+
 #[derive(Error, Debug)]
 pub enum RustError {
     #[error("Empty argument: {}", name)]
@@ -47,6 +68,69 @@ pub enum RustError {
         #[cfg(feature = "backtraces")]
         backtrace: Backtrace,
     },
+    #[error("Invalid ciphertext: {}", name)]
+    InvalidCiphertext {
+        name: String,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("The {} key is not loaded", kind)]
+    KeyNotLoaded {
+        kind: KeyKind,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Failed deserializing {}: {}", arg, reason)]
+    DeserializationFailed {
+        arg: String,
+        reason: String,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Failed serializing {}", name)]
+    SerializationFailed {
+        name: String,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Type mismatch: {}", name)]
+    TypeMismatch {
+        name: String,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Unsupported operation: {}", name)]
+    UnsupportedOperation {
+        name: String,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Invalid argument: {}", name)]
+    InvalidArgument {
+        name: String,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Key mismatch: {}", name)]
+    KeyMismatch {
+        name: String,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("A different {} key is already loaded: {}", kind, name)]
+    AlreadyLoaded {
+        kind: KeyKind,
+        name: String,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
+    #[error("Out of gas: operation costs {} but only {} is left", cost, remaining)]
+    OutOfGas {
+        cost: u64,
+        remaining: u64,
+        #[cfg(feature = "backtraces")]
+        backtrace: Backtrace,
+    },
 }
 
 impl RustError {
@@ -105,6 +189,89 @@ impl RustError {
             backtrace: Backtrace::capture(),
         }
     }
+
+    pub fn invalid_ciphertext<T: Into<String>>(name: T) -> Self {
+        RustError::InvalidCiphertext {
+            name: name.into(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn key_not_loaded(kind: KeyKind) -> Self {
+        RustError::KeyNotLoaded {
+            kind,
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn deserialization_failed<A: Into<String>, R: fmt::Debug>(arg: A, reason: R) -> Self {
+        RustError::DeserializationFailed {
+            arg: arg.into(),
+            reason: format!("{:?}", reason),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn serialization_failed<T: Into<String>>(name: T) -> Self {
+        RustError::SerializationFailed {
+            name: name.into(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn type_mismatch<T: Into<String>>(name: T) -> Self {
+        RustError::TypeMismatch {
+            name: name.into(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn unsupported_operation<T: Into<String>>(name: T) -> Self {
+        RustError::UnsupportedOperation {
+            name: name.into(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn invalid_argument<T: Into<String>>(name: T) -> Self {
+        RustError::InvalidArgument {
+            name: name.into(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn key_mismatch<T: Into<String>>(name: T) -> Self {
+        RustError::KeyMismatch {
+            name: name.into(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn already_loaded<T: Into<String>>(kind: KeyKind, name: T) -> Self {
+        RustError::AlreadyLoaded {
+            kind,
+            name: name.into(),
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
+
+    pub fn out_of_gas(cost: u64, remaining: u64) -> Self {
+        RustError::OutOfGas {
+            cost,
+            remaining,
+            #[cfg(feature = "backtraces")]
+            backtrace: Backtrace::capture(),
+        }
+    }
 }
 
 impl From<std::str::Utf8Error> for RustError {
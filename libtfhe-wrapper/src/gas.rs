@@ -0,0 +1,270 @@
+//! Gas cost model for FHE operations.
+//!
+//! Costs are relative weights that roughly follow the number of programmable bootstraps each
+//! operation needs. Linear operations grow with the bit width of the type, multiplication and
+//! division grow with its square. A `Bool` is a single block (see `boolean`), so its operations
+//! cost a quarter of those of a `Uint8`, which has four blocks under the default parameters.
+
+use crate::api::{FheUintType, Op, UnaryOp};
+use crate::error::RustError;
+
+/// Passing this as a gas budget disables the budget check.
+pub const UNLIMITED_GAS: u64 = u64::MAX;
+
+const BITWISE_COST: u64 = 20_000;
+const ADD_SUB_COST: u64 = 60_000;
+const EQUALITY_COST: u64 = 50_000;
+const ORDERING_COST: u64 = 70_000;
+const MIN_MAX_COST: u64 = 120_000;
+const SHIFT_COST: u64 = 100_000;
+const MUL_COST: u64 = 150_000;
+const DIV_REM_COST: u64 = 500_000;
+const NOT_COST: u64 = 10_000;
+const CAST_COST: u64 = 30_000;
+const EXPAND_COST: u64 = 40_000;
+const TRIVIAL_ENCRYPT_COST: u64 = 5_000;
+
+const UINT8_BLOCKS: u64 = 4;
+
+/// The kinds of operations `operation_cost` can price.
+///
+/// cbindgen:prefix-with-name
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CostedOperation {
+    Math = 0,
+    ScalarMath = 1,
+    UnaryMath = 2,
+    Cast = 3,
+    Cmux = 4,
+    Comparison = 5,
+    Expand = 6,
+    TrivialEncrypt = 7,
+}
+
+fn width_factor(uint_type: FheUintType) -> u64 {
+    match uint_type {
+        FheUintType::Uint8 | FheUintType::Bool => 1,
+        FheUintType::Uint16 => 2,
+        FheUintType::Uint32 => 4,
+        FheUintType::Uint64 => 8,
+    }
+}
+
+/// Scales the `Uint8` cost of an operation with linear cost to `uint_type`.
+fn scale(cost: u64, uint_type: FheUintType) -> u64 {
+    match uint_type {
+        FheUintType::Bool => cost / UINT8_BLOCKS,
+        other => cost * width_factor(other),
+    }
+}
+
+/// Cost of `operation` on two ciphertexts of type `uint_type`.
+pub fn op_cost(operation: Op, uint_type: FheUintType) -> u64 {
+    let w = width_factor(uint_type);
+
+    let cost = match operation {
+        Op::BitAnd | Op::BitOr | Op::BitXor => BITWISE_COST,
+        Op::Add | Op::Sub => ADD_SUB_COST,
+        Op::Eq | Op::Ne => EQUALITY_COST,
+        Op::Lt | Op::Lte | Op::Gt | Op::Gte => ORDERING_COST,
+        Op::Min | Op::Max => MIN_MAX_COST,
+        Op::Shl | Op::Shr => SHIFT_COST,
+        Op::Mul => MUL_COST * w,
+        Op::Div | Op::Rem => DIV_REM_COST * w,
+    };
+
+    scale(cost, uint_type)
+}
+
+/// Cost of `operation` on a ciphertext and a plaintext. A known operand saves about half the
+/// work of the ciphertext-ciphertext version.
+pub fn scalar_op_cost(operation: Op, uint_type: FheUintType) -> u64 {
+    op_cost(operation, uint_type) / 2
+}
+
+pub fn unary_op_cost(operation: UnaryOp, uint_type: FheUintType) -> u64 {
+    match (operation, uint_type) {
+        // the logical not of a bool is a xor with 1 (see `math::compute_bool_not`)
+        (UnaryOp::Not, FheUintType::Bool) => op_cost(Op::BitXor, uint_type),
+        (UnaryOp::Not, _) => NOT_COST * width_factor(uint_type),
+    }
+}
+
+pub fn cast_cost(from_type: FheUintType, to_type: FheUintType) -> u64 {
+    if from_type == to_type {
+        return 0;
+    }
+
+    match (from_type, to_type) {
+        // a bool is narrowed from a comparison against zero, and widened by padding it with
+        // trivial zeros - neither needs a bootstrap of its own
+        (_, FheUintType::Bool) => op_cost(Op::Ne, from_type),
+        (FheUintType::Bool, _) => 0,
+        _ => CAST_COST * width_factor(from_type).max(width_factor(to_type)),
+    }
+}
+
+/// Cost of `comparison_operation`. Narrowing the result to a bool needs no bootstrap.
+pub fn comparison_cost(operation: Op, uint_type: FheUintType) -> u64 {
+    op_cost(operation, uint_type)
+}
+
+/// Cost of `cmux`, which is built from a subtraction, a not, two ands and an or (see
+/// `FheValue::cmux`), plus the widening of a `Bool` control to the type of the options.
+pub fn cmux_cost(control_type: FheUintType, uint_type: FheUintType) -> u64 {
+    cast_cost(control_type, uint_type)
+        + trivial_encrypt_cost(uint_type)
+        + op_cost(Op::Sub, uint_type)
+        + unary_op_cost(UnaryOp::Not, uint_type)
+        + 2 * op_cost(Op::BitAnd, uint_type)
+        + op_cost(Op::BitOr, uint_type)
+}
+
+/// Cost of `expand_compressed`. A compact `Bool` is a `Uint8`, which is compared against zero
+/// when it is expanded.
+pub fn expand_cost(uint_type: FheUintType) -> u64 {
+    match uint_type {
+        FheUintType::Bool => EXPAND_COST + op_cost(Op::Ne, FheUintType::Uint8),
+        _ => EXPAND_COST * width_factor(uint_type),
+    }
+}
+
+/// Cost of `expand_list` on a list of `len` values.
+pub fn expand_list_cost(uint_type: FheUintType, len: usize) -> u64 {
+    expand_cost(uint_type).saturating_mul(len as u64)
+}
+
+pub fn trivial_encrypt_cost(uint_type: FheUintType) -> u64 {
+    TRIVIAL_ENCRYPT_COST * width_factor(uint_type)
+}
+
+/// Prices any of the `CostedOperation`s.
+///
+/// `operation` is the numeric value of the `Op` (or `UnaryOp`) for `Math`, `ScalarMath`,
+/// `UnaryMath` and `Comparison`, and is ignored otherwise. `to_type` is only used by `Cast`, and
+/// is the control type for `Cmux`. An unknown `operation` costs `u64::MAX`, which exceeds every
+/// budget short of `UNLIMITED_GAS`.
+pub fn operation_cost_safe(
+    kind: CostedOperation,
+    operation: u32,
+    uint_type: FheUintType,
+    to_type: FheUintType,
+) -> u64 {
+    let cost = match kind {
+        CostedOperation::Math => Op::try_from(operation).map(|op| op_cost(op, uint_type)),
+        CostedOperation::ScalarMath => {
+            Op::try_from(operation).map(|op| scalar_op_cost(op, uint_type))
+        }
+        CostedOperation::UnaryMath => {
+            UnaryOp::try_from(operation).map(|op| unary_op_cost(op, uint_type))
+        }
+        CostedOperation::Cast => Ok(cast_cost(uint_type, to_type)),
+        CostedOperation::Cmux => Ok(cmux_cost(to_type, uint_type)),
+        CostedOperation::Comparison => {
+            Op::try_from(operation).map(|op| comparison_cost(op, uint_type))
+        }
+        CostedOperation::Expand => Ok(expand_cost(uint_type)),
+        CostedOperation::TrivialEncrypt => Ok(trivial_encrypt_cost(uint_type)),
+    };
+
+    cost.unwrap_or(u64::MAX)
+}
+
+/// Fails with `RustError::OutOfGas` if `cost` does not fit in `gas_budget`.
+pub fn check_gas(cost: u64, gas_budget: u64) -> Result<(), RustError> {
+    if cost > gas_budget {
+        log::debug!("out of gas: cost {} exceeds budget {}", cost, gas_budget);
+        return Err(RustError::out_of_gas(cost, gas_budget));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPES: [FheUintType; 4] = [
+        FheUintType::Uint8,
+        FheUintType::Uint16,
+        FheUintType::Uint32,
+        FheUintType::Uint64,
+    ];
+
+    #[test]
+    fn costs_grow_with_width() {
+        for op in 0..18u32 {
+            let op = Op::try_from(op).unwrap();
+            for pair in TYPES.windows(2) {
+                assert!(op_cost(op, pair[0]) < op_cost(op, pair[1]), "{:?}", op);
+                assert!(
+                    scalar_op_cost(op, pair[0]) < op_cost(op, pair[0]),
+                    "{:?}",
+                    op
+                );
+            }
+        }
+
+        assert!(op_cost(Op::Div, FheUintType::Uint64) > op_cost(Op::Add, FheUintType::Uint64));
+        assert!(op_cost(Op::BitAnd, FheUintType::Bool) < op_cost(Op::BitAnd, FheUintType::Uint8));
+    }
+
+    #[test]
+    fn unknown_operations_exceed_any_budget() {
+        for kind in [CostedOperation::Math, CostedOperation::UnaryMath] {
+            let cost = operation_cost_safe(kind, 18, FheUintType::Uint8, FheUintType::Uint8);
+            assert_eq!(cost, u64::MAX);
+        }
+    }
+
+    #[test]
+    fn identity_cast_is_free() {
+        for uint_type in TYPES {
+            assert_eq!(cast_cost(uint_type, uint_type), 0);
+        }
+        assert!(
+            cast_cost(FheUintType::Uint32, FheUintType::Bool)
+                > cast_cost(FheUintType::Uint32, FheUintType::Uint8)
+        );
+    }
+
+    #[test]
+    fn widening_a_bool_costs_no_more_than_an_identity_cast() {
+        for uint_type in TYPES {
+            assert_eq!(
+                cast_cost(FheUintType::Bool, uint_type),
+                cast_cost(uint_type, uint_type)
+            );
+        }
+    }
+
+    #[test]
+    fn bool_controls_are_widened_for_free() {
+        for uint_type in TYPES {
+            assert_eq!(
+                cmux_cost(FheUintType::Bool, uint_type),
+                cmux_cost(uint_type, uint_type)
+            );
+        }
+        assert!(
+            cmux_cost(FheUintType::Bool, FheUintType::Bool)
+                < cmux_cost(FheUintType::Uint8, FheUintType::Uint8)
+        );
+    }
+
+    #[test]
+    fn budget_is_enforced() {
+        assert!(check_gas(100, 100).is_ok());
+        assert!(check_gas(0, 0).is_ok());
+        assert!(check_gas(100, UNLIMITED_GAS).is_ok());
+        assert!(matches!(
+            check_gas(101, 100),
+            Err(RustError::OutOfGas {
+                cost: 101,
+                remaining: 100,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use once_cell::sync::Lazy;
+
+use crate::error::RustError;
+use crate::keys::{GlobalKeys, KeySetId};
+use crate::value::FheValue;
+
+/// Handle value that never refers to a live ciphertext. FFI calls return it on error.
+pub const INVALID_HANDLE: u64 = 0;
+
+/// An arena of live ciphertexts, keyed by an opaque `u64` handle.
+///
+/// Values are stored behind an `Arc` and handed out as `Arc` clones, so the lock is only held to
+/// bump a reference count - never while copying a ciphertext or doing FHE work - and independent
+/// handles can be operated on from multiple threads.
+///
+/// Every value remembers the key set it was created under, and can only be read back while that
+/// key set is active.
+pub struct HandleArena {
+    next_handle: u64,
+    values: HashMap<u64, (KeySetId, Arc<FheValue>)>,
+}
+
+impl Default for HandleArena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HandleArena {
+    pub fn new() -> Self {
+        Self {
+            next_handle: INVALID_HANDLE + 1,
+            values: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn insert(&mut self, value: FheValue) -> u64 {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.values
+            .insert(handle, (GlobalKeys::active_key_set(), Arc::new(value)));
+        handle
+    }
+
+    pub(crate) fn get(&self, handle: u64) -> Result<Arc<FheValue>, RustError> {
+        let (key_set, value) = self.values.get(&handle).ok_or_else(|| {
+            log::error!("unknown ciphertext handle: {}", handle);
+            RustError::invalid_argument(format!("unknown ciphertext handle: {}", handle))
+        })?;
+
+        let active_key_set = GlobalKeys::active_key_set();
+        if *key_set != active_key_set {
+            log::error!(
+                "handle {} belongs to key set {}, not {}",
+                handle,
+                key_set,
+                active_key_set
+            );
+            return Err(RustError::invalid_argument(format!(
+                "handle {} belongs to key set {}",
+                handle, key_set
+            )));
+        }
+
+        Ok(Arc::clone(value))
+    }
+
+    pub(crate) fn remove(&mut self, handle: u64) -> bool {
+        self.values.remove(&handle).is_some()
+    }
+}
+
+pub static HANDLES: Lazy<Mutex<HandleArena>> = Lazy::new(|| Mutex::new(HandleArena::new()));
+
+pub(crate) fn insert_value(value: FheValue) -> u64 {
+    HANDLES.lock().unwrap().insert(value)
+}
+
+pub(crate) fn get_value(handle: u64) -> Result<Arc<FheValue>, RustError> {
+    HANDLES.lock().unwrap().get(handle)
+}
+
+pub(crate) fn free_value(handle: u64) -> bool {
+    HANDLES.lock().unwrap().remove(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{FheUintType, Op, UnaryOp};
+    use crate::encryption::{decrypt_safe, encrypt_safe, expand_compressed_safe};
+    use crate::keys::DEFAULT_KEY_SET;
+    use crate::test_utils::setup_keys;
+
+    fn encrypted_handle(msg: u64, uint_type: FheUintType) -> u64 {
+        let compact = encrypt_safe(msg, uint_type).unwrap();
+        let expanded = expand_compressed_safe(&compact, uint_type).unwrap();
+        insert_value(FheValue::from_bytes(&expanded, uint_type).unwrap())
+    }
+
+    #[test]
+    fn chained_operations_on_handles() {
+        setup_keys();
+
+        let a = encrypted_handle(10, FheUintType::Uint32);
+        let b = encrypted_handle(32, FheUintType::Uint32);
+
+        // (a + b) * 2, then !((a + b) * 2)
+        let sum = get_value(a)
+            .unwrap()
+            .op(&get_value(b).unwrap(), Op::Add)
+            .unwrap();
+        let doubled = sum.scalar_op(2, Op::Mul).unwrap();
+        let result = insert_value(doubled);
+
+        let bytes = get_value(result).unwrap().to_bytes().unwrap();
+        assert_eq!(decrypt_safe(&bytes, FheUintType::Uint32).unwrap(), 84);
+
+        let not = get_value(result).unwrap().unary_op(UnaryOp::Not).unwrap();
+        let bytes = not.to_bytes().unwrap();
+        assert_eq!(
+            decrypt_safe(&bytes, FheUintType::Uint32).unwrap(),
+            !84u32 as u64
+        );
+
+        for handle in [a, b, result] {
+            assert!(free_value(handle));
+            assert!(!free_value(handle));
+            assert!(get_value(handle).is_err());
+        }
+    }
+
+    #[test]
+    fn mismatched_handle_types_fail() {
+        setup_keys();
+
+        let a = encrypted_handle(1, FheUintType::Uint8);
+        let b = encrypted_handle(1, FheUintType::Uint16);
+
+        let result = get_value(a).unwrap().op(&get_value(b).unwrap(), Op::Add);
+        assert!(matches!(result, Err(RustError::TypeMismatch { .. })));
+
+        free_value(a);
+        free_value(b);
+    }
+
+    #[test]
+    fn handles_are_bound_to_their_key_set() {
+        setup_keys();
+
+        let a = encrypted_handle(1, FheUintType::Uint8);
+        {
+            let _other = GlobalKeys::use_key_set(DEFAULT_KEY_SET + 1);
+            assert!(matches!(
+                get_value(a),
+                Err(RustError::InvalidArgument { .. })
+            ));
+        }
+        assert!(get_value(a).is_ok());
+
+        free_value(a);
+    }
+
+    #[test]
+    fn handles_share_their_value() {
+        setup_keys();
+
+        let a = encrypted_handle(1, FheUintType::Uint8);
+        let first = get_value(a).unwrap();
+        let second = get_value(a).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // freeing the handle leaves values already handed out intact
+        assert!(free_value(a));
+        assert_eq!(first.uint_type(), FheUintType::Uint8);
+    }
+
+    #[test]
+    fn invalid_handle_is_never_live() {
+        assert!(get_value(INVALID_HANDLE).is_err());
+        assert!(!free_value(INVALID_HANDLE));
+    }
+}
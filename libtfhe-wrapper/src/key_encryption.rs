@@ -0,0 +1,245 @@
+//! Passphrase-encrypted key files, so secret keys are never stored on disk in the clear.
+//!
+//! An encrypted key file wraps a whole key file (see `key_file`):
+//!
+//! | bytes  | field                                           |
+//! |--------|-------------------------------------------------|
+//! | 0..4   | magic, `b"FHKE"`                                |
+//! | 4      | format version                                  |
+//! | 5..9   | Argon2id memory cost in KiB, little endian      |
+//! | 9..13  | Argon2id iterations, little endian              |
+//! | 13..17 | Argon2id parallelism, little endian             |
+//! | 17..33 | Argon2id salt                                   |
+//! | 33..57 | XChaCha20-Poly1305 nonce                        |
+//! | 57..   | encrypted key file, followed by the 16 byte tag |
+//!
+//! The encryption key is derived from the passphrase with Argon2id, and the header is
+//! authenticated along with the key, so tampering with the KDF parameters is detected as well.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use zeroize::Zeroizing;
+
+use crate::error::RustError;
+
+pub const ENCRYPTED_KEY_MAGIC: [u8; 4] = *b"FHKE";
+pub const ENCRYPTED_KEY_VERSION: u8 = 1;
+pub const ENCRYPTED_KEY_HEADER_LEN: usize = 57;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// The most memory a key file may ask the KDF for, so a crafted file cannot exhaust the node.
+const MAX_MEMORY_COST_KIB: u32 = 1 << 21;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 16;
+
+/// Argon2id cost parameters, recorded in every encrypted key file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    pub memory_cost_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    /// 64 MiB and 3 passes - about a second on a validator, and costly to brute force.
+    fn default() -> Self {
+        Self {
+            memory_cost_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+        }
+    }
+}
+
+impl KdfParams {
+    fn derive_key(self, passphrase: &[u8], salt: &[u8]) -> Result<Zeroizing<[u8; 32]>, RustError> {
+        if self.memory_cost_kib > MAX_MEMORY_COST_KIB
+            || self.iterations > MAX_ITERATIONS
+            || self.parallelism > MAX_PARALLELISM
+        {
+            log::error!("key file asks for excessive KDF parameters: {:?}", self);
+            return Err(RustError::invalid_argument(
+                "key file asks for excessive KDF parameters",
+            ));
+        }
+
+        let params = Params::new(
+            self.memory_cost_kib,
+            self.iterations,
+            self.parallelism,
+            Some(32),
+        )
+        .map_err(|err| {
+            log::error!("invalid KDF parameters {:?}: {}", self, err);
+            RustError::invalid_argument(format!("invalid KDF parameters: {}", err))
+        })?;
+
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase, salt, &mut key[..])
+            .map_err(|err| {
+                log::error!("failed deriving key file key: {}", err);
+                RustError::generic_error("failed deriving key file key")
+            })?;
+        Ok(key)
+    }
+}
+
+/// Whether `bytes` hold an encrypted key file rather than a plain one.
+pub fn is_encrypted_key(bytes: &[u8]) -> bool {
+    bytes.len() >= ENCRYPTED_KEY_HEADER_LEN && bytes[0..4] == ENCRYPTED_KEY_MAGIC
+}
+
+/// Encrypts a key file under `passphrase`, with the default KDF parameters.
+pub fn encrypt_key(key: &[u8], passphrase: &[u8]) -> Result<Vec<u8>, RustError> {
+    encrypt_key_with_params(key, passphrase, KdfParams::default())
+}
+
+pub fn encrypt_key_with_params(
+    key: &[u8],
+    passphrase: &[u8],
+    params: KdfParams,
+) -> Result<Vec<u8>, RustError> {
+    if passphrase.is_empty() {
+        return Err(RustError::invalid_argument("key passphrase is empty"));
+    }
+
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let mut encrypted = Vec::with_capacity(ENCRYPTED_KEY_HEADER_LEN + key.len() + 16);
+    encrypted.extend_from_slice(&ENCRYPTED_KEY_MAGIC);
+    encrypted.push(ENCRYPTED_KEY_VERSION);
+    encrypted.extend_from_slice(&params.memory_cost_kib.to_le_bytes());
+    encrypted.extend_from_slice(&params.iterations.to_le_bytes());
+    encrypted.extend_from_slice(&params.parallelism.to_le_bytes());
+    encrypted.extend_from_slice(&salt);
+    encrypted.extend_from_slice(&nonce);
+
+    let cipher_key = params.derive_key(passphrase, &salt)?;
+    let ciphertext = XChaCha20Poly1305::new(Key::from_slice(&cipher_key[..]))
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: key,
+                aad: &encrypted,
+            },
+        )
+        .map_err(|_| {
+            log::error!("failed encrypting key file");
+            RustError::generic_error("failed encrypting key file")
+        })?;
+
+    encrypted.extend_from_slice(&ciphertext);
+    Ok(encrypted)
+}
+
+/// Decrypts an encrypted key file. The returned key file is wiped when dropped.
+///
+/// A wrong passphrase and a tampered file cannot be told apart - both fail with
+/// `RustError::DeserializationFailed`.
+pub fn decrypt_key(bytes: &[u8], passphrase: &[u8]) -> Result<Zeroizing<Vec<u8>>, RustError> {
+    if !is_encrypted_key(bytes) {
+        return Err(RustError::deserialization_failed(
+            "encrypted key",
+            "not an encrypted key file",
+        ));
+    }
+    if bytes[4] != ENCRYPTED_KEY_VERSION {
+        log::error!("unsupported encrypted key file version {}", bytes[4]);
+        return Err(RustError::deserialization_failed(
+            "encrypted key",
+            format!("unsupported encrypted key file version {}", bytes[4]),
+        ));
+    }
+
+    let read_u32 = |offset: usize| {
+        let mut le_bytes = [0u8; 4];
+        le_bytes.copy_from_slice(&bytes[offset..offset + 4]);
+        u32::from_le_bytes(le_bytes)
+    };
+    let params = KdfParams {
+        memory_cost_kib: read_u32(5),
+        iterations: read_u32(9),
+        parallelism: read_u32(13),
+    };
+    let salt = &bytes[17..17 + SALT_LEN];
+    let nonce = XNonce::from_slice(&bytes[17 + SALT_LEN..17 + SALT_LEN + NONCE_LEN]);
+
+    let cipher_key = params.derive_key(passphrase, salt)?;
+    XChaCha20Poly1305::new(Key::from_slice(&cipher_key[..]))
+        .decrypt(
+            nonce,
+            Payload {
+                msg: &bytes[ENCRYPTED_KEY_HEADER_LEN..],
+                aad: &bytes[..ENCRYPTED_KEY_HEADER_LEN],
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| {
+            log::error!("failed decrypting key file: wrong passphrase or corrupted file");
+            RustError::deserialization_failed("encrypted key", "wrong passphrase or corrupted file")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cheap parameters, so the tests do not spend seconds in the KDF.
+    const TEST_PARAMS: KdfParams = KdfParams {
+        memory_cost_kib: 1024,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn encrypted_keys_round_trip() {
+        let encrypted = encrypt_key_with_params(b"secret key", b"passphrase", TEST_PARAMS).unwrap();
+        assert!(is_encrypted_key(&encrypted));
+        assert!(!encrypted
+            .windows(b"secret key".len())
+            .any(|window| window == b"secret key"));
+
+        let decrypted = decrypt_key(&encrypted, b"passphrase").unwrap();
+        assert_eq!(decrypted.as_slice(), b"secret key");
+    }
+
+    #[test]
+    fn wrong_passphrases_and_tampering_are_rejected() {
+        let encrypted = encrypt_key_with_params(b"secret key", b"passphrase", TEST_PARAMS).unwrap();
+        assert!(matches!(
+            decrypt_key(&encrypted, b"wrong"),
+            Err(RustError::DeserializationFailed { .. })
+        ));
+
+        // the header is authenticated too
+        let mut tampered = encrypted.clone();
+        tampered[9] += 1;
+        assert!(decrypt_key(&tampered, b"passphrase").is_err());
+
+        let mut tampered = encrypted;
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt_key(&tampered, b"passphrase").is_err());
+
+        assert!(decrypt_key(b"secret key", b"passphrase").is_err());
+        assert!(encrypt_key_with_params(b"secret key", b"", TEST_PARAMS).is_err());
+    }
+
+    #[test]
+    fn excessive_kdf_parameters_are_rejected() {
+        let params = KdfParams {
+            memory_cost_kib: MAX_MEMORY_COST_KIB + 1,
+            ..TEST_PARAMS
+        };
+        assert!(matches!(
+            encrypt_key_with_params(b"secret key", b"passphrase", params),
+            Err(RustError::InvalidArgument { .. })
+        ));
+    }
+}
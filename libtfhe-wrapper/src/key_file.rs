@@ -0,0 +1,274 @@
+//! Self-describing wrapper around serialized keys.
+//!
+//! Generated keys are prefixed with a small header:
+//!
+//! | bytes | field                                                   |
+//! |-------|---------------------------------------------------------|
+//! | 0..4  | magic, `b"FHKY"`                                        |
+//! | 4     | format version                                          |
+//! | 5     | `KeyKind` tag, 3 for a compressed server                |
+//! | 6     | `ParameterProfile` tag                                  |
+//! | 7..15 | fingerprint of the key set the key was generated with   |
+//! | 15..  | bincode encoded key                                     |
+//!
+//! The profile lets loaders refuse to mix keys generated with different parameters into one key
+//! set. The key set fingerprint is the same for every key of one generation, so a node that only
+//! loads a server key stamps its ciphertexts with the same fingerprint as one that also holds the
+//! public key (see `envelope`).
+//!
+//! Version 1 headers end after the profile and record no key set fingerprint. Keys without a
+//! header (plain bincode, as written before profiles existed or as returned by `get_public_key`)
+//! are still accepted, with an unknown profile. Compressed server keys can only be told apart by
+//! their header, so they always need one.
+
+use crate::error::{KeyKind, RustError};
+use crate::key_encryption::is_encrypted_key;
+use crate::keys::KeyFingerprint;
+use crate::params::ParameterProfile;
+
+pub const KEY_FILE_MAGIC: [u8; 4] = *b"FHKY";
+pub const KEY_FILE_VERSION: u8 = 2;
+pub const KEY_FILE_HEADER_LEN: usize = 15;
+
+const KEY_FILE_V1_HEADER_LEN: usize = 7;
+
+const COMPRESSED_SERVER_KEY_TAG: u8 = 3;
+
+fn kind_tag(kind: KeyKind) -> u8 {
+    match kind {
+        KeyKind::Server => 0,
+        KeyKind::Client => 1,
+        KeyKind::Public => 2,
+    }
+}
+
+/// The kind of key a tag stands for, and whether it is a compressed server key.
+fn kind_from_tag(tag: u8) -> Option<(KeyKind, bool)> {
+    match tag {
+        0 => Some((KeyKind::Server, false)),
+        1 => Some((KeyKind::Client, false)),
+        2 => Some((KeyKind::Public, false)),
+        COMPRESSED_SERVER_KEY_TAG => Some((KeyKind::Server, true)),
+        _ => None,
+    }
+}
+
+/// A parsed key file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyFile<'a> {
+    /// `None` for keys without a header.
+    pub profile: Option<ParameterProfile>,
+    /// Set for server keys stored in tfhe's compressed (seeded) form.
+    pub compressed: bool,
+    /// Fingerprint of the key set the key was generated with. `None` for keys without a header
+    /// or with a version 1 header.
+    pub key_set_fingerprint: Option<KeyFingerprint>,
+    /// The bincode encoded key.
+    pub payload: &'a [u8],
+}
+
+fn seal_tagged(
+    tag: u8,
+    profile: ParameterProfile,
+    key_set_fingerprint: KeyFingerprint,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut sealed = Vec::with_capacity(KEY_FILE_HEADER_LEN + payload.len());
+    sealed.extend_from_slice(&KEY_FILE_MAGIC);
+    sealed.push(KEY_FILE_VERSION);
+    sealed.push(tag);
+    sealed.push(profile.tag());
+    sealed.extend_from_slice(&key_set_fingerprint);
+    sealed.extend_from_slice(payload);
+    sealed
+}
+
+/// Wraps a bincode encoded key in a header recording its kind, parameter profile and the key set
+/// it belongs to.
+pub fn seal_key(
+    kind: KeyKind,
+    profile: ParameterProfile,
+    key_set_fingerprint: KeyFingerprint,
+    payload: &[u8],
+) -> Vec<u8> {
+    seal_tagged(kind_tag(kind), profile, key_set_fingerprint, payload)
+}
+
+/// Like `seal_key`, for a bincode encoded `CompressedServerKey`.
+pub fn seal_compressed_server_key(
+    profile: ParameterProfile,
+    key_set_fingerprint: KeyFingerprint,
+    payload: &[u8],
+) -> Vec<u8> {
+    seal_tagged(
+        COMPRESSED_SERVER_KEY_TAG,
+        profile,
+        key_set_fingerprint,
+        payload,
+    )
+}
+
+/// Parses a serialized `kind` key. Server keys may be compressed.
+///
+/// Fails with `RustError::DeserializationFailed` if the header is malformed, holds a key of
+/// another kind or the file is encrypted (see `key_encryption`). Keys without a header are
+/// returned as is, with no profile.
+pub fn open_key(bytes: &[u8], kind: KeyKind) -> Result<KeyFile<'_>, RustError> {
+    let arg = format!("{} key", kind);
+
+    if is_encrypted_key(bytes) {
+        log::error!("{} is encrypted", arg);
+        return Err(RustError::deserialization_failed(
+            arg,
+            "key file is encrypted, load it with its passphrase",
+        ));
+    }
+
+    if bytes.len() < KEY_FILE_HEADER_LEN || bytes[0..4] != KEY_FILE_MAGIC {
+        log::warn!("{} has no key file header, assuming a legacy key", arg);
+        return Ok(KeyFile {
+            profile: None,
+            compressed: false,
+            key_set_fingerprint: None,
+            payload: bytes,
+        });
+    }
+
+    let header_len = match bytes[4] {
+        1 => KEY_FILE_V1_HEADER_LEN,
+        KEY_FILE_VERSION if bytes.len() >= KEY_FILE_HEADER_LEN => KEY_FILE_HEADER_LEN,
+        KEY_FILE_VERSION => {
+            log::error!("{} has a truncated key file header", arg);
+            return Err(RustError::deserialization_failed(
+                arg,
+                "truncated key file header",
+            ));
+        }
+        version => {
+            log::error!("unsupported key file version {}", version);
+            return Err(RustError::deserialization_failed(
+                arg,
+                format!("unsupported key file version {}", version),
+            ));
+        }
+    };
+
+    let compressed = match kind_from_tag(bytes[5]) {
+        Some((found, compressed)) if found == kind => compressed,
+        Some((found, _)) => {
+            log::error!("expected a {} key, got a {} key", kind, found);
+            return Err(RustError::deserialization_failed(
+                arg,
+                format!("got a {} key", found),
+            ));
+        }
+        None => {
+            log::error!("unknown key kind tag {}", bytes[5]);
+            return Err(RustError::deserialization_failed(
+                arg,
+                format!("unknown key kind tag {}", bytes[5]),
+            ));
+        }
+    };
+
+    let profile = ParameterProfile::from_tag(bytes[6]).ok_or_else(|| {
+        log::error!("unknown parameter profile tag {}", bytes[6]);
+        RustError::deserialization_failed(
+            arg,
+            format!("unknown parameter profile tag {}", bytes[6]),
+        )
+    })?;
+
+    let key_set_fingerprint = (header_len == KEY_FILE_HEADER_LEN).then(|| {
+        let mut fingerprint = KeyFingerprint::default();
+        fingerprint.copy_from_slice(&bytes[KEY_FILE_V1_HEADER_LEN..KEY_FILE_HEADER_LEN]);
+        fingerprint
+    });
+
+    Ok(KeyFile {
+        profile: Some(profile),
+        compressed,
+        key_set_fingerprint,
+        payload: &bytes[header_len..],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_SET: KeyFingerprint = [7; 8];
+
+    #[test]
+    fn seal_and_open_round_trip() {
+        let sealed = seal_key(
+            KeyKind::Server,
+            ParameterProfile::Message1Carry1PbsKs,
+            KEY_SET,
+            &[1, 2, 3],
+        );
+        assert_eq!(sealed.len(), KEY_FILE_HEADER_LEN + 3);
+
+        let key = open_key(&sealed, KeyKind::Server).unwrap();
+        assert_eq!(key.profile, Some(ParameterProfile::Message1Carry1PbsKs));
+        assert!(!key.compressed);
+        assert_eq!(key.key_set_fingerprint, Some(KEY_SET));
+        assert_eq!(key.payload, &[1, 2, 3]);
+
+        let compressed =
+            seal_compressed_server_key(ParameterProfile::default(), KEY_SET, &[1, 2, 3]);
+        let key = open_key(&compressed, KeyKind::Server).unwrap();
+        assert!(key.compressed);
+        assert_eq!(key.payload, &[1, 2, 3]);
+        assert!(open_key(&compressed, KeyKind::Client).is_err());
+    }
+
+    #[test]
+    fn legacy_keys_have_no_profile() {
+        let key = open_key(&[1, 2, 3], KeyKind::Client).unwrap();
+        assert_eq!(key.profile, None);
+        assert!(!key.compressed);
+        assert_eq!(key.key_set_fingerprint, None);
+        assert_eq!(key.payload, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn version_1_keys_have_no_key_set_fingerprint() {
+        let sealed = seal_key(
+            KeyKind::Public,
+            ParameterProfile::default(),
+            KEY_SET,
+            &[1, 2, 3],
+        );
+        let mut version_1 = sealed[..KEY_FILE_V1_HEADER_LEN].to_vec();
+        version_1[4] = 1;
+        version_1.extend_from_slice(&[1, 2, 3]);
+
+        let key = open_key(&version_1, KeyKind::Public).unwrap();
+        assert_eq!(key.profile, Some(ParameterProfile::default()));
+        assert_eq!(key.key_set_fingerprint, None);
+        assert_eq!(key.payload, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn malformed_headers_are_rejected() {
+        let sealed = seal_key(
+            KeyKind::Public,
+            ParameterProfile::default(),
+            KEY_SET,
+            &[1, 2, 3],
+        );
+        assert!(matches!(
+            open_key(&sealed, KeyKind::Server),
+            Err(RustError::DeserializationFailed { .. })
+        ));
+
+        let mut bad_version = sealed.clone();
+        bad_version[4] = KEY_FILE_VERSION + 1;
+        assert!(open_key(&bad_version, KeyKind::Public).is_err());
+
+        let mut bad_profile = sealed;
+        bad_profile[6] = 42;
+        assert!(open_key(&bad_profile, KeyKind::Public).is_err());
+    }
+}
@@ -1,46 +1,331 @@
-use std::collections::HashSet;
-use std::sync::Mutex;
-use std::{thread, thread::ThreadId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::mem::ManuallyDrop;
+use std::sync::{Arc, RwLock};
 
-use once_cell::sync::OnceCell;
-use tfhe::shortint::parameters::PARAM_MESSAGE_2_CARRY_2_KS_PBS as KEYGEN_PARAMS;
-use tfhe::{ClientKey, CompactPublicKey, ConfigBuilder, ServerKey};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tfhe::core_crypto::prelude::{ActivatedRandomGenerator, DeterministicSeeder, Seed};
+use tfhe::shortint::engine::ShortintEngine;
+use tfhe::{ClientKey, CompactPublicKey, CompressedServerKey, ServerKey};
+use zeroize::{Zeroize, Zeroizing};
 
-use crate::error::RustError;
-pub struct InitGuard {
-    key: Option<ServerKey>,
-    init_threads: HashSet<ThreadId>,
+use crate::api::{math_operation_helper, FheUintType, Op};
+use crate::encryption::{decrypt_safe, encrypt_safe, expand_compressed_safe};
+use crate::error::{KeyKind, RustError};
+use crate::key_encryption::decrypt_key;
+use crate::key_file::{open_key, seal_compressed_server_key, seal_key, KeyFile};
+use crate::mock::is_mock_backend;
+use crate::params::ParameterProfile;
+
+/// Identifies one of the key sets loaded side by side, e.g. one per network.
+pub type KeySetId = u32;
+
+/// The key set used by callers that do not care about multiple key sets.
+pub const DEFAULT_KEY_SET: KeySetId = 0;
+
+/// A server key together with its epoch within the key set. The epoch starts at 1 and is bumped
+/// every time a different server key is loaded into the key set.
+#[derive(Clone)]
+struct LoadedServerKey {
+    epoch: u64,
+    fingerprint: KeyFingerprint,
+    /// The key set fingerprint recorded in the key file, if it had one.
+    key_set_fingerprint: Option<KeyFingerprint>,
+    key: Arc<ServerKey>,
+}
+
+/// The keys of a single key set. Every kind is optional, as most processes only need some of
+/// them.
+#[derive(Default)]
+struct KeySet {
+    server_key: Option<LoadedServerKey>,
+    client_key: Option<Arc<SecretClientKey>>,
+    public_key: Option<Arc<CompactPublicKey>>,
+    /// Key set fingerprint recorded by the client or public key, or derived from a public key
+    /// without one. See `KeySet::fingerprint`.
+    fingerprint: Option<KeyFingerprint>,
+    /// Parameter profile of the keys, taken from the first key file that recorded one.
+    profile: Option<ParameterProfile>,
+    /// Metadata of the loaded keys, at most one entry per kind.
+    key_info: Vec<KeyInfo>,
+}
+
+/// A client key whose secret key buffers are wiped when it is dropped.
+///
+/// The key is deserialized once, when it is loaded, and lives until it is unloaded and the last
+/// operation holding it finishes. tfhe does not wipe its keys on drop, so the key is taken apart
+/// into its LWE and GLWE secret keys, which are overwritten instead.
+pub struct SecretClientKey {
+    key: ManuallyDrop<ClientKey>,
 }
 
-impl Default for InitGuard {
-    fn default() -> Self {
-        Self::new()
+impl SecretClientKey {
+    /// Deserializes a bincode encoded `ClientKey`.
+    fn new(serialized: &[u8]) -> Result<Self, RustError> {
+        let key = bincode::deserialize::<ClientKey>(serialized).map_err(|err| {
+            log::error!("failed deserializing client key: {:?}", err);
+            RustError::deserialization_failed("client key", err)
+        })?;
+
+        Ok(SecretClientKey {
+            key: ManuallyDrop::new(key),
+        })
+    }
+
+    /// Runs `f` with the key.
+    pub fn with_key<T>(
+        &self,
+        f: impl FnOnce(&ClientKey) -> Result<T, RustError>,
+    ) -> Result<T, RustError> {
+        f(&self.key)
     }
 }
 
-impl InitGuard {
-    pub fn new() -> Self {
-        Self {
-            key: None,
-            init_threads: HashSet::new(),
+impl Drop for SecretClientKey {
+    fn drop(&mut self) {
+        // SAFETY: the key is not touched again after being taken out here
+        let key = unsafe { ManuallyDrop::take(&mut self.key) };
+
+        // parameter profiles only enable integers, so there is no boolean key to wipe
+        let (_, _, integer_key) = key.into_raw_parts();
+        if let Some(integer_key) = integer_key {
+            let (glwe_secret_key, lwe_secret_key, _) =
+                integer_key.into_raw_parts().into_raw_parts();
+            glwe_secret_key.into_container().zeroize();
+            lwe_secret_key.into_container().zeroize();
         }
     }
+}
 
-    pub fn is_key_set(&self) -> bool {
-        self.key.is_some()
+/// Metadata about a loaded key, recorded when it is loaded so it can be reported without going
+/// back to the (large) serialized key. See `key_info_safe`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct KeyInfo {
+    pub kind: KeyKind,
+    /// `key_fingerprint` of the bincode encoded key. Stable across reloads and processes.
+    pub fingerprint: KeyFingerprint,
+    /// `None` for keys whose file did not record a profile.
+    pub profile: Option<ParameterProfile>,
+    /// Bit widths of the integer types the key works with.
+    pub integer_widths: Vec<u32>,
+    /// Size of the bincode encoded key in bytes, as it was loaded.
+    pub serialized_size: u64,
+    /// Set for server keys loaded in compressed form. Fingerprint and size are those of the
+    /// compressed key, so the compressed and full form of one key have different fingerprints.
+    pub compressed: bool,
+}
+
+impl KeyInfo {
+    /// Describes the `kind` key of `key_file`.
+    pub fn new(kind: KeyKind, key_file: &KeyFile) -> Self {
+        KeyInfo {
+            kind,
+            fingerprint: key_fingerprint(key_file.payload),
+            profile: key_file.profile,
+            integer_widths: key_file
+                .profile
+                .unwrap_or_default()
+                .integer_widths()
+                .to_vec(),
+            serialized_size: key_file.payload.len() as u64,
+            compressed: key_file.compressed,
+        }
+    }
+}
+
+impl KeySet {
+    /// Fails with `RustError::KeyMismatch` if a `kind` key generated with `profile` does not
+    /// belong with the keys already in the key set, and records the profile otherwise. Keys with
+    /// an unknown profile are let through.
+    fn check_profile(
+        &mut self,
+        key_set: KeySetId,
+        kind: KeyKind,
+        profile: Option<ParameterProfile>,
+    ) -> Result<(), RustError> {
+        match (self.profile, profile) {
+            (Some(current), Some(profile)) if current != profile => {
+                log::error!(
+                    "{} key uses parameters {}, but key set {} uses {}",
+                    kind,
+                    profile.name(),
+                    key_set,
+                    current.name()
+                );
+                Err(RustError::key_mismatch(format!(
+                    "{} key uses parameters {}, but key set {} uses {}",
+                    kind,
+                    profile.name(),
+                    key_set,
+                    current.name()
+                )))
+            }
+            (None, Some(profile)) => {
+                self.profile = Some(profile);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
     }
 
-    pub fn set_key(&mut self, key: ServerKey) {
-        self.key = Some(key);
+    /// The fingerprint stamped into the envelopes of the key set.
+    ///
+    /// Keys record the fingerprint of the key set they were generated with (see `key_file`), so
+    /// every key of a generation yields the same one - in particular a compute-only key set with
+    /// just a server key. Public keys without a recorded fingerprint fall back to their own, which
+    /// is what key generation records. Only a key set whose only key is a server key without a
+    /// recorded fingerprint falls back to the fingerprint of that key.
+    fn fingerprint(&self) -> Option<KeyFingerprint> {
+        self.fingerprint.or_else(|| {
+            self.server_key.as_ref().map(|server_key| {
+                server_key
+                    .key_set_fingerprint
+                    .unwrap_or(server_key.fingerprint)
+            })
+        })
     }
 
-    pub fn ensure_init(&mut self) {
-        match &self.key {
-            None => panic!("Server Key not set"),
-            Some(key) => match self.init_threads.insert(thread::current().id()) {
-                false => {} // thread already set key
-                true => tfhe::set_server_key(key.clone()),
-            },
+    /// Fails with `RustError::KeyMismatch` if a `kind` key of the key set `fingerprint` (if known)
+    /// does not belong with the keys already in the key set. A server key is not compared with
+    /// the server key it replaces, so it can be rotated to another generation.
+    fn check_fingerprint(
+        &self,
+        key_set: KeySetId,
+        kind: KeyKind,
+        fingerprint: Option<KeyFingerprint>,
+    ) -> Result<(), RustError> {
+        let server_fingerprint = match kind {
+            KeyKind::Server => None,
+            _ => self
+                .server_key
+                .as_ref()
+                .and_then(|server_key| server_key.key_set_fingerprint),
+        };
+
+        let current = match self.fingerprint.or(server_fingerprint) {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+        match fingerprint {
+            Some(fingerprint) if fingerprint != current => {
+                log::error!(
+                    "{} key belongs to key set {}, but key set {} holds keys of {}",
+                    kind,
+                    hex::encode(fingerprint),
+                    key_set,
+                    hex::encode(current)
+                );
+                Err(RustError::key_mismatch(format!(
+                    "{} key belongs to key set {}, but key set {} holds keys of {}",
+                    kind,
+                    hex::encode(fingerprint),
+                    key_set,
+                    hex::encode(current)
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the `kind` slot of the key set before a key with `fingerprint` is loaded into it.
+    /// Returns whether that very key is loaded already, and fails with `RustError::AlreadyLoaded`
+    /// if another one is.
+    fn check_slot(
+        &self,
+        key_set: KeySetId,
+        kind: KeyKind,
+        fingerprint: KeyFingerprint,
+    ) -> Result<bool, RustError> {
+        let loaded = match self.key_info.iter().find(|info| info.kind == kind) {
+            Some(info) => info.fingerprint,
+            None => return Ok(false),
+        };
+        if loaded == fingerprint {
+            return Ok(true);
+        }
+
+        log::error!(
+            "key set {} already holds {} key {}, cannot load {}",
+            key_set,
+            kind,
+            hex::encode(loaded),
+            hex::encode(fingerprint)
+        );
+        Err(RustError::already_loaded(
+            kind,
+            format!(
+                "key set {} already holds {} key {}, cannot load {}",
+                key_set,
+                kind,
+                hex::encode(loaded),
+                hex::encode(fingerprint)
+            ),
+        ))
+    }
+
+    fn record_key_info(&mut self, key_set: KeySetId, info: KeyInfo) {
+        log::info!(
+            "loaded {} key {} into key set {} ({} bytes, parameters {})",
+            info.kind,
+            hex::encode(info.fingerprint),
+            key_set,
+            info.serialized_size,
+            info.profile.map_or("unknown", ParameterProfile::name)
+        );
+        self.key_info.retain(|known| known.kind != info.kind);
+        self.key_info.push(info);
+    }
+}
+
+static KEY_SETS: Lazy<RwLock<HashMap<KeySetId, KeySet>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// A key set selected for a thread, with its server key pinned at the time of selection.
+///
+/// Pinning is what lets a rotation happen while operations are running: everything that started
+/// under the old server key keeps using it until it is done, and only new selections pick up the
+/// new one.
+#[derive(Clone)]
+pub struct PinnedKeySet {
+    id: KeySetId,
+    server_key: Option<LoadedServerKey>,
+}
+
+impl PinnedKeySet {
+    pub fn id(&self) -> KeySetId {
+        self.id
+    }
+
+    /// Epoch of the pinned server key, or `None` if the key set had no server key when pinned.
+    pub fn server_key_epoch(&self) -> Option<u64> {
+        self.server_key.as_ref().map(|server_key| server_key.epoch)
+    }
+}
+
+thread_local! {
+    /// The key set operations on this thread run under - see `GlobalKeys::use_key_set`. Threads
+    /// that never selected one use the latest keys of the default key set.
+    static ACTIVE_KEY_SET: RefCell<PinnedKeySet> = RefCell::new(PinnedKeySet {
+        id: DEFAULT_KEY_SET,
+        server_key: None,
+    });
+    /// Key set and epoch of the server key installed in tfhe for this thread. tfhe-rs keeps the
+    /// server key per thread, so switching key sets or epochs means installing a different key.
+    static INSTALLED_SERVER_KEY: RefCell<Option<(KeySetId, u64)>> = RefCell::new(None);
+}
+
+/// Restores the previously active key set when dropped.
+pub struct KeySetGuard {
+    previous: Option<PinnedKeySet>,
+}
+
+impl Drop for KeySetGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            ACTIVE_KEY_SET.with(|active| *active.borrow_mut() = previous);
         }
     }
 }
@@ -48,127 +333,842 @@ impl InitGuard {
 pub struct GlobalKeys {}
 
 impl GlobalKeys {
-    pub fn get_public_key() -> Option<&'static CompactPublicKey> {
-        PUBLIC_KEY.get()
+    /// Runs every following key lookup on this thread under `key_set`, until the returned guard
+    /// is dropped. The current server key of the key set is pinned for that whole time.
+    pub fn use_key_set(key_set: KeySetId) -> KeySetGuard {
+        let server_key = KEY_SETS
+            .read()
+            .unwrap()
+            .get(&key_set)
+            .and_then(|keys| keys.server_key.clone());
+
+        Self::use_pinned_key_set(PinnedKeySet {
+            id: key_set,
+            server_key,
+        })
+    }
+
+    /// Like `use_key_set`, but keeps the server key that was pinned by another thread. Used to
+    /// run work on a thread pool under the caller's keys.
+    pub fn use_pinned_key_set(pinned: PinnedKeySet) -> KeySetGuard {
+        let previous = ACTIVE_KEY_SET.with(|active| active.replace(pinned));
+        KeySetGuard {
+            previous: Some(previous),
+        }
+    }
+
+    pub fn pinned_key_set() -> PinnedKeySet {
+        ACTIVE_KEY_SET.with(|active| active.borrow().clone())
+    }
+
+    pub fn active_key_set() -> KeySetId {
+        ACTIVE_KEY_SET.with(|active| active.borrow().id)
+    }
+
+    /// Ids of every key set that has at least one key loaded.
+    pub fn loaded_key_sets() -> Vec<KeySetId> {
+        let mut ids: Vec<KeySetId> = KEY_SETS.read().unwrap().keys().copied().collect();
+        ids.sort_unstable();
+        ids
     }
-    pub fn get_client_key() -> Option<&'static ClientKey> {
-        CLIENT_KEY.get()
+
+    fn with_active_key_set<T>(f: impl FnOnce(&KeySet) -> Option<T>) -> Option<T> {
+        let key_set = Self::active_key_set();
+        KEY_SETS.read().unwrap().get(&key_set).and_then(f)
     }
 
-    // pub fn is_server_key_set() -> bool {
-    //     SERVER_KEY.get().unwrap_or(&InitGuard::new()).is_key_set()
-    // }
+    pub fn get_public_key() -> Option<Arc<CompactPublicKey>> {
+        Self::with_active_key_set(|keys| keys.public_key.clone())
+    }
+
+    pub fn get_client_key() -> Option<Arc<SecretClientKey>> {
+        Self::with_active_key_set(|keys| keys.client_key.clone())
+    }
+
+    /// Fingerprint of the active key set (see `KeySet::fingerprint`). `None` until a key that
+    /// identifies the key set is loaded into it.
+    pub fn get_key_set_fingerprint() -> Option<KeyFingerprint> {
+        Self::with_active_key_set(|keys| keys.fingerprint())
+    }
+
+    /// Metadata of the keys loaded into `key_set`, in server, client, public order.
+    pub fn key_info(key_set: KeySetId) -> Vec<KeyInfo> {
+        let mut key_info = KEY_SETS
+            .read()
+            .unwrap()
+            .get(&key_set)
+            .map(|keys| keys.key_info.clone())
+            .unwrap_or_default();
+        key_info.sort_by_key(|info| info.kind as u8);
+        key_info
+    }
+
+    /// Parameter profile of the active key set, if any of its keys recorded one.
+    pub fn get_parameter_profile() -> Option<ParameterProfile> {
+        Self::with_active_key_set(|keys| keys.profile)
+    }
 
-    pub fn set_public_key(key: CompactPublicKey) -> Result<(), RustError> {
-        if PUBLIC_KEY.get().is_some() {
-            log::debug!("already loaded public key");
+    /// Loads `key` as the public key of `key_set`. `key_set_fingerprint` is the one recorded in
+    /// the key file, if any.
+    ///
+    /// Reloading the loaded public key is a no-op, loading a different one fails with
+    /// `RustError::AlreadyLoaded`.
+    pub fn set_public_key(
+        key_set: KeySetId,
+        key: CompactPublicKey,
+        info: KeyInfo,
+        key_set_fingerprint: Option<KeyFingerprint>,
+    ) -> Result<(), RustError> {
+        let mut key_sets = KEY_SETS.write().unwrap();
+        let keys = key_sets.entry(key_set).or_default();
+        if keys.check_slot(key_set, KeyKind::Public, info.fingerprint)? {
+            log::debug!("already loaded public key for key set {}", key_set);
             return Ok(());
-            // return Err(RustError::generic_error(
-            //     "Cannot set public key multiple times",
-            // ));
         }
-        PUBLIC_KEY
-            .set(key)
-            .map_err(|_key| RustError::generic_error("failed setting public key"))
+        let key_set_fingerprint = key_set_fingerprint.unwrap_or(info.fingerprint);
+        keys.check_fingerprint(key_set, KeyKind::Public, Some(key_set_fingerprint))?;
+        keys.check_profile(key_set, KeyKind::Public, info.profile)?;
+        keys.public_key = Some(Arc::new(key));
+        keys.fingerprint = Some(key_set_fingerprint);
+        keys.record_key_info(key_set, info);
+        Ok(())
     }
 
-    pub fn set_client_key(key: ClientKey) -> Result<(), RustError> {
-        if CLIENT_KEY.get().is_some() {
-            log::debug!("already loaded client key");
+    /// Loads `key` as the client key of `key_set`. `key_set_fingerprint` is the one recorded in
+    /// the key file, if any.
+    ///
+    /// Reloading the loaded client key is a no-op, loading a different one fails with
+    /// `RustError::AlreadyLoaded` - unload the current one first to replace it.
+    pub fn set_client_key(
+        key_set: KeySetId,
+        key: SecretClientKey,
+        info: KeyInfo,
+        key_set_fingerprint: Option<KeyFingerprint>,
+    ) -> Result<(), RustError> {
+        let mut key_sets = KEY_SETS.write().unwrap();
+        let keys = key_sets.entry(key_set).or_default();
+        if keys.check_slot(key_set, KeyKind::Client, info.fingerprint)? {
+            log::debug!("already loaded client key for key set {}", key_set);
             return Ok(());
-            // return Err(RustError::generic_error(
-            //     "Cannot set client key multiple times",
-            // ));
         }
-        CLIENT_KEY
-            .set(key)
-            .map_err(|_key| RustError::generic_error("failed setting client key"))
+        keys.check_fingerprint(key_set, KeyKind::Client, key_set_fingerprint)?;
+        keys.check_profile(key_set, KeyKind::Client, info.profile)?;
+        keys.client_key = Some(Arc::new(key));
+        keys.fingerprint = keys.fingerprint.or(key_set_fingerprint);
+        keys.record_key_info(key_set, info);
+        Ok(())
     }
 
-    pub fn is_server_key_set() -> bool {
-        if let Some(mutex) = SERVER_KEY.get() {
-            mutex.lock().unwrap().is_key_set()
-        } else {
-            false
+    /// Drops the client key of `key_set`, wiping it once operations that already hold it finish.
+    /// Returns whether a client key was loaded.
+    pub fn unload_client_key(key_set: KeySetId) -> bool {
+        let mut key_sets = KEY_SETS.write().unwrap();
+        let keys = match key_sets.get_mut(&key_set) {
+            Some(keys) => keys,
+            None => return false,
+        };
+        if keys.client_key.take().is_none() {
+            return false;
         }
+        keys.key_info.retain(|info| info.kind != KeyKind::Client);
+        log::info!("unloaded client key of key set {}", key_set);
+        true
+    }
+
+    /// The pinned server key of the active key set, or its latest one if none was pinned.
+    fn get_server_key() -> Option<LoadedServerKey> {
+        let pinned = ACTIVE_KEY_SET.with(|active| active.borrow().server_key.clone());
+        pinned.or_else(|| Self::with_active_key_set(|keys| keys.server_key.clone()))
+    }
+
+    pub fn is_server_key_set() -> bool {
+        Self::get_server_key().is_some()
+    }
+
+    /// The latest server key epoch of `key_set`, or `None` if it has no server key.
+    pub fn server_key_epoch(key_set: KeySetId) -> Option<u64> {
+        KEY_SETS
+            .read()
+            .unwrap()
+            .get(&key_set)
+            .and_then(|keys| keys.server_key.as_ref().map(|server_key| server_key.epoch))
     }
 
+    /// Makes sure tfhe runs this thread's operations under the server key of the active key set,
+    /// reinstalling it if the thread still holds a key of another key set or an older epoch.
+    ///
+    /// Panics if the active key set has no server key.
     pub fn refresh_server_key_for_thread() {
-        let mutex = SERVER_KEY.get_or_init(|| Mutex::new(InitGuard::new()));
-        let mut guard = mutex.lock().unwrap();
-        guard.ensure_init();
+        let server_key = match Self::get_server_key() {
+            Some(server_key) => server_key,
+            None => panic!("Server Key not set for key set {}", Self::active_key_set()),
+        };
+        let wanted = (Self::active_key_set(), server_key.epoch);
+
+        INSTALLED_SERVER_KEY.with(|installed| {
+            let mut installed = installed.borrow_mut();
+            if *installed != Some(wanted) {
+                tfhe::set_server_key((*server_key.key).clone());
+                *installed = Some(wanted);
+            }
+        });
     }
 
-    pub fn set_server_key(key: ServerKey) -> Result<(), bool> {
-        let mutex = SERVER_KEY.get_or_init(|| Mutex::new(InitGuard::new()));
-        let mut guard = mutex.lock().unwrap();
-        if !guard.is_key_set() {
-            guard.set_key(key);
-        }
-        guard.ensure_init();
-        Ok(())
+    /// Loads `key` as the server key of `key_set` and returns its epoch.
+    ///
+    /// Loading the key that is already current is a no-op. Loading a different one rotates the
+    /// key: the epoch is bumped, new operations pick up the new key and operations that already
+    /// pinned the old one finish under it.
+    ///
+    /// A server key generated with other parameters than the rest of the key set, or for another
+    /// key set than its client or public key, is rejected. `key_set_fingerprint` is the one
+    /// recorded in the key file, if any.
+    pub fn set_server_key(
+        key_set: KeySetId,
+        key: ServerKey,
+        info: KeyInfo,
+        key_set_fingerprint: Option<KeyFingerprint>,
+    ) -> Result<u64, RustError> {
+        let mut key_sets = KEY_SETS.write().unwrap();
+        let keys = key_sets.entry(key_set).or_default();
+        keys.check_fingerprint(key_set, KeyKind::Server, key_set_fingerprint)?;
+        keys.check_profile(key_set, KeyKind::Server, info.profile)?;
+
+        let fingerprint = info.fingerprint;
+        let epoch = match &keys.server_key {
+            Some(current) if current.fingerprint == fingerprint => {
+                log::debug!("already loaded server key for key set {}", key_set);
+                return Ok(current.epoch);
+            }
+            Some(current) => {
+                log::info!(
+                    "rotating server key of key set {} to epoch {}",
+                    key_set,
+                    current.epoch + 1
+                );
+                current.epoch + 1
+            }
+            None => 1,
+        };
+
+        keys.server_key = Some(LoadedServerKey {
+            epoch,
+            fingerprint,
+            key_set_fingerprint,
+            key: Arc::new(key),
+        });
+        keys.record_key_info(key_set, info);
+        Ok(epoch)
     }
 }
 
-/// InitGuard is only defined on the server key, as this is the only key that tfhe-rs assumes
-/// will be loaded per-thread. The other keys are passed into their respective function calls
-/// not sure why this is the case - I guess optimizations?
-pub static SERVER_KEY: OnceCell<Mutex<InitGuard>> = OnceCell::new();
-pub static PUBLIC_KEY: OnceCell<CompactPublicKey> = OnceCell::new();
-pub static CLIENT_KEY: OnceCell<ClientKey> = OnceCell::new();
+/// Short identifier of a key set, stamped into every ciphertext envelope.
+pub type KeyFingerprint = [u8; 8];
 
-pub fn deserialize_client_key_safe(key: &[u8]) -> Result<(), RustError> {
-    let maybe_key_deserialized = bincode::deserialize::<ClientKey>(key).map_err(|err| {
-        log::error!("failed deserializing client key: {:?}", err);
-        RustError::generic_error("failed deserializing client key")
-    })?;
+/// The first 8 bytes of the SHA-256 of a serialized key.
+pub fn key_fingerprint(key: &[u8]) -> KeyFingerprint {
+    let digest = Sha256::digest(key);
+    let mut fingerprint = [0u8; 8];
+    fingerprint.copy_from_slice(&digest[..8]);
+    fingerprint
+}
+
+/// Loads a client key file into `key_set`. The key set keeps its own copy of the key, which is
+/// wiped when the key is unloaded - `key` is left to the caller.
+pub fn deserialize_client_key_safe(key_set: KeySetId, key: &[u8]) -> Result<(), RustError> {
+    let key_file = open_key(key, KeyKind::Client)?;
+    let maybe_key_deserialized = SecretClientKey::new(key_file.payload)?;
 
-    GlobalKeys::set_client_key(maybe_key_deserialized)?;
+    GlobalKeys::set_client_key(
+        key_set,
+        maybe_key_deserialized,
+        KeyInfo::new(KeyKind::Client, &key_file),
+        key_file.key_set_fingerprint,
+    )?;
 
     Ok(())
 }
 
-pub fn deserialize_public_key_safe(key: &[u8]) -> Result<(), RustError> {
-    let maybe_key_deserialized = bincode::deserialize::<CompactPublicKey>(key).map_err(|err| {
-        log::error!("failed deserializing public key: {:?}", err);
-        RustError::generic_error("failed deserializing public key")
-    })?;
+/// Like `deserialize_client_key_safe`, for a client key file encrypted with `passphrase`. The
+/// decrypted key file is wiped once the key is loaded.
+pub fn deserialize_encrypted_client_key_safe(
+    key_set: KeySetId,
+    key: &[u8],
+    passphrase: &[u8],
+) -> Result<(), RustError> {
+    let key_file = decrypt_key(key, passphrase)?;
+    deserialize_client_key_safe(key_set, &key_file)
+}
+
+pub fn deserialize_public_key_safe(key_set: KeySetId, key: &[u8]) -> Result<(), RustError> {
+    let key_file = open_key(key, KeyKind::Public)?;
+    let maybe_key_deserialized = bincode::deserialize::<CompactPublicKey>(key_file.payload)
+        .map_err(|err| {
+            log::error!("failed deserializing public key: {:?}", err);
+            RustError::deserialization_failed("public key", err)
+        })?;
 
-    GlobalKeys::set_public_key(maybe_key_deserialized)?;
+    GlobalKeys::set_public_key(
+        key_set,
+        maybe_key_deserialized,
+        KeyInfo::new(KeyKind::Public, &key_file),
+        key_file.key_set_fingerprint,
+    )?;
 
     Ok(())
 }
 
-pub fn load_server_key_safe(key: &[u8]) -> Result<(), RustError> {
-    let server_key = bincode::deserialize::<ServerKey>(key).map_err(|err| {
+/// Loads (or rotates) the server key of `key_set` and returns its epoch.
+///
+/// The key may be a full or a compressed server key, which is decompressed before it is loaded.
+pub fn load_server_key_safe(key_set: KeySetId, key: &[u8]) -> Result<u64, RustError> {
+    let key_file = open_key(key, KeyKind::Server)?;
+
+    let server_key = if key_file.compressed {
+        bincode::deserialize::<CompressedServerKey>(key_file.payload)
+            .map(CompressedServerKey::decompress)
+    } else {
+        bincode::deserialize::<ServerKey>(key_file.payload)
+    }
+    .map_err(|err| {
         log::error!("failed setting server key: {:?}", err);
-        RustError::generic_error("failed setting server key: bad input")
+        RustError::deserialization_failed("server key", err)
     })?;
 
-    tfhe::set_server_key(server_key.clone());
+    GlobalKeys::set_server_key(
+        key_set,
+        server_key,
+        KeyInfo::new(KeyKind::Server, &key_file),
+        key_file.key_set_fingerprint,
+    )
+}
 
-    GlobalKeys::set_server_key(server_key).map_err(|_| {
-        log::error!("failed setting server key");
-        RustError::generic_error("failed setting server key: set failed")
+/// Returns the bincode encoded `Vec<KeyInfo>` of the keys loaded into `key_set`. A key set without
+/// keys yields an empty list.
+pub fn key_info_safe(key_set: KeySetId) -> Result<Vec<u8>, RustError> {
+    bincode::serialize(&GlobalKeys::key_info(key_set)).map_err(|err| {
+        log::error!("failed serializing key info: {:?}", err);
+        RustError::serialization_failed("key info")
     })
 }
 
-pub fn generate_keys_safe() -> (Vec<u8>, Vec<u8>, Vec<u8>) {
-    let config = ConfigBuilder::all_disabled()
-        .enable_custom_integers(KEYGEN_PARAMS, None)
-        .build();
+/// The value `validate_keys_safe` sends through the keys. Wide enough that a wrong client key
+/// cannot decrypt it to the expected value by chance.
+const VALIDATION_PROBE: u64 = 0x5a5a_a5a5;
+
+/// Checks that the keys of the active key set belong together: a probe is encrypted with the
+/// public key, added to itself with the server key and, if a client key is loaded, both the probe
+/// and the sum are decrypted again.
+///
+/// Fails with `RustError::KeyNotLoaded` if the server or public key is missing and with
+/// `RustError::KeyMismatch` naming the offending key otherwise. Without a client key only keys
+/// that cannot work together at all (e.g. other parameters) are caught. The mock backend needs no
+/// keys, so there is nothing to validate.
+pub fn validate_keys_safe() -> Result<(), RustError> {
+    if is_mock_backend() {
+        return Ok(());
+    }
+    if !GlobalKeys::is_server_key_set() {
+        return Err(RustError::key_not_loaded(KeyKind::Server));
+    }
+
+    let uint_type = FheUintType::Uint32;
+
+    let probe = encrypt_safe(VALIDATION_PROBE, uint_type)
+        .and_then(|compact| expand_compressed_safe(&compact, uint_type))
+        .map_err(mismatch("public key cannot encrypt"))?;
+
+    GlobalKeys::refresh_server_key_for_thread();
+    let sum = math_operation_helper(&probe, &probe, Op::Add, uint_type)
+        .map_err(mismatch("server key does not match the public key"))?;
+
+    if GlobalKeys::get_client_key().is_none() {
+        log::warn!("no client key loaded, validated the server and public key only");
+        return Ok(());
+    }
+
+    for (ciphertext, expected, key) in [
+        (&probe, VALIDATION_PROBE, "public"),
+        (&sum, VALIDATION_PROBE * 2, "server"),
+    ] {
+        let decrypted =
+            decrypt_safe(ciphertext, uint_type).map_err(mismatch("client key cannot decrypt"))?;
+        if decrypted != expected {
+            log::error!(
+                "key validation failed: the {} key does not match the client key",
+                key
+            );
+            return Err(RustError::key_mismatch(format!(
+                "the {} key does not match the client key",
+                key
+            )));
+        }
+    }
 
+    Ok(())
+}
+
+/// Turns a failure while validating keys into a `RustError::KeyMismatch`, except for missing keys.
+fn mismatch(what: &'static str) -> impl Fn(RustError) -> RustError {
+    move |err| match err {
+        RustError::KeyNotLoaded { .. } => err,
+        err => {
+            log::error!("key validation failed: {}: {}", what, err);
+            RustError::key_mismatch(format!("{}: {}", what, err))
+        }
+    }
+}
+
+/// Freshly generated keys, each serialized as a key file that records the parameter profile.
+#[derive(Clone, PartialEq, Eq)]
+pub struct GeneratedKeys {
+    /// Wiped when dropped, as it holds the secret key.
+    pub client_key: Zeroizing<Vec<u8>>,
+    pub server_key: Vec<u8>,
+    /// The same server key in tfhe's compressed (seeded) form, if it was asked for. A fraction of
+    /// the size of `server_key`, and decompressed again when it is loaded.
+    pub compressed_server_key: Option<Vec<u8>>,
+    pub public_key: Vec<u8>,
+}
+
+// the client key is left out, so the keys can be logged
+impl fmt::Debug for GeneratedKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeneratedKeys")
+            .field("client_key", &"<redacted>")
+            .field("server_key", &self.server_key)
+            .field("compressed_server_key", &self.compressed_server_key)
+            .field("public_key", &self.public_key)
+            .finish()
+    }
+}
+
+/// Generates a client, server and public key with the parameters of `profile`, and the
+/// compressed form of the server key if `compressed` is set.
+pub fn generate_keys_safe(profile: ParameterProfile, compressed: bool) -> GeneratedKeys {
+    log::info!("generating keys with parameters {}", profile.name());
+
+    serialize_new_keys(profile, compressed)
+}
+
+/// Length of the seed taken by `generate_seeded_keys_insecure`.
+pub const KEYGEN_SEED_LEN: usize = 32;
+
+/// Like `generate_keys_safe`, but derives all the randomness from `seed`, so the same seed and
+/// profile always produce bit-identical keys.
+///
+/// **Never use this for production keys.** Anyone who knows the seed can regenerate the client
+/// key and decrypt everything. It only exists so devnets and CI can rebuild a well-known key set
+/// instead of shipping the key files around.
+pub fn generate_seeded_keys_insecure(
+    profile: ParameterProfile,
+    compressed: bool,
+    seed: &[u8],
+) -> Result<GeneratedKeys, RustError> {
+    if seed.len() != KEYGEN_SEED_LEN {
+        return Err(RustError::invalid_argument(format!(
+            "key generation seed must be {} bytes, got {}",
+            KEYGEN_SEED_LEN,
+            seed.len()
+        )));
+    }
+
+    log::warn!(
+        "generating INSECURE keys from a fixed seed with parameters {} - never use them in \
+         production",
+        profile.name()
+    );
+
+    // tfhe seeds its generators with a u128, so the seed is compressed with a domain separated
+    // hash rather than truncated.
+    let digest = Sha256::new()
+        .chain_update(b"tfhe-wrapper seeded keygen")
+        .chain_update(seed)
+        .finalize();
+    let mut tfhe_seed = [0u8; 16];
+    tfhe_seed.copy_from_slice(&digest[..16]);
+    let tfhe_seed = Seed(u128::from_le_bytes(tfhe_seed));
+
+    // tfhe draws key randomness from a thread local engine. Replacing it on a dedicated thread
+    // keeps the seeded engine away from every other thread, and it is dropped with the thread.
+    std::thread::spawn(move || {
+        let mut seeder = DeterministicSeeder::<ActivatedRandomGenerator>::new(tfhe_seed);
+        ShortintEngine::replace_thread_local(ShortintEngine::new_from_seeder(&mut seeder));
+
+        serialize_new_keys(profile, compressed)
+    })
+    .join()
+    .map_err(|err| {
+        log::error!("seeded key generation panicked: {:?}", err);
+        RustError::generic_error("seeded key generation panicked")
+    })
+}
+
+fn serialize_new_keys(profile: ParameterProfile, compressed: bool) -> GeneratedKeys {
     // Client-side
-    let (cks, sks) = tfhe::generate_keys(config);
+    let cks = ClientKey::generate(profile.config());
     let pks: CompactPublicKey = CompactPublicKey::new(&cks);
 
-    let serialized_secret_key = bincode::serialize(&cks).unwrap();
+    // Both forms have to hold the same key, so the full one is decompressed from the compressed
+    // one rather than generated on its own.
+    let (sks, serialized_compressed_server_key) = if compressed {
+        let compressed_sks = CompressedServerKey::new(&cks);
+        let serialized = bincode::serialize(&compressed_sks).unwrap();
+        (compressed_sks.decompress(), Some(serialized))
+    } else {
+        (ServerKey::new(&cks), None)
+    };
+
+    let serialized_secret_key = Zeroizing::new(bincode::serialize(&cks).unwrap());
     let serialized_server_key = bincode::serialize(&sks).unwrap();
     let serialized_public_key = bincode::serialize(&pks).unwrap();
 
-    (
-        serialized_secret_key,
-        serialized_server_key,
-        serialized_public_key,
-    )
+    // The key set is identified by its public key, as it was before key files recorded it, so
+    // loading the public key on its own still yields the same fingerprint.
+    let key_set = key_fingerprint(&serialized_public_key);
+
+    GeneratedKeys {
+        client_key: Zeroizing::new(seal_key(
+            KeyKind::Client,
+            profile,
+            key_set,
+            &serialized_secret_key,
+        )),
+        server_key: seal_key(KeyKind::Server, profile, key_set, &serialized_server_key),
+        compressed_server_key: serialized_compressed_server_key
+            .map(|serialized| seal_compressed_server_key(profile, key_set, &serialized)),
+        public_key: seal_key(KeyKind::Public, profile, key_set, &serialized_public_key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::trivial_encrypt_safe;
+    use crate::key_encryption::{encrypt_key_with_params, KdfParams};
+    use crate::test_utils::{setup_keys, test_keys, TestKeys};
+    use once_cell::sync::OnceCell;
+
+    const OTHER_KEY_SET: KeySetId = 7;
+    const ROTATED_KEY_SET: KeySetId = 8;
+    const MISMATCHED_KEY_SET: KeySetId = 9;
+    const MIXED_KEY_SET: KeySetId = 10;
+    const EMPTY_KEY_SET: KeySetId = 11;
+    const COMPRESSED_KEY_SET: KeySetId = 12;
+    const UNLOADED_KEY_SET: KeySetId = 13;
+    const ENCRYPTED_KEY_SET: KeySetId = 14;
+    const COMPUTE_ONLY_KEY_SET: KeySetId = 15;
+    const OCCUPIED_KEY_SET: KeySetId = 16;
+    const SEED: [u8; KEYGEN_SEED_LEN] = [42; KEYGEN_SEED_LEN];
+
+    /// A second generation of keys, shared by the tests that need keys other than the test keys.
+    fn other_keys() -> &'static TestKeys {
+        static OTHER_KEYS: OnceCell<TestKeys> = OnceCell::new();
+        OTHER_KEYS.get_or_init(|| {
+            let keys = generate_keys_safe(ParameterProfile::default(), false);
+            (keys.client_key.to_vec(), keys.server_key, keys.public_key)
+        })
+    }
+
+    fn encrypt_expanded(msg: u64, uint_type: FheUintType) -> Vec<u8> {
+        expand_compressed_safe(&encrypt_safe(msg, uint_type).unwrap(), uint_type).unwrap()
+    }
+
+    #[test]
+    fn key_sets_are_isolated() {
+        setup_keys();
+
+        let (cks, sks, pks) = other_keys();
+        deserialize_client_key_safe(OTHER_KEY_SET, cks).unwrap();
+        load_server_key_safe(OTHER_KEY_SET, sks).unwrap();
+        deserialize_public_key_safe(OTHER_KEY_SET, pks).unwrap();
+        assert!(GlobalKeys::loaded_key_sets().contains(&OTHER_KEY_SET));
+
+        let default_ciphertext = encrypt_expanded(3, FheUintType::Uint8);
+
+        let other_ciphertext = {
+            let _key_set = GlobalKeys::use_key_set(OTHER_KEY_SET);
+            assert!(GlobalKeys::get_key_set_fingerprint().is_some());
+
+            let ciphertext = encrypt_expanded(5, FheUintType::Uint8);
+            assert_eq!(decrypt_safe(&ciphertext, FheUintType::Uint8).unwrap(), 5);
+            assert!(decrypt_safe(&default_ciphertext, FheUintType::Uint8).is_err());
+            ciphertext
+        };
+
+        assert_eq!(GlobalKeys::active_key_set(), DEFAULT_KEY_SET);
+        assert_eq!(
+            decrypt_safe(&default_ciphertext, FheUintType::Uint8).unwrap(),
+            3
+        );
+        assert!(decrypt_safe(&other_ciphertext, FheUintType::Uint8).is_err());
+    }
+
+    #[test]
+    fn compute_only_key_sets_share_the_fingerprint() {
+        setup_keys();
+        let (_, sks, _) = test_keys();
+        let fingerprint = GlobalKeys::get_key_set_fingerprint();
+        assert!(fingerprint.is_some());
+
+        load_server_key_safe(COMPUTE_ONLY_KEY_SET, sks).unwrap();
+        let compute_only = GlobalKeys::use_key_set(COMPUTE_ONLY_KEY_SET);
+        assert_eq!(GlobalKeys::get_key_set_fingerprint(), fingerprint);
+
+        // so ciphertexts move freely between the two
+        let ciphertext = trivial_encrypt_safe(3, FheUintType::Uint8).unwrap();
+        drop(compute_only);
+        assert_eq!(decrypt_safe(&ciphertext, FheUintType::Uint8).unwrap(), 3);
+    }
+
+    #[test]
+    fn server_key_rotation_bumps_the_epoch() {
+        let (_, sks, _) = test_keys();
+        let (_, rotated_sks, _) = other_keys();
+
+        assert_eq!(load_server_key_safe(ROTATED_KEY_SET, sks).unwrap(), 1);
+        assert_eq!(load_server_key_safe(ROTATED_KEY_SET, sks).unwrap(), 1);
+
+        let in_flight = GlobalKeys::use_key_set(ROTATED_KEY_SET);
+        assert_eq!(
+            load_server_key_safe(ROTATED_KEY_SET, rotated_sks).unwrap(),
+            2
+        );
+        assert_eq!(GlobalKeys::server_key_epoch(ROTATED_KEY_SET), Some(2));
+        // work that started before the rotation keeps the old key
+        assert_eq!(GlobalKeys::pinned_key_set().server_key_epoch(), Some(1));
+        drop(in_flight);
+
+        let _next = GlobalKeys::use_key_set(ROTATED_KEY_SET);
+        assert_eq!(GlobalKeys::pinned_key_set().server_key_epoch(), Some(2));
+    }
+
+    #[test]
+    fn keys_of_other_profiles_are_rejected() {
+        let (cks, sks, _) = test_keys();
+        load_server_key_safe(MISMATCHED_KEY_SET, sks).unwrap();
+
+        let cks_file = open_key(cks, KeyKind::Client).unwrap();
+        let cks_payload = cks_file.payload;
+        let other_profile = seal_key(
+            KeyKind::Client,
+            ParameterProfile::Message1Carry1KsPbs,
+            cks_file.key_set_fingerprint.unwrap(),
+            cks_payload,
+        );
+        assert!(matches!(
+            deserialize_client_key_safe(MISMATCHED_KEY_SET, &other_profile),
+            Err(RustError::KeyMismatch { .. })
+        ));
+
+        // keys without a recorded profile are let through
+        deserialize_client_key_safe(MISMATCHED_KEY_SET, cks_payload).unwrap();
+        let _key_set = GlobalKeys::use_key_set(MISMATCHED_KEY_SET);
+        assert_eq!(
+            GlobalKeys::get_parameter_profile(),
+            Some(ParameterProfile::default())
+        );
+    }
+
+    #[test]
+    fn seeded_keys_are_reproducible() {
+        let (cks, _, _) = test_keys();
+
+        let generate =
+            |seed: &[u8]| generate_seeded_keys_insecure(ParameterProfile::default(), false, seed);
+
+        let first = generate(&SEED).unwrap();
+        let second = generate(&SEED).unwrap();
+        // not assert_eq, so a failure does not print the keys
+        assert!(first == second);
+        assert!(*first.client_key != *cks);
+
+        assert!(matches!(
+            generate(&SEED[1..]),
+            Err(RustError::InvalidArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn keys_of_different_generations_fail_validation() {
+        setup_keys();
+        assert!(validate_keys_safe().is_ok());
+
+        let (_, sks, pks) = test_keys();
+        let (other_cks, _, _) = other_keys();
+        load_server_key_safe(MIXED_KEY_SET, sks).unwrap();
+        deserialize_public_key_safe(MIXED_KEY_SET, pks).unwrap();
+
+        {
+            let _key_set = GlobalKeys::use_key_set(MIXED_KEY_SET);
+            // a matching server and public key pass on their own
+            assert!(validate_keys_safe().is_ok());
+
+            // a key file that records another key set is refused when loaded ...
+            assert!(matches!(
+                deserialize_client_key_safe(MIXED_KEY_SET, other_cks),
+                Err(RustError::KeyMismatch { .. })
+            ));
+
+            // ... and a legacy one without a header only fails validation
+            let legacy_cks = open_key(other_cks, KeyKind::Client).unwrap().payload;
+            deserialize_client_key_safe(MIXED_KEY_SET, legacy_cks).unwrap();
+            assert!(matches!(
+                validate_keys_safe(),
+                Err(RustError::KeyMismatch { .. })
+            ));
+        }
+
+        let _key_set = GlobalKeys::use_key_set(EMPTY_KEY_SET);
+        assert!(matches!(
+            validate_keys_safe(),
+            Err(RustError::KeyNotLoaded { .. })
+        ));
+    }
+
+    #[test]
+    fn occupied_slots_refuse_other_keys() {
+        let (cks, _, pks) = test_keys();
+        let (other_cks, _, other_pks) = other_keys();
+
+        deserialize_public_key_safe(OCCUPIED_KEY_SET, pks).unwrap();
+        deserialize_public_key_safe(OCCUPIED_KEY_SET, pks).unwrap();
+        assert!(matches!(
+            deserialize_public_key_safe(OCCUPIED_KEY_SET, other_pks),
+            Err(RustError::AlreadyLoaded {
+                kind: KeyKind::Public,
+                ..
+            })
+        ));
+
+        deserialize_client_key_safe(OCCUPIED_KEY_SET, cks).unwrap();
+        deserialize_client_key_safe(OCCUPIED_KEY_SET, cks).unwrap();
+        assert!(matches!(
+            deserialize_client_key_safe(OCCUPIED_KEY_SET, other_cks),
+            Err(RustError::AlreadyLoaded {
+                kind: KeyKind::Client,
+                ..
+            })
+        ));
+
+        // an emptied slot takes another key again, as long as it belongs to the key set
+        assert!(GlobalKeys::unload_client_key(OCCUPIED_KEY_SET));
+        assert!(matches!(
+            deserialize_client_key_safe(OCCUPIED_KEY_SET, other_cks),
+            Err(RustError::KeyMismatch { .. })
+        ));
+        deserialize_client_key_safe(OCCUPIED_KEY_SET, cks).unwrap();
+    }
+
+    #[test]
+    fn key_info_describes_loaded_keys() {
+        setup_keys();
+        let (cks, sks, pks) = test_keys();
+
+        let info: Vec<KeyInfo> =
+            bincode::deserialize(&key_info_safe(DEFAULT_KEY_SET).unwrap()).unwrap();
+        assert_eq!(info, GlobalKeys::key_info(DEFAULT_KEY_SET));
+
+        let kinds = [KeyKind::Server, KeyKind::Client, KeyKind::Public];
+        assert_eq!(info.len(), kinds.len());
+        for ((info, kind), key) in info.iter().zip(kinds).zip([sks, cks, pks]) {
+            let payload = open_key(key, kind).unwrap().payload;
+            assert_eq!(info.kind, kind);
+            assert_eq!(info.fingerprint, key_fingerprint(payload));
+            assert_eq!(info.profile, Some(ParameterProfile::default()));
+            assert_eq!(info.integer_widths, vec![8, 16, 32, 64]);
+            assert_eq!(info.serialized_size, payload.len() as u64);
+            assert!(!info.compressed);
+        }
+
+        assert!(GlobalKeys::key_info(EMPTY_KEY_SET).is_empty());
+    }
+
+    #[test]
+    fn compressed_server_keys_are_decompressed_on_load() {
+        let keys = generate_keys_safe(ParameterProfile::default(), true);
+        let compressed_sks = keys.compressed_server_key.unwrap();
+        assert!(compressed_sks.len() < keys.server_key.len());
+
+        deserialize_client_key_safe(COMPRESSED_KEY_SET, &keys.client_key).unwrap();
+        load_server_key_safe(COMPRESSED_KEY_SET, &compressed_sks).unwrap();
+        deserialize_public_key_safe(COMPRESSED_KEY_SET, &keys.public_key).unwrap();
+
+        let server_info = &GlobalKeys::key_info(COMPRESSED_KEY_SET)[0];
+        assert_eq!(server_info.kind, KeyKind::Server);
+        assert!(server_info.compressed);
+
+        let _key_set = GlobalKeys::use_key_set(COMPRESSED_KEY_SET);
+        assert!(validate_keys_safe().is_ok());
+    }
+
+    #[test]
+    fn unloaded_client_keys_cannot_decrypt() {
+        setup_keys();
+        let (cks, sks, pks) = test_keys();
+        deserialize_client_key_safe(UNLOADED_KEY_SET, cks).unwrap();
+        load_server_key_safe(UNLOADED_KEY_SET, sks).unwrap();
+        deserialize_public_key_safe(UNLOADED_KEY_SET, pks).unwrap();
+
+        let _key_set = GlobalKeys::use_key_set(UNLOADED_KEY_SET);
+        let ciphertext = encrypt_expanded(7, FheUintType::Uint8);
+        assert_eq!(decrypt_safe(&ciphertext, FheUintType::Uint8).unwrap(), 7);
+
+        assert!(GlobalKeys::unload_client_key(UNLOADED_KEY_SET));
+        assert!(!GlobalKeys::unload_client_key(UNLOADED_KEY_SET));
+        assert!(GlobalKeys::get_client_key().is_none());
+        assert!(matches!(
+            decrypt_safe(&ciphertext, FheUintType::Uint8),
+            Err(RustError::KeyNotLoaded { .. })
+        ));
+        assert!(GlobalKeys::key_info(UNLOADED_KEY_SET)
+            .iter()
+            .all(|info| info.kind != KeyKind::Client));
+
+        // the other keys stay usable
+        assert!(encrypt_safe(1, FheUintType::Uint8).is_ok());
+    }
+
+    #[test]
+    fn encrypted_client_keys_need_their_passphrase() {
+        let (cks, _, _) = test_keys();
+        let params = KdfParams {
+            memory_cost_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let encrypted = encrypt_key_with_params(cks, b"passphrase", params).unwrap();
+
+        assert!(matches!(
+            deserialize_client_key_safe(ENCRYPTED_KEY_SET, &encrypted),
+            Err(RustError::DeserializationFailed { .. })
+        ));
+        assert!(
+            deserialize_encrypted_client_key_safe(ENCRYPTED_KEY_SET, &encrypted, b"wrong").is_err()
+        );
+        assert!(GlobalKeys::key_info(ENCRYPTED_KEY_SET).is_empty());
+
+        deserialize_encrypted_client_key_safe(ENCRYPTED_KEY_SET, &encrypted, b"passphrase")
+            .unwrap();
+        let info = &GlobalKeys::key_info(ENCRYPTED_KEY_SET)[0];
+        assert_eq!(info.kind, KeyKind::Client);
+        assert_eq!(
+            info.fingerprint,
+            key_fingerprint(open_key(cks, KeyKind::Client).unwrap().payload)
+        );
+    }
+
+    #[test]
+    fn generated_keys_do_not_print_the_client_key() {
+        let keys = GeneratedKeys {
+            client_key: Zeroizing::new(b"secret".to_vec()),
+            server_key: vec![1],
+            compressed_server_key: None,
+            public_key: vec![2],
+        };
+
+        let printed = format!("{:?}", keys);
+        assert!(printed.contains("<redacted>"));
+        assert!(!printed.contains(&format!("{:?}", b"secret".to_vec())));
+    }
 }
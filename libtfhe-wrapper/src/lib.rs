@@ -2,19 +2,35 @@
 #![allow(clippy::not_unsafe_ptr_arg_deref, clippy::missing_safety_doc)]
 
 pub mod api;
+pub mod batch;
+pub(crate) mod boolean;
+pub mod circuit;
 pub mod encryption;
+pub mod envelope;
 pub mod error;
+pub mod gas;
 
 #[cfg(target_arch = "wasm32")]
 pub(crate) mod imports;
 
 pub(crate) mod cast;
+pub(crate) mod handles;
+pub mod key_encryption;
+pub mod key_file;
 pub mod keys;
 pub(crate) mod logger;
+pub mod mock;
+pub mod params;
+pub mod sealing;
 
 #[macro_use]
 pub(crate) mod math;
 pub(crate) mod serialization;
+pub(crate) mod validation;
+pub(crate) mod value;
+
+#[cfg(test)]
+pub(crate) mod test_utils;
 
 use ctor::ctor;
 
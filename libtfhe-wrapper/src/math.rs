@@ -1,11 +1,16 @@
-use crate::api::{Op, UnaryOp};
+use crate::api::{FheUintType, Op, UnaryOp};
+use crate::boolean;
 use crate::error::RustError;
 use crate::keys::GlobalKeys;
 use serde::Serialize;
 use std::ops::{Add, BitAnd, BitOr, BitXor, Div, Mul, Not, Rem, Shl, Shr, Sub};
 use tfhe::prelude::*;
+use tfhe::FheUint8;
 
-use crate::serialization::{deserialize_fhe_uint16, deserialize_fhe_uint32, deserialize_fhe_uint8};
+use crate::serialization::{
+    deserialize_fhe_bool, deserialize_fhe_uint16, deserialize_fhe_uint32, deserialize_fhe_uint64,
+    deserialize_fhe_uint8,
+};
 
 /// Performs the specified operation on two encrypted x-bit unsigned integers.
 ///
@@ -29,17 +34,11 @@ macro_rules! define_op_fn {
             match ($deserialize_func(lhs, false), $deserialize_func(rhs, false)) {
                 (Err(e), _) => {
                     log::error!("failed deserializing lhs value: {:?}", e);
-                    Err(RustError::generic_error(format!(
-                        "failed deserializing lhs value: {:?}",
-                        e
-                    )))
+                    Err(RustError::deserialization_failed("lhs value", e))
                 }
                 (_, Err(e)) => {
                     log::error!("failed deserializing rhs value: {:?}", e);
-                    Err(RustError::generic_error(format!(
-                        "failed deserializing rhs value: {:?}",
-                        e
-                    )))
+                    Err(RustError::deserialization_failed("rhs value", e))
                 }
                 (Ok(l), Ok(r)) => common_op(l, r, operation),
             }
@@ -51,24 +50,72 @@ macro_rules! define_op_fn {
 define_op_fn!(op_uint8, deserialize_fhe_uint8, FheUint8);
 define_op_fn!(op_uint16, deserialize_fhe_uint16, FheUint16);
 define_op_fn!(op_uint32, deserialize_fhe_uint32, FheUint32);
+define_op_fn!(op_uint64, deserialize_fhe_uint64, FheUint64);
 
-/// A generic function that performs the given operation on two encrypted numbers.
+/// Performs the specified logical operation on two encrypted booleans.
 ///
-/// This function is used internally by the other `op_` functions to
-/// perform the actual arithmetic. It's generic over the type of the operands.
+/// Only the operations that keep the result in {0, 1} are allowed - `BitAnd`, `BitOr` and `BitXor`
+/// act as the logical AND/OR/XOR, and `Eq`/`Ne` compare the two booleans. The inputs are not
+/// checked to hold 0 or 1: expanded `Bool`s are only ever produced by this library, which
+/// normalizes user input when expanding it (see `boolean`).
 ///
 /// # Arguments
 ///
-/// * `num1` - The first operand.
-/// * `num2` - The second operand.
+/// * `lhs` - The left-hand side operand.
+/// * `rhs` - The right-hand side operand.
 /// * `operation` - The operation to perform.
-/// * `err_msg` - A mutable reference to an `UnmanagedVector` that can contain error messages.
 ///
 /// # Returns
 ///
-/// An `UnmanagedVector` containing the serialized result.
-fn common_op<
-    T: Add<Output = T>
+/// A `Vec<u8>` containing the serialized boolean, or `RustError`
+pub fn op_bool(lhs: &[u8], rhs: &[u8], operation: Op) -> Result<Vec<u8>, RustError> {
+    check_bool_op(operation)?;
+
+    op_uint8(lhs, rhs, operation)
+}
+
+/// The operations every encrypted integer type supports, so generic code does not have to spell
+/// out the whole list of bounds. The operators themselves are implemented on references, see
+/// `FheIntegerRef`.
+pub(crate) trait FheInteger:
+    for<'a> FheOrd<&'a Self, Output = Self>
+    + for<'a> FheEq<&'a Self, Output = Self>
+    + for<'a> FheMin<&'a Self, Output = Self>
+    + for<'a> FheMax<&'a Self, Output = Self>
+    + Serialize
+    + Sized
+{
+}
+
+impl<T> FheInteger for T where
+    T: for<'a> FheOrd<&'a T, Output = T>
+        + for<'a> FheEq<&'a T, Output = T>
+        + for<'a> FheMin<&'a T, Output = T>
+        + for<'a> FheMax<&'a T, Output = T>
+        + Serialize
+{
+}
+
+/// The operators of an encrypted integer `T`, implemented by `&T`, so live values can be combined
+/// without giving them up - or cloning them first.
+pub(crate) trait FheIntegerRef<T>:
+    Add<Output = T>
+    + Sub<Output = T>
+    + Mul<Output = T>
+    + Div<Output = T>
+    + BitAnd<Output = T>
+    + BitOr<Output = T>
+    + BitXor<Output = T>
+    + Rem<Output = T>
+    + Shl<Output = T>
+    + Shr<Output = T>
+    + Not<Output = T>
+    + Sized
+{
+}
+
+impl<T, R> FheIntegerRef<T> for R where
+    R: Add<Output = T>
         + Sub<Output = T>
         + Mul<Output = T>
         + Div<Output = T>
@@ -76,21 +123,80 @@ fn common_op<
         + BitOr<Output = T>
         + BitXor<Output = T>
         + Rem<Output = T>
-        + FheOrd<Output = T>
-        + FheEq<Output = T>
-        + for<'a> FheMin<&'a T, Output = T>
-        + for<'a> FheMax<&'a T, Output = T>
         + Shl<Output = T>
         + Shr<Output = T>
-        + Serialize,
->(
-    num1: T,
-    num2: T,
-    operation: Op,
-) -> Result<Vec<u8>, RustError> {
+        + Not<Output = T>
+{
+}
+
+/// Returns an error if `operation` could take an encrypted boolean out of {0, 1}.
+pub(crate) fn check_bool_op(operation: Op) -> Result<(), RustError> {
+    match operation {
+        Op::BitAnd | Op::BitOr | Op::BitXor | Op::Eq | Op::Ne => Ok(()),
+        _ => Err(RustError::unsupported_operation(format!(
+            "operation {:?} is not supported for booleans",
+            operation
+        ))),
+    }
+}
+
+/// Returns an error unless a `control_type` control can select between `uint_type` options.
+///
+/// The control must be a `Bool` or of the options' own type. A wider control would have to be
+/// cast down to build the mask, which truncates it - a `Uint16` control of 256 would select the
+/// `false` option of a `Uint8` cmux.
+pub(crate) fn check_cmux_control(
+    control_type: FheUintType,
+    uint_type: FheUintType,
+) -> Result<(), RustError> {
+    if control_type == FheUintType::Bool || control_type == uint_type {
+        return Ok(());
+    }
+
+    Err(RustError::type_mismatch(format!(
+        "cmux control must be a Bool or a {:?}, got {:?}",
+        uint_type, control_type
+    )))
+}
+
+/// A generic function that performs the given operation on two encrypted numbers.
+///
+/// This function is used internally by the other `op_` functions to
+/// perform the actual arithmetic. It's generic over the type of the operands.
+///
+/// # Arguments
+///
+/// * `num1` - The first operand.
+/// * `num2` - The second operand.
+/// * `operation` - The operation to perform.
+///
+/// # Returns
+///
+/// A `Vec<u8>` containing the serialized result, or `RustError`
+fn common_op<T>(num1: T, num2: T, operation: Op) -> Result<Vec<u8>, RustError>
+where
+    T: FheInteger,
+    for<'a> &'a T: FheIntegerRef<T>,
+{
     GlobalKeys::refresh_server_key_for_thread();
 
-    let result = match operation {
+    let result = compute_op(&num1, &num2, operation);
+
+    bincode::serialize(&result).map_err(|err| {
+        log::error!("failed serializing result: {:?}", err);
+        RustError::serialization_failed(format!("result after operation: {:?}", operation))
+    })
+}
+
+/// Performs the given operation on two live encrypted numbers.
+///
+/// The caller is responsible for installing the server key on the current thread.
+pub(crate) fn compute_op<T>(num1: &T, num2: &T, operation: Op) -> T
+where
+    T: FheInteger,
+    for<'a> &'a T: FheIntegerRef<T>,
+{
+    match operation {
         Op::Add => num1 + num2,
         Op::Sub => num1 - num2,
         Op::Mul => num1 * num2,
@@ -105,21 +211,187 @@ fn common_op<
         Op::BitXor => num1 ^ num2,
         Op::Eq => num1.eq(num2),
         Op::Ne => num1.ne(num2),
-        Op::Min => num1.min(&num2),
-        Op::Max => num1.max(&num2),
+        Op::Min => num1.min(num2),
+        Op::Max => num1.max(num2),
         Op::Shl => num1 << num2,
         Op::Shr => num1 >> num2,
+    }
+}
+
+/// Performs the specified operation on an encrypted x-bit unsigned integer and a plaintext scalar.
+///
+/// This function deserializes the ciphertext, performs the specified operation, and
+/// returns the serialized result. The scalar is truncated to the width of the ciphertext.
+///
+///
+/// # Arguments
+///
+/// * `lhs` - The encrypted left-hand side operand.
+/// * `rhs` - The plaintext right-hand side operand.
+/// * `operation` - The operation to perform.
+///
+/// # Returns
+///
+/// A `Vec<u8>` containing the serialized result, or `RustError`
+macro_rules! define_scalar_op_fn {
+    ($func_name:ident, $deserialize_func:ident, $type:ty, $scalar_type:ty) => {
+        #[export_name = stringify!($func_name)]
+        pub fn $func_name(lhs: &[u8], rhs: u64, operation: Op) -> Result<Vec<u8>, RustError> {
+            let rhs = scalar_operand(rhs, operation, <$scalar_type>::BITS);
+
+            match $deserialize_func(lhs, false) {
+                Err(e) => {
+                    log::error!("failed deserializing lhs value: {:?}", e);
+                    Err(RustError::deserialization_failed("lhs value", e))
+                }
+                Ok(l) => common_scalar_op::<$type, $scalar_type>(l, rhs as $scalar_type, operation),
+            }
+        }
     };
+}
+
+// Use the macro to define the functions
+define_scalar_op_fn!(scalar_op_uint8, deserialize_fhe_uint8, FheUint8, u8);
+define_scalar_op_fn!(scalar_op_uint16, deserialize_fhe_uint16, FheUint16, u16);
+define_scalar_op_fn!(scalar_op_uint32, deserialize_fhe_uint32, FheUint32, u32);
+define_scalar_op_fn!(scalar_op_uint64, deserialize_fhe_uint64, FheUint64, u64);
+
+/// Performs the specified logical operation on an encrypted boolean and a plaintext one.
+///
+/// There is nothing to gain from scalar operations on a single bit, so the scalar is trivially
+/// encrypted and handed to `op_bool`.
+pub fn scalar_op_bool(lhs: &[u8], rhs: u64, operation: Op) -> Result<Vec<u8>, RustError> {
+    GlobalKeys::refresh_server_key_for_thread();
+
+    let rhs = bincode::serialize(&boolean::trivial(rhs != 0)?).map_err(|err| {
+        log::error!("failed serializing trivial encryption: {:?}", err);
+        RustError::serialization_failed("trivial encryption")
+    })?;
+
+    op_bool(lhs, &rhs, operation)
+}
+
+/// A generic function that performs the given operation on an encrypted number and a scalar.
+///
+/// Arithmetic, shifts, comparisons and min/max use tfhe's scalar operations, which are much
+/// cheaper than their ciphertext-ciphertext counterparts. There are no scalar bitwise operations
+/// in this version of tfhe, so those fall back to a trivial encryption of the scalar - as does
+/// division (and remainder) by zero, so the result matches the ciphertext-ciphertext operation
+/// rather than panicking.
+///
+/// # Arguments
+///
+/// * `num` - The encrypted operand.
+/// * `scalar` - The plaintext operand.
+/// * `operation` - The operation to perform.
+///
+/// # Returns
+///
+/// A `Vec<u8>` containing the serialized result, or `RustError`
+fn common_scalar_op<T, S>(num: T, scalar: S, operation: Op) -> Result<Vec<u8>, RustError>
+where
+    S: Copy + Default + PartialEq,
+    T: FheScalarOps<S>,
+    for<'a> &'a T: FheScalarOpsRef<T, S> + FheIntegerRef<T>,
+{
+    GlobalKeys::refresh_server_key_for_thread();
+
+    let result = compute_scalar_op(&num, scalar, operation);
 
     bincode::serialize(&result).map_err(|err| {
         log::error!("failed serializing result: {:?}", err);
-        RustError::generic_error(format!(
-            "failed serializing result after operation: {:?}",
-            operation
-        ))
+        RustError::serialization_failed(format!("result after scalar operation: {:?}", operation))
     })
 }
 
+/// The scalar counterpart of `FheInteger`.
+pub(crate) trait FheScalarOps<S>:
+    FheOrd<S, Output = Self>
+    + FheEq<S, Output = Self>
+    + FheMin<S, Output = Self>
+    + FheMax<S, Output = Self>
+    + FheInteger
+    + FheTrivialEncrypt<S>
+{
+}
+
+impl<T, S> FheScalarOps<S> for T where
+    T: FheOrd<S, Output = T>
+        + FheEq<S, Output = T>
+        + FheMin<S, Output = T>
+        + FheMax<S, Output = T>
+        + FheInteger
+        + FheTrivialEncrypt<S>
+{
+}
+
+/// The scalar counterpart of `FheIntegerRef`.
+pub(crate) trait FheScalarOpsRef<T, S>:
+    Add<S, Output = T>
+    + Sub<S, Output = T>
+    + Mul<S, Output = T>
+    + Div<S, Output = T>
+    + Rem<S, Output = T>
+    + Shl<S, Output = T>
+    + Shr<S, Output = T>
+{
+}
+
+impl<T, S, R> FheScalarOpsRef<T, S> for R where
+    R: Add<S, Output = T>
+        + Sub<S, Output = T>
+        + Mul<S, Output = T>
+        + Div<S, Output = T>
+        + Rem<S, Output = T>
+        + Shl<S, Output = T>
+        + Shr<S, Output = T>
+{
+}
+
+/// Truncates a `u64` scalar operand to a `bits` wide integer. Encrypted shift amounts are taken
+/// modulo the bit width, so the same is done for scalar shifts.
+pub(crate) fn scalar_operand(rhs: u64, operation: Op, bits: u32) -> u64 {
+    match operation {
+        Op::Shl | Op::Shr => rhs % bits as u64,
+        _ => rhs,
+    }
+}
+
+/// Performs the given operation on a live encrypted number and a scalar.
+///
+/// The caller is responsible for installing the server key on the current thread.
+pub(crate) fn compute_scalar_op<T, S>(num: &T, scalar: S, operation: Op) -> T
+where
+    S: Copy + Default + PartialEq,
+    T: FheScalarOps<S>,
+    for<'a> &'a T: FheScalarOpsRef<T, S> + FheIntegerRef<T>,
+{
+    let is_zero = scalar == S::default();
+
+    match operation {
+        Op::Add => num + scalar,
+        Op::Sub => num - scalar,
+        Op::Mul => num * scalar,
+        Op::Lt => num.lt(scalar),
+        Op::Lte => num.le(scalar),
+        Op::Div if is_zero => num / &T::encrypt_trivial(scalar),
+        Op::Div => num / scalar,
+        Op::Gt => num.gt(scalar),
+        Op::Gte => num.ge(scalar),
+        Op::Rem if is_zero => num % &T::encrypt_trivial(scalar),
+        Op::Rem => num % scalar,
+        Op::BitAnd => num & &T::encrypt_trivial(scalar),
+        Op::BitOr => num | &T::encrypt_trivial(scalar),
+        Op::BitXor => num ^ &T::encrypt_trivial(scalar),
+        Op::Eq => num.eq(scalar),
+        Op::Ne => num.ne(scalar),
+        Op::Min => num.min(scalar),
+        Op::Max => num.max(scalar),
+        Op::Shl => num << scalar,
+        Op::Shr => num >> scalar,
+    }
+}
+
 /// Performs the specified operation on an encrypted x-bit unsigned integer.
 ///
 /// This function deserializes the input, performs the specified operation, and
@@ -138,7 +410,12 @@ macro_rules! define_unary_op_fn {
     ($func_name:ident, $deserialize_func:ident, $type:ty) => {
         #[export_name = stringify!($func_name)]
         pub fn $func_name(lhs: &[u8], operation: UnaryOp) -> Result<Vec<u8>, RustError> {
-            unary_op($deserialize_func(lhs, false).unwrap(), operation)
+            let value = $deserialize_func(lhs, false).map_err(|e| {
+                log::error!("failed deserializing value: {:?}", e);
+                RustError::deserialization_failed("value", e)
+            })?;
+
+            unary_op(value, operation)
         }
     };
 }
@@ -147,6 +424,26 @@ macro_rules! define_unary_op_fn {
 define_unary_op_fn!(unary_op_uint8, deserialize_fhe_uint8, FheUint8);
 define_unary_op_fn!(unary_op_uint16, deserialize_fhe_uint16, FheUint16);
 define_unary_op_fn!(unary_op_uint32, deserialize_fhe_uint32, FheUint32);
+define_unary_op_fn!(unary_op_uint64, deserialize_fhe_uint64, FheUint64);
+
+/// Performs the specified operation on an encrypted boolean.
+pub fn unary_op_bool(lhs: &[u8], operation: UnaryOp) -> Result<Vec<u8>, RustError> {
+    let value = deserialize_fhe_bool(lhs).map_err(|e| {
+        log::error!("failed deserializing bool value: {:?}", e);
+        RustError::deserialization_failed("bool value", e)
+    })?;
+
+    GlobalKeys::refresh_server_key_for_thread();
+
+    let result = match operation {
+        UnaryOp::Not => compute_bool_not(&value)?,
+    };
+
+    bincode::serialize(&result).map_err(|err| {
+        log::debug!("failed to serialize result: {:?}", err);
+        RustError::serialization_failed(format!("result after operation: {:?}", operation))
+    })
+}
 
 /// A generic function that performs the given operation on a single encrypted number.
 ///
@@ -162,22 +459,35 @@ define_unary_op_fn!(unary_op_uint32, deserialize_fhe_uint32, FheUint32);
 /// # Returns
 ///
 /// An `UnmanagedVector` containing the serialized result.
-fn unary_op<T: Not<Output = T> + Serialize>(
-    num1: T,
-    operation: UnaryOp,
-) -> Result<Vec<u8>, RustError> {
+fn unary_op<T: Serialize>(num1: T, operation: UnaryOp) -> Result<Vec<u8>, RustError>
+where
+    for<'a> &'a T: Not<Output = T>,
+{
     GlobalKeys::refresh_server_key_for_thread();
 
-    let result = match operation {
-        UnaryOp::Not => !num1,
-        // todo add remaining unary ops
-    };
+    let result = compute_unary_op(&num1, operation);
 
     bincode::serialize(&result).map_err(|err| {
         log::debug!("failed to serialize result: {:?}", err);
-        RustError::generic_error(format!(
-            "failed to serialize result after operation: {:?}",
-            operation
-        ))
+        RustError::serialization_failed(format!("result after operation: {:?}", operation))
     })
 }
+
+/// Performs the given operation on a single live encrypted number.
+///
+/// The caller is responsible for installing the server key on the current thread.
+pub(crate) fn compute_unary_op<T>(num1: &T, operation: UnaryOp) -> T
+where
+    for<'a> &'a T: Not<Output = T>,
+{
+    match operation {
+        UnaryOp::Not => !num1,
+        // todo add remaining unary ops
+    }
+}
+
+/// The logical not of an encrypted boolean. A bitwise not would turn 1 into the largest value
+/// of the block, so this is computed as `x ^ 1` instead.
+pub(crate) fn compute_bool_not(value: &FheUint8) -> Result<FheUint8, RustError> {
+    Ok(value ^ &boolean::trivial(true)?)
+}
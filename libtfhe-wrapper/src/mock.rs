@@ -0,0 +1,435 @@
+//! A plaintext stand-in for the tfhe backend, for testing contract logic without paying for FHE.
+//!
+//! When the mock backend is enabled every "ciphertext" is the plaintext value, stored as a
+//! little-endian `u64` inside the usual envelope with the mock flag set, and a list is the
+//! concatenation of its values. The FFI surface, the
+//! overflow semantics and the envelope checks stay the same as with tfhe, but no keys are needed
+//! and every operation runs in nanoseconds.
+//!
+//! Mock ciphertexts are only accepted while the mock backend is enabled, and real ciphertexts
+//! only while it is disabled, so the two can never be mixed up. This is **not** encryption -
+//! never enable the mock backend outside of tests.
+//!
+//! The switch only exists in builds with the `mock-backend` feature. Without it
+//! `is_mock_backend` is always false, so a production library cannot be put into mock mode.
+
+#[cfg(feature = "mock-backend")]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::api::{FheUintType, Op, UnaryOp};
+use crate::encryption::check_expand_list_len;
+use crate::envelope::{
+    open_for_backend, open_list_for_backend, seal_for_backend, seal_list_for_backend,
+};
+use crate::error::RustError;
+use crate::math::{check_bool_op, check_cmux_control, scalar_operand};
+
+#[cfg(feature = "mock-backend")]
+static MOCK_BACKEND: AtomicBool = AtomicBool::new(false);
+
+const MOCK_PAYLOAD_LEN: usize = 8;
+
+#[cfg(feature = "mock-backend")]
+pub fn is_mock_backend() -> bool {
+    MOCK_BACKEND.load(Ordering::Relaxed)
+}
+
+#[cfg(not(feature = "mock-backend"))]
+pub fn is_mock_backend() -> bool {
+    false
+}
+
+/// Switches every entry point between the tfhe backend and the plaintext mock.
+#[cfg(feature = "mock-backend")]
+pub fn set_mock_backend(enabled: bool) {
+    if enabled {
+        log::warn!("mock backend enabled - ciphertexts are NOT encrypted");
+    }
+    MOCK_BACKEND.store(enabled, Ordering::Relaxed);
+}
+
+fn bits(uint_type: FheUintType) -> u32 {
+    match uint_type {
+        FheUintType::Uint8 | FheUintType::Bool => 8,
+        FheUintType::Uint16 => 16,
+        FheUintType::Uint32 => 32,
+        FheUintType::Uint64 => 64,
+    }
+}
+
+/// The largest value a `uint_type` can hold.
+fn mask(uint_type: FheUintType) -> u64 {
+    match uint_type {
+        FheUintType::Bool => 1,
+        FheUintType::Uint64 => u64::MAX,
+        other => (1 << bits(other)) - 1,
+    }
+}
+
+/// Normalizes a plaintext the same way `encrypt` and `trivial_encrypt` do.
+fn plaintext(msg: u64, uint_type: FheUintType) -> u64 {
+    match uint_type {
+        FheUintType::Bool => (msg != 0) as u64,
+        other => msg & mask(other),
+    }
+}
+
+fn seal_value(value: u64, uint_type: FheUintType, compact: bool) -> Vec<u8> {
+    seal_for_backend(&value.to_le_bytes(), uint_type, compact, true)
+}
+
+fn open_value(bytes: &[u8], uint_type: FheUintType, compact: bool) -> Result<u64, RustError> {
+    let payload = open_for_backend(bytes, uint_type, compact, true)?;
+
+    let value = <[u8; MOCK_PAYLOAD_LEN]>::try_from(payload)
+        .map(u64::from_le_bytes)
+        .map_err(|_| {
+            log::error!("mock ciphertext payload has {} bytes", payload.len());
+            RustError::invalid_ciphertext(format!(
+                "mock ciphertext payload must be {} bytes, got {}",
+                MOCK_PAYLOAD_LEN,
+                payload.len()
+            ))
+        })?;
+
+    if value > mask(uint_type) {
+        log::error!(
+            "mock ciphertext value {} out of range for {:?}",
+            value,
+            uint_type
+        );
+        return Err(RustError::invalid_ciphertext(format!(
+            "mock value out of range for {:?}",
+            uint_type
+        )));
+    }
+
+    Ok(value)
+}
+
+/// Performs `operation` on two plaintexts of type `uint_type`, wrapping like the encrypted
+/// integers do. Division by zero gives the maximum value and the remainder by zero gives `lhs`.
+pub(crate) fn compute_op(lhs: u64, rhs: u64, operation: Op, uint_type: FheUintType) -> u64 {
+    let mask = mask(uint_type);
+    let bits = bits(uint_type);
+
+    let result = match operation {
+        Op::Add => lhs.wrapping_add(rhs),
+        Op::Sub => lhs.wrapping_sub(rhs),
+        Op::Mul => lhs.wrapping_mul(rhs),
+        Op::Lt => (lhs < rhs) as u64,
+        Op::Lte => (lhs <= rhs) as u64,
+        Op::Div => lhs.checked_div(rhs).unwrap_or(mask),
+        Op::Gt => (lhs > rhs) as u64,
+        Op::Gte => (lhs >= rhs) as u64,
+        Op::Rem => lhs.checked_rem(rhs).unwrap_or(lhs),
+        Op::BitAnd => lhs & rhs,
+        Op::BitOr => lhs | rhs,
+        Op::BitXor => lhs ^ rhs,
+        Op::Eq => (lhs == rhs) as u64,
+        Op::Ne => (lhs != rhs) as u64,
+        Op::Min => lhs.min(rhs),
+        Op::Max => lhs.max(rhs),
+        Op::Shl => lhs << (rhs % bits as u64),
+        Op::Shr => lhs >> (rhs % bits as u64),
+    };
+
+    result & mask
+}
+
+pub fn encrypt(msg: u64, uint_type: FheUintType) -> Vec<u8> {
+    seal_value(plaintext(msg, uint_type), uint_type, true)
+}
+
+pub fn trivial_encrypt(msg: u64, uint_type: FheUintType) -> Vec<u8> {
+    seal_value(plaintext(msg, uint_type), uint_type, false)
+}
+
+pub fn expand_compressed(ciphertext: &[u8], uint_type: FheUintType) -> Result<Vec<u8>, RustError> {
+    let value = open_value(ciphertext, uint_type, true)?;
+    Ok(seal_value(value, uint_type, false))
+}
+
+pub fn encrypt_list(values: &[u64], uint_type: FheUintType) -> Vec<u8> {
+    let payload: Vec<u8> = values
+        .iter()
+        .flat_map(|value| plaintext(*value, uint_type).to_le_bytes())
+        .collect();
+    seal_list_for_backend(&payload, uint_type, true)
+}
+
+pub fn expand_list(list: &[u8], uint_type: FheUintType) -> Result<Vec<Vec<u8>>, RustError> {
+    let payload = open_list_for_backend(list, uint_type, true)?;
+    if payload.is_empty() || payload.len() % MOCK_PAYLOAD_LEN != 0 {
+        log::error!("mock list payload has {} bytes", payload.len());
+        return Err(RustError::invalid_ciphertext(format!(
+            "mock list payload must be a multiple of {} bytes, got {}",
+            MOCK_PAYLOAD_LEN,
+            payload.len()
+        )));
+    }
+    check_expand_list_len(payload.len() / MOCK_PAYLOAD_LEN)?;
+
+    payload
+        .chunks(MOCK_PAYLOAD_LEN)
+        .map(|chunk| {
+            let mut le_bytes = [0u8; MOCK_PAYLOAD_LEN];
+            le_bytes.copy_from_slice(chunk);
+            let value = u64::from_le_bytes(le_bytes);
+            if value > mask(uint_type) {
+                return Err(RustError::invalid_ciphertext(format!(
+                    "mock value out of range for {:?}",
+                    uint_type
+                )));
+            }
+            Ok(seal_value(value, uint_type, false))
+        })
+        .collect()
+}
+
+pub fn decrypt(ciphertext: &[u8], uint_type: FheUintType) -> Result<u64, RustError> {
+    open_value(ciphertext, uint_type, false)
+}
+
+pub fn math_operation(
+    lhs: &[u8],
+    rhs: &[u8],
+    operation: Op,
+    uint_type: FheUintType,
+) -> Result<Vec<u8>, RustError> {
+    if uint_type == FheUintType::Bool {
+        check_bool_op(operation)?;
+    }
+
+    let lhs = open_value(lhs, uint_type, false)?;
+    let rhs = open_value(rhs, uint_type, false)?;
+
+    let result = compute_op(lhs, rhs, operation, uint_type);
+    Ok(seal_value(result, uint_type, false))
+}
+
+pub fn scalar_math_operation(
+    lhs: &[u8],
+    rhs: u64,
+    operation: Op,
+    uint_type: FheUintType,
+) -> Result<Vec<u8>, RustError> {
+    let rhs = match uint_type {
+        FheUintType::Bool => {
+            check_bool_op(operation)?;
+            (rhs != 0) as u64
+        }
+        other => scalar_operand(rhs, operation, bits(other)) & mask(other),
+    };
+
+    let lhs = open_value(lhs, uint_type, false)?;
+
+    let result = compute_op(lhs, rhs, operation, uint_type);
+    Ok(seal_value(result, uint_type, false))
+}
+
+pub fn unary_math_operation(
+    lhs: &[u8],
+    operation: UnaryOp,
+    uint_type: FheUintType,
+) -> Result<Vec<u8>, RustError> {
+    let lhs = open_value(lhs, uint_type, false)?;
+
+    // `mask` is 1 for bools, so this is the logical not there
+    let result = match operation {
+        UnaryOp::Not => lhs ^ mask(uint_type),
+    };
+    Ok(seal_value(result, uint_type, false))
+}
+
+pub fn cast(
+    val: &[u8],
+    from_type: FheUintType,
+    to_type: FheUintType,
+) -> Result<Vec<u8>, RustError> {
+    let value = open_value(val, from_type, false)?;
+    Ok(seal_value(plaintext(value, to_type), to_type, false))
+}
+
+pub fn comparison_operation(
+    lhs: &[u8],
+    rhs: &[u8],
+    operation: Op,
+    uint_type: FheUintType,
+) -> Result<Vec<u8>, RustError> {
+    match operation {
+        Op::Lt | Op::Lte | Op::Gt | Op::Gte | Op::Eq | Op::Ne => {}
+        _ => {
+            return Err(RustError::unsupported_operation(format!(
+                "{:?} is not a comparison operation",
+                operation
+            )))
+        }
+    }
+
+    let lhs = open_value(lhs, uint_type, false)?;
+    let rhs = open_value(rhs, uint_type, false)?;
+
+    let result = compute_op(lhs, rhs, operation, uint_type);
+    Ok(seal_value(result, FheUintType::Bool, false))
+}
+
+/// Mirrors the mask arithmetic of the encrypted cmux, including for controls other than 0 and 1.
+pub fn cmux(
+    control_type: FheUintType,
+    uint_type: FheUintType,
+    control: &[u8],
+    if_true: &[u8],
+    if_false: &[u8],
+) -> Result<Vec<u8>, RustError> {
+    check_cmux_control(control_type, uint_type)?;
+
+    let control = open_value(control, control_type, false)?;
+    let if_true = open_value(if_true, uint_type, false)?;
+    let if_false = open_value(if_false, uint_type, false)?;
+
+    let control = plaintext(control, uint_type);
+    let select = 0u64.wrapping_sub(control) & mask(uint_type);
+
+    let result = (select & if_true) | (!select & if_false);
+    Ok(seal_value(result & mask(uint_type), uint_type, false))
+}
+
+#[cfg(all(test, feature = "mock-backend"))]
+mod tests {
+    use super::*;
+    use crate::keys::{GlobalKeys, KeySetId};
+
+    /// Mock ciphertexts are still stamped with the key set fingerprint, so the tests run under a
+    /// key set that no other test loads keys into.
+    const MOCK_KEY_SET: KeySetId = 98;
+
+    const TYPES: [FheUintType; 5] = [
+        FheUintType::Uint8,
+        FheUintType::Uint16,
+        FheUintType::Uint32,
+        FheUintType::Uint64,
+        FheUintType::Bool,
+    ];
+
+    #[test]
+    fn mock_round_trip() {
+        let _key_set = GlobalKeys::use_key_set(MOCK_KEY_SET);
+        for uint_type in TYPES {
+            let compact = encrypt(1, uint_type);
+            assert!(decrypt(&compact, uint_type).is_err());
+
+            let expanded = expand_compressed(&compact, uint_type).unwrap();
+            assert_eq!(decrypt(&expanded, uint_type).unwrap(), 1);
+        }
+
+        assert_eq!(
+            decrypt(
+                &trivial_encrypt(0x1ff, FheUintType::Uint8),
+                FheUintType::Uint8
+            )
+            .unwrap(),
+            0xff
+        );
+        assert_eq!(
+            decrypt(&trivial_encrypt(7, FheUintType::Bool), FheUintType::Bool).unwrap(),
+            1
+        );
+
+        let list = encrypt_list(&[1, 0x1ff, 3], FheUintType::Uint8);
+        let values: Vec<u64> = expand_list(&list, FheUintType::Uint8)
+            .unwrap()
+            .iter()
+            .map(|ciphertext| decrypt(ciphertext, FheUintType::Uint8).unwrap())
+            .collect();
+        assert_eq!(values, [1, 0xff, 3]);
+        assert!(expand_compressed(&list, FheUintType::Uint8).is_err());
+    }
+
+    #[test]
+    fn mock_arithmetic_wraps_like_tfhe() {
+        let _key_set = GlobalKeys::use_key_set(MOCK_KEY_SET);
+        use FheUintType::*;
+
+        assert_eq!(compute_op(250, 10, Op::Add, Uint8), 4);
+        assert_eq!(compute_op(1, 2, Op::Sub, Uint16), 0xfffe);
+        assert_eq!(compute_op(0x10000, 0x10000, Op::Mul, Uint32), 0);
+        assert_eq!(compute_op(u64::MAX, 2, Op::Add, Uint64), 1);
+        assert_eq!(compute_op(7, 0, Op::Div, Uint16), 0xffff);
+        assert_eq!(compute_op(7, 0, Op::Rem, Uint16), 7);
+        assert_eq!(compute_op(1, 9, Op::Shl, Uint8), 2);
+        assert_eq!(compute_op(3, 5, Op::Min, Uint32), 3);
+        assert_eq!(compute_op(3, 5, Op::Gte, Uint32), 0);
+
+        let a = trivial_encrypt(200, Uint8);
+        let b = trivial_encrypt(100, Uint8);
+        let sum = math_operation(&a, &b, Op::Add, Uint8).unwrap();
+        assert_eq!(decrypt(&sum, Uint8).unwrap(), 44);
+
+        let shifted = scalar_math_operation(&a, 8 + 1, Op::Shr, Uint8).unwrap();
+        assert_eq!(decrypt(&shifted, Uint8).unwrap(), 100);
+
+        let not = unary_math_operation(&b, UnaryOp::Not, Uint8).unwrap();
+        assert_eq!(decrypt(&not, Uint8).unwrap(), !100u8 as u64);
+    }
+
+    #[test]
+    fn mock_bools_stay_boolean() {
+        let _key_set = GlobalKeys::use_key_set(MOCK_KEY_SET);
+        let t = trivial_encrypt(1, FheUintType::Bool);
+        let f = trivial_encrypt(0, FheUintType::Bool);
+
+        let not = unary_math_operation(&t, UnaryOp::Not, FheUintType::Bool).unwrap();
+        assert_eq!(decrypt(&not, FheUintType::Bool).unwrap(), 0);
+
+        let or = scalar_math_operation(&f, 5, Op::BitOr, FheUintType::Bool).unwrap();
+        assert_eq!(decrypt(&or, FheUintType::Bool).unwrap(), 1);
+
+        assert!(matches!(
+            math_operation(&t, &f, Op::Add, FheUintType::Bool),
+            Err(RustError::UnsupportedOperation { .. })
+        ));
+    }
+
+    #[test]
+    fn mock_cast_comparison_and_cmux() {
+        let _key_set = GlobalKeys::use_key_set(MOCK_KEY_SET);
+        use FheUintType::*;
+
+        let wide = trivial_encrypt(0x1234, Uint16);
+        let narrow = cast(&wide, Uint16, Uint8).unwrap();
+        assert_eq!(decrypt(&narrow, Uint8).unwrap(), 0x34);
+        let flag = cast(&wide, Uint16, Bool).unwrap();
+        assert_eq!(decrypt(&flag, Bool).unwrap(), 1);
+
+        let a = trivial_encrypt(3, Uint32);
+        let b = trivial_encrypt(9, Uint32);
+        let lt = comparison_operation(&a, &b, Op::Lt, Uint32).unwrap();
+        assert_eq!(decrypt(&lt, Bool).unwrap(), 1);
+        assert!(comparison_operation(&a, &b, Op::Add, Uint32).is_err());
+
+        let picked = cmux(Bool, Uint32, &lt, &a, &b).unwrap();
+        assert_eq!(decrypt(&picked, Uint32).unwrap(), 3);
+
+        let ge = comparison_operation(&a, &b, Op::Gte, Uint32).unwrap();
+        let picked = cmux(Bool, Uint32, &ge, &a, &b).unwrap();
+        assert_eq!(decrypt(&picked, Uint32).unwrap(), 9);
+        assert!(matches!(
+            cmux(Uint64, Uint32, &a, &a, &b),
+            Err(RustError::TypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn mock_and_real_ciphertexts_do_not_mix() {
+        let _key_set = GlobalKeys::use_key_set(MOCK_KEY_SET);
+        let real = seal_for_backend(&[0; 8], FheUintType::Uint8, false, false);
+        assert!(matches!(
+            decrypt(&real, FheUintType::Uint8),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+
+        let out_of_range = seal_for_backend(&2u64.to_le_bytes(), FheUintType::Bool, false, true);
+        assert!(decrypt(&out_of_range, FheUintType::Bool).is_err());
+    }
+}
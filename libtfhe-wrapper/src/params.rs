@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+use tfhe::shortint::parameters::{
+    ClassicPBSParameters, PARAM_MESSAGE_1_CARRY_1_COMPACT_PK, PARAM_MESSAGE_1_CARRY_1_KS_PBS,
+    PARAM_MESSAGE_1_CARRY_1_PBS_KS, PARAM_MESSAGE_2_CARRY_2_COMPACT_PK,
+    PARAM_MESSAGE_2_CARRY_2_KS_PBS, PARAM_MESSAGE_2_CARRY_2_PBS_KS,
+};
+use tfhe::{Config, ConfigBuilder};
+
+/// The named tfhe parameter sets keys can be generated with.
+///
+/// The numeric values are recorded in key files, so never renumber them, only append new ones.
+///
+/// cbindgen:prefix-with-name
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParameterProfile {
+    /// The parameters every key set was generated with before profiles existed.
+    #[default]
+    Message2Carry2KsPbs = 0,
+    Message2Carry2PbsKs = 1,
+    Message1Carry1KsPbs = 2,
+    Message1Carry1PbsKs = 3,
+    Message2Carry2CompactPk = 4,
+    Message1Carry1CompactPk = 5,
+}
+
+impl ParameterProfile {
+    pub fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(ParameterProfile::Message2Carry2KsPbs),
+            1 => Some(ParameterProfile::Message2Carry2PbsKs),
+            2 => Some(ParameterProfile::Message1Carry1KsPbs),
+            3 => Some(ParameterProfile::Message1Carry1PbsKs),
+            4 => Some(ParameterProfile::Message2Carry2CompactPk),
+            5 => Some(ParameterProfile::Message1Carry1CompactPk),
+            _ => None,
+        }
+    }
+
+    pub fn tag(self) -> u8 {
+        self as u8
+    }
+
+    /// The name of the underlying tfhe parameter set.
+    pub fn name(self) -> &'static str {
+        match self {
+            ParameterProfile::Message2Carry2KsPbs => "PARAM_MESSAGE_2_CARRY_2_KS_PBS",
+            ParameterProfile::Message2Carry2PbsKs => "PARAM_MESSAGE_2_CARRY_2_PBS_KS",
+            ParameterProfile::Message1Carry1KsPbs => "PARAM_MESSAGE_1_CARRY_1_KS_PBS",
+            ParameterProfile::Message1Carry1PbsKs => "PARAM_MESSAGE_1_CARRY_1_PBS_KS",
+            ParameterProfile::Message2Carry2CompactPk => "PARAM_MESSAGE_2_CARRY_2_COMPACT_PK",
+            ParameterProfile::Message1Carry1CompactPk => "PARAM_MESSAGE_1_CARRY_1_COMPACT_PK",
+        }
+    }
+
+    /// Bit widths of the integer types keys of this profile work with. Every profile enables the
+    /// radix integers the wrapper exposes, so this is the same for all of them - a `Bool` is
+    /// stored as an 8 bit integer.
+    pub fn integer_widths(self) -> &'static [u32] {
+        &[8, 16, 32, 64]
+    }
+
+    /// The underlying tfhe parameter set.
+    pub fn shortint_parameters(self) -> ClassicPBSParameters {
+        match self {
+            ParameterProfile::Message2Carry2KsPbs => PARAM_MESSAGE_2_CARRY_2_KS_PBS,
+            ParameterProfile::Message2Carry2PbsKs => PARAM_MESSAGE_2_CARRY_2_PBS_KS,
+            ParameterProfile::Message1Carry1KsPbs => PARAM_MESSAGE_1_CARRY_1_KS_PBS,
+            ParameterProfile::Message1Carry1PbsKs => PARAM_MESSAGE_1_CARRY_1_PBS_KS,
+            ParameterProfile::Message2Carry2CompactPk => PARAM_MESSAGE_2_CARRY_2_COMPACT_PK,
+            ParameterProfile::Message1Carry1CompactPk => PARAM_MESSAGE_1_CARRY_1_COMPACT_PK,
+        }
+    }
+
+    /// The tfhe config to generate keys with.
+    pub fn config(self) -> Config {
+        ConfigBuilder::all_disabled()
+            .enable_custom_integers(self.shortint_parameters(), None)
+            .build()
+    }
+}
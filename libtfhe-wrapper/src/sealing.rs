@@ -0,0 +1,181 @@
+//! Re-encrypting decrypted values to a user's X25519 key, so the plaintext never leaves the
+//! library.
+//!
+//! Values are sealed with an ephemeral sender key in a NaCl box (x25519-xsalsa20-poly1305) and
+//! returned in the JSON layout of MetaMask's `eth_decrypt`, which is what wallets and the Go
+//! oracle already use:
+//!
+//! ```json
+//! {"version":"x25519-xsalsa20-poly1305","nonce":"<b64>","ephemPublicKey":"<b64>","ciphertext":"<b64>"}
+//! ```
+//!
+//! The boxed plaintext is the value as a big-endian integer without leading zero bytes, so zero
+//! seals to an empty message.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use crypto_box::aead::generic_array::GenericArray;
+use crypto_box::aead::{Aead, AeadCore, OsRng};
+use crypto_box::{PublicKey, SalsaBox, SecretKey};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::api::FheUintType;
+use crate::encryption::decrypt_safe;
+use crate::error::RustError;
+
+pub const SEALING_VERSION: &str = "x25519-xsalsa20-poly1305";
+pub const SEALING_KEY_LEN: usize = 32;
+
+/// A sealed value, as serialized to JSON.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SealedOutput {
+    pub version: String,
+    pub nonce: String,
+    pub ephem_public_key: String,
+    pub ciphertext: String,
+}
+
+fn read_key(key: &[u8], what: &str) -> Result<[u8; SEALING_KEY_LEN], RustError> {
+    key.try_into().map_err(|_| {
+        log::error!(
+            "{} must be {} bytes, got {}",
+            what,
+            SEALING_KEY_LEN,
+            key.len()
+        );
+        RustError::invalid_argument(format!(
+            "{} must be {} bytes, got {}",
+            what,
+            SEALING_KEY_LEN,
+            key.len()
+        ))
+    })
+}
+
+/// Decrypts `ciphertext` with the client key of the active key set and seals the value to
+/// `user_public_key`, returning the JSON encoded `SealedOutput`.
+pub fn seal_output_safe(
+    ciphertext: &[u8],
+    uint_type: FheUintType,
+    user_public_key: &[u8],
+) -> Result<Vec<u8>, RustError> {
+    let user_public_key = PublicKey::from(read_key(user_public_key, "user public key")?);
+    let value = Zeroizing::new(decrypt_safe(ciphertext, uint_type)?);
+
+    let be_bytes = Zeroizing::new(value.to_be_bytes());
+    let leading_zeros = be_bytes.iter().take_while(|byte| **byte == 0).count();
+
+    let ephemeral_key = SecretKey::generate(&mut OsRng);
+    let nonce = SalsaBox::generate_nonce(&mut OsRng);
+    let ciphertext = SalsaBox::new(&user_public_key, &ephemeral_key)
+        .encrypt(&nonce, &be_bytes[leading_zeros..])
+        .map_err(|_| {
+            log::error!("failed sealing output");
+            RustError::generic_error("failed sealing output")
+        })?;
+
+    let sealed = SealedOutput {
+        version: SEALING_VERSION.to_string(),
+        nonce: BASE64.encode(nonce),
+        ephem_public_key: BASE64.encode(ephemeral_key.public_key().as_bytes()),
+        ciphertext: BASE64.encode(ciphertext),
+    };
+
+    serde_json::to_vec(&sealed).map_err(|err| {
+        log::error!("failed serializing sealed output: {:?}", err);
+        RustError::serialization_failed("sealed output")
+    })
+}
+
+/// Opens the output of `seal_output_safe` with the user's X25519 secret key. Meant for tests -
+/// users open their outputs in the wallet.
+pub fn unseal_output(sealed: &[u8], user_secret_key: &[u8]) -> Result<u64, RustError> {
+    let user_secret_key = SecretKey::from(read_key(user_secret_key, "user secret key")?);
+
+    let sealed: SealedOutput = serde_json::from_slice(sealed).map_err(|err| {
+        log::error!("failed deserializing sealed output: {:?}", err);
+        RustError::deserialization_failed("sealed output", err)
+    })?;
+    if sealed.version != SEALING_VERSION {
+        return Err(RustError::deserialization_failed(
+            "sealed output",
+            format!("unsupported version {}", sealed.version),
+        ));
+    }
+
+    let decode = |field: &str, value: &str| {
+        BASE64
+            .decode(value)
+            .map_err(|err| RustError::deserialization_failed(format!("sealed {}", field), err))
+    };
+    let nonce = decode("nonce", &sealed.nonce)?;
+    let ephemeral_public_key = decode("ephemeral public key", &sealed.ephem_public_key)?;
+    let ciphertext = decode("ciphertext", &sealed.ciphertext)?;
+    if nonce.len() != 24 {
+        return Err(RustError::deserialization_failed(
+            "sealed nonce",
+            format!("expected 24 bytes, got {}", nonce.len()),
+        ));
+    }
+
+    let ephemeral_public_key =
+        PublicKey::from(read_key(&ephemeral_public_key, "ephemeral public key")?);
+    let plaintext = SalsaBox::new(&ephemeral_public_key, &user_secret_key)
+        .decrypt(GenericArray::from_slice(&nonce), ciphertext.as_slice())
+        .map(Zeroizing::new)
+        .map_err(|_| RustError::deserialization_failed("sealed output", "failed opening box"))?;
+
+    if plaintext.len() > 8 {
+        return Err(RustError::deserialization_failed(
+            "sealed output",
+            format!("sealed value has {} bytes", plaintext.len()),
+        ));
+    }
+    let mut be_bytes = Zeroizing::new([0u8; 8]);
+    be_bytes[8 - plaintext.len()..].copy_from_slice(&plaintext);
+    Ok(u64::from_be_bytes(*be_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::{encrypt_safe, expand_compressed_safe};
+    use crate::test_utils::setup_keys;
+
+    #[test]
+    fn sealed_outputs_open_with_the_user_key() {
+        setup_keys();
+
+        let user_key = SecretKey::generate(&mut OsRng);
+        let user_public_key = user_key.public_key();
+        let other_key = SecretKey::generate(&mut OsRng);
+
+        for value in [0, 1, 0x1234, u32::MAX as u64] {
+            let compact = encrypt_safe(value, FheUintType::Uint32).unwrap();
+            let ciphertext = expand_compressed_safe(&compact, FheUintType::Uint32).unwrap();
+
+            let sealed =
+                seal_output_safe(&ciphertext, FheUintType::Uint32, user_public_key.as_bytes())
+                    .unwrap();
+            let parsed: SealedOutput = serde_json::from_slice(&sealed).unwrap();
+            assert_eq!(parsed.version, SEALING_VERSION);
+
+            assert_eq!(unseal_output(&sealed, &user_key.to_bytes()).unwrap(), value);
+            assert!(unseal_output(&sealed, &other_key.to_bytes()).is_err());
+        }
+    }
+
+    #[test]
+    fn malformed_user_keys_are_rejected() {
+        setup_keys();
+
+        let compact = encrypt_safe(1, FheUintType::Uint8).unwrap();
+        let ciphertext = expand_compressed_safe(&compact, FheUintType::Uint8).unwrap();
+        assert!(matches!(
+            seal_output_safe(&ciphertext, FheUintType::Uint8, &[1; 31]),
+            Err(RustError::InvalidArgument { .. })
+        ));
+    }
+}
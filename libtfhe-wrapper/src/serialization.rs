@@ -1,4 +1,53 @@
-use tfhe::{CompactFheUint16, CompactFheUint32, CompactFheUint8, FheUint16, FheUint32, FheUint8};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use tfhe::integer::{RadixCiphertextBig, RadixCiphertextSmall};
+use tfhe::shortint::ciphertext::{CompactCiphertextListBig, CompactCiphertextListSmall};
+use tfhe::{
+    CompactFheUint16, CompactFheUint16List, CompactFheUint32, CompactFheUint32List,
+    CompactFheUint64, CompactFheUint64List, CompactFheUint8, CompactFheUint8List, FheUint16,
+    FheUint32, FheUint64, FheUint8,
+};
+
+use crate::validation::MAX_CIPHERTEXT_LEN;
+
+/// The options of `bincode::deserialize`, but refusing to read - and so to allocate - more than
+/// `MAX_CIPHERTEXT_LEN` bytes, whatever the length prefixes in the input claim.
+pub(crate) fn ciphertext_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_CIPHERTEXT_LEN as u64)
+}
+
+/// tfhe's high level integers serialize as the radix ciphertext, in whichever form the keys
+/// encrypt to, followed by a zero-sized type id. These mirror that layout, so the blocks of a
+/// ciphertext can be read (see `validation`) and rewritten (see `boolean`) without going through
+/// the high level API, which keeps them private.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct HighLevelCiphertext<T> {
+    pub(crate) ciphertext: T,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum RadixCiphertextDyn {
+    Big(RadixCiphertextBig),
+    Small(RadixCiphertextSmall),
+}
+
+/// tfhe's integer compact list, whose fields are private. The shortint list it wraps keeps them
+/// public, so the LWE size and count of a compact ciphertext can be read without expanding it.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct IntegerCompactList<T> {
+    pub(crate) ct_list: T,
+    /// Blocks per value. The shortint list holds the blocks of every value back to back.
+    pub(crate) num_blocks: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) enum CompactCiphertextListDyn {
+    Big(IntegerCompactList<CompactCiphertextListBig>),
+    Small(IntegerCompactList<CompactCiphertextListSmall>),
+}
 
 macro_rules! deserialize_fhe_uint {
     ($name:ident, $type:ty, $compact_type:ty) => {
@@ -18,10 +67,10 @@ macro_rules! deserialize_fhe_uint {
         /// A `Result` with the deserialized `FheUint`, or a `Box<bincode::ErrorKind>` on error.
         pub(crate) fn $name(slice: &[u8], compact: bool) -> Result<$type, Box<bincode::ErrorKind>> {
             if compact {
-                let x: $compact_type = bincode::deserialize(slice)?;
+                let x: $compact_type = ciphertext_options().deserialize(slice)?;
                 Ok(x.expand())
             } else {
-                let x: $type = bincode::deserialize(slice).map_err(|err| {
+                let x: $type = ciphertext_options().deserialize(slice).map_err(|err| {
                     #[cfg(target_arch = "wasm32")]
                     crate::imports::console_log(
                         format!("failed deserializing: {:?}", err).as_str(),
@@ -38,6 +87,31 @@ macro_rules! deserialize_fhe_uint {
 deserialize_fhe_uint!(deserialize_fhe_uint8, FheUint8, CompactFheUint8);
 deserialize_fhe_uint!(deserialize_fhe_uint16, FheUint16, CompactFheUint16);
 deserialize_fhe_uint!(deserialize_fhe_uint32, FheUint32, CompactFheUint32);
+deserialize_fhe_uint!(deserialize_fhe_uint64, FheUint64, CompactFheUint64);
+
+/// Deserializes an expanded `Bool`, a single-block `FheUint8` (see `boolean`). Compact bools are
+/// encrypted as a whole `FheUint8`, so they go through `deserialize_fhe_uint8` and
+/// `boolean::is_non_zero` instead.
+pub(crate) fn deserialize_fhe_bool(slice: &[u8]) -> Result<FheUint8, Box<bincode::ErrorKind>> {
+    deserialize_fhe_uint8(slice, false)
+}
+
+macro_rules! deserialize_fhe_uint_list {
+    ($name:ident, $list_type:ty) => {
+        /// Deserializes a compact list of `FheUint`s. The values are expanded separately, so the
+        /// caller can check the length of the list first.
+        pub(crate) fn $name(slice: &[u8]) -> Result<$list_type, Box<bincode::ErrorKind>> {
+            ciphertext_options().deserialize(slice)
+        }
+    };
+}
+
+deserialize_fhe_uint_list!(deserialize_fhe_uint8_list, CompactFheUint8List);
+deserialize_fhe_uint_list!(deserialize_fhe_uint16_list, CompactFheUint16List);
+deserialize_fhe_uint_list!(deserialize_fhe_uint32_list, CompactFheUint32List);
+deserialize_fhe_uint_list!(deserialize_fhe_uint64_list, CompactFheUint64List);
+
+pub(crate) use deserialize_fhe_uint8_list as deserialize_fhe_bool_list;
 
 // leaving this here because debugging and modifying macros is a bitch
 // so you can just uncomment this code and play with it
@@ -54,3 +128,52 @@ deserialize_fhe_uint!(deserialize_fhe_uint32, FheUint32, CompactFheUint32);
 //         Ok(x)
 //     }
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::FheUintType;
+    use crate::encryption::encrypt_safe;
+    use crate::envelope::open;
+    use crate::test_utils::setup_keys;
+    use tfhe::integer::IntegerCiphertext;
+
+    fn round_trip<T: Serialize + serde::de::DeserializeOwned>(serialized: &[u8]) -> T {
+        let mirrored: T = ciphertext_options().deserialize(serialized).unwrap();
+        assert_eq!(bincode::serialize(&mirrored).unwrap(), serialized);
+        mirrored
+    }
+
+    #[test]
+    fn mirrored_layouts_match_tfhe() {
+        setup_keys();
+
+        let sealed = encrypt_safe(300, FheUintType::Uint16).unwrap();
+        let compact: CompactFheUint16 = ciphertext_options()
+            .deserialize(open(&sealed, FheUintType::Uint16, true).unwrap())
+            .unwrap();
+        let expanded: FheUint16 = compact.expand();
+
+        let list: HighLevelCiphertext<CompactCiphertextListDyn> =
+            round_trip(&bincode::serialize(&compact).unwrap());
+        let radix: HighLevelCiphertext<RadixCiphertextDyn> =
+            round_trip(&bincode::serialize(&expanded).unwrap());
+
+        let blocks = match radix.ciphertext {
+            RadixCiphertextDyn::Big(radix) => radix.blocks().len(),
+            RadixCiphertextDyn::Small(radix) => radix.blocks().len(),
+        };
+        let (list_blocks, num_blocks) = match list.ciphertext {
+            CompactCiphertextListDyn::Big(list) => (
+                list.ct_list.ct_list.lwe_ciphertext_count().0,
+                list.num_blocks,
+            ),
+            CompactCiphertextListDyn::Small(list) => (
+                list.ct_list.ct_list.lwe_ciphertext_count().0,
+                list.num_blocks,
+            ),
+        };
+        assert_eq!(list_blocks, blocks);
+        assert_eq!(num_blocks, blocks);
+    }
+}
@@ -0,0 +1,34 @@
+use once_cell::sync::OnceCell;
+
+use crate::keys::{
+    deserialize_client_key_safe, deserialize_public_key_safe, generate_keys_safe,
+    load_server_key_safe, DEFAULT_KEY_SET,
+};
+use crate::params::ParameterProfile;
+
+/// Serialized client, server and public key of the test key set.
+pub(crate) type TestKeys = (Vec<u8>, Vec<u8>, Vec<u8>);
+
+static TEST_KEYS: OnceCell<TestKeys> = OnceCell::new();
+
+/// Generates a fresh key set and loads it into `GlobalKeys` as the default key set.
+///
+/// Key generation is slow, so this only runs once per test binary - every test that needs keys
+/// should call it first.
+pub(crate) fn setup_keys() {
+    test_keys();
+}
+
+/// The serialized keys loaded by `setup_keys`.
+pub(crate) fn test_keys() -> &'static TestKeys {
+    TEST_KEYS.get_or_init(|| {
+        let keys = generate_keys_safe(ParameterProfile::default(), false);
+        let (cks, sks, pks) = (keys.client_key, keys.server_key, keys.public_key);
+
+        deserialize_client_key_safe(DEFAULT_KEY_SET, &cks).expect("failed loading client key");
+        load_server_key_safe(DEFAULT_KEY_SET, &sks).expect("failed loading server key");
+        deserialize_public_key_safe(DEFAULT_KEY_SET, &pks).expect("failed loading public key");
+
+        (cks.to_vec(), sks, pks)
+    })
+}
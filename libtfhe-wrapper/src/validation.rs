@@ -0,0 +1,264 @@
+//! Structural checks on incoming ciphertexts, run before they reach tfhe's operations.
+//!
+//! tfhe deserializes whatever bincode can parse, so a ciphertext with the wrong LWE dimension or
+//! block count is only noticed - by a panic - deep inside an operation. The radix ciphertext (or
+//! compact list) is therefore deserialized on its own first, under the bincode size limit of
+//! `serialization`, and its block count and LWE dimension are compared with the parameters of the
+//! active key set. Those come from the key files, so no public key is needed.
+
+use bincode::Options;
+use tfhe::integer::IntegerCiphertext;
+use tfhe::shortint::parameters::EncryptionKeyChoice;
+
+use crate::api::FheUintType;
+use crate::error::RustError;
+use crate::keys::GlobalKeys;
+use crate::params::ParameterProfile;
+use crate::serialization::{
+    ciphertext_options, CompactCiphertextListDyn, HighLevelCiphertext, RadixCiphertextDyn,
+};
+
+/// The largest ciphertext payload accepted, whatever the key set. An expanded `Uint64` under the
+/// largest supported parameters is well below 1 MiB.
+pub const MAX_CIPHERTEXT_LEN: usize = 16 << 20;
+
+/// Checks that `payload` has the shape of a `uint_type` ciphertext under the active key set.
+///
+/// Fails with `RustError::InvalidCiphertext` if it is larger than `MAX_CIPHERTEXT_LEN`, does not
+/// deserialize, or if its block count or the LWE dimension of any of its blocks does not match
+/// the parameters of the key set. Without any key loaded only the size cap is checked.
+pub(crate) fn check_ciphertext(
+    payload: &[u8],
+    uint_type: FheUintType,
+    compact: bool,
+) -> Result<(), RustError> {
+    check_ciphertext_len(payload)?;
+
+    if GlobalKeys::get_key_set_fingerprint().is_none() {
+        return Ok(());
+    }
+    // keys that did not record a profile were all generated with the default one
+    let profile = GlobalKeys::get_parameter_profile().unwrap_or_default();
+    let (expected_blocks, expected_dimension) = expected_shape(profile, uint_type, compact);
+
+    let (blocks, dimensions) = block_dimensions(payload, compact).map_err(|err| {
+        log::error!("malformed {:?} ciphertext: {:?}", uint_type, err);
+        RustError::invalid_ciphertext(format!("{:?} ciphertext is malformed", uint_type))
+    })?;
+
+    if blocks != expected_blocks {
+        log::error!(
+            "malformed {:?} ciphertext: {} blocks, expected {}",
+            uint_type,
+            blocks,
+            expected_blocks
+        );
+        return Err(RustError::invalid_ciphertext(format!(
+            "{:?} ciphertext has {} blocks, but ciphertexts of the loaded keys have {}",
+            uint_type, blocks, expected_blocks
+        )));
+    }
+
+    if let Some((block, dimension)) = dimensions
+        .iter()
+        .enumerate()
+        .find(|(_, dimension)| **dimension != expected_dimension)
+    {
+        log::error!(
+            "malformed {:?} ciphertext: block {} has LWE dimension {}, expected {}",
+            uint_type,
+            block,
+            dimension,
+            expected_dimension
+        );
+        return Err(RustError::invalid_ciphertext(format!(
+            "block {} of {:?} ciphertext has LWE dimension {}, but the loaded keys use {}",
+            block, uint_type, dimension, expected_dimension
+        )));
+    }
+
+    Ok(())
+}
+
+/// Checks `payload` against `MAX_CIPHERTEXT_LEN`. This is all that is checked for compact
+/// ciphertext lists, whose size depends on the number of values they hold.
+pub(crate) fn check_ciphertext_len(payload: &[u8]) -> Result<(), RustError> {
+    if payload.len() > MAX_CIPHERTEXT_LEN {
+        log::error!(
+            "rejecting {} byte ciphertext, the limit is {}",
+            payload.len(),
+            MAX_CIPHERTEXT_LEN
+        );
+        return Err(RustError::invalid_ciphertext(format!(
+            "ciphertext of {} bytes exceeds the limit of {}",
+            payload.len(),
+            MAX_CIPHERTEXT_LEN
+        )));
+    }
+
+    Ok(())
+}
+
+/// Block count and per-block LWE dimension of a `uint_type` ciphertext under `profile`.
+fn expected_shape(
+    profile: ParameterProfile,
+    uint_type: FheUintType,
+    compact: bool,
+) -> (usize, usize) {
+    let params = profile.shortint_parameters();
+
+    let bits_per_block = params.message_modulus.0.trailing_zeros() as usize;
+    // a Bool is encrypted as a Uint8, and only narrowed to a single block once expanded
+    let blocks = match uint_type {
+        FheUintType::Bool if !compact => 1,
+        FheUintType::Uint8 | FheUintType::Bool => 8 / bits_per_block,
+        FheUintType::Uint16 => 16 / bits_per_block,
+        FheUintType::Uint32 => 32 / bits_per_block,
+        FheUintType::Uint64 => 64 / bits_per_block,
+    };
+
+    // ciphertexts live under the key they are encrypted with, which is the big (GLWE) one for
+    // KS_PBS parameters and the small (LWE) one for PBS_KS parameters
+    let dimension = match params.encryption_key_choice {
+        EncryptionKeyChoice::Big => params.glwe_dimension.0 * params.polynomial_size.0,
+        EncryptionKeyChoice::Small => params.lwe_dimension.0,
+    };
+
+    (blocks, dimension)
+}
+
+/// The block count of the ciphertext in `payload`, and the LWE dimension of its blocks.
+///
+/// Every block of a compact ciphertext shares the LWE size of its list, so it is read from the
+/// list rather than by expanding it, and reported once. A compact ciphertext holds a single value,
+/// so a list of more (or fewer) blocks than one value has is malformed.
+fn block_dimensions(
+    payload: &[u8],
+    compact: bool,
+) -> Result<(usize, Vec<usize>), Box<bincode::ErrorKind>> {
+    macro_rules! list_shape {
+        ($list:expr) => {{
+            let blocks = $list.ct_list.ct_list.lwe_ciphertext_count().0;
+            if blocks != $list.num_blocks {
+                return Err(Box::new(bincode::ErrorKind::Custom(format!(
+                    "compact list of {} blocks holds values of {}",
+                    blocks, $list.num_blocks
+                ))));
+            }
+            (
+                blocks,
+                vec![$list.ct_list.ct_list.lwe_size().to_lwe_dimension().0],
+            )
+        }};
+    }
+
+    macro_rules! radix_shape {
+        ($radix:expr) => {
+            (
+                $radix.blocks().len(),
+                $radix
+                    .blocks()
+                    .iter()
+                    .map(|block| block.ct.lwe_size().to_lwe_dimension().0)
+                    .collect(),
+            )
+        };
+    }
+
+    let shape = if compact {
+        let list: HighLevelCiphertext<CompactCiphertextListDyn> =
+            ciphertext_options().deserialize(payload)?;
+        match list.ciphertext {
+            CompactCiphertextListDyn::Big(list) => list_shape!(list),
+            CompactCiphertextListDyn::Small(list) => list_shape!(list),
+        }
+    } else {
+        let radix: HighLevelCiphertext<RadixCiphertextDyn> =
+            ciphertext_options().deserialize(payload)?;
+        match radix.ciphertext {
+            RadixCiphertextDyn::Big(radix) => radix_shape!(radix),
+            RadixCiphertextDyn::Small(radix) => radix_shape!(radix),
+        }
+    };
+
+    Ok(shape)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::{decrypt_safe, encrypt_safe, expand_compressed_safe};
+    use crate::envelope::{open, seal, ENVELOPE_HEADER_LEN};
+    use crate::keys::{load_server_key_safe, KeySetId};
+    use crate::test_utils::{setup_keys, test_keys};
+
+    const SERVER_ONLY_KEY_SET: KeySetId = 17;
+
+    #[test]
+    fn oversized_ciphertexts_are_rejected() {
+        let payload = vec![0u8; MAX_CIPHERTEXT_LEN + 1];
+        assert!(matches!(
+            check_ciphertext(&payload, FheUintType::Uint8, false),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+    }
+
+    #[test]
+    fn malformed_ciphertexts_are_rejected_before_deserializing() {
+        setup_keys();
+
+        let compact = encrypt_safe(7, FheUintType::Uint16).unwrap();
+        let expanded = expand_compressed_safe(&compact, FheUintType::Uint16).unwrap();
+        assert!(open(&compact, FheUintType::Uint16, true).is_ok());
+        assert!(open(&expanded, FheUintType::Uint16, false).is_ok());
+
+        // more blocks than the type it claims to be has
+        let wrong_type = seal(&expanded[ENVELOPE_HEADER_LEN..], FheUintType::Uint8, false);
+        assert!(matches!(
+            decrypt_safe(&wrong_type, FheUintType::Uint8),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+
+        // compact ciphertexts are checked without expanding them
+        let wrong_compact_type = seal(&compact[ENVELOPE_HEADER_LEN..], FheUintType::Uint8, true);
+        assert!(matches!(
+            open(&wrong_compact_type, FheUintType::Uint8, true),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+
+        let truncated = seal(
+            &expanded[ENVELOPE_HEADER_LEN..expanded.len() - 8],
+            FheUintType::Uint16,
+            false,
+        );
+        assert!(matches!(
+            decrypt_safe(&truncated, FheUintType::Uint16),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+
+        assert_eq!(decrypt_safe(&expanded, FheUintType::Uint16).unwrap(), 7);
+    }
+
+    #[test]
+    fn ciphertexts_are_checked_without_a_public_key() {
+        setup_keys();
+        let (_, sks, _) = test_keys();
+
+        let expanded = expand_compressed_safe(
+            &encrypt_safe(7, FheUintType::Uint16).unwrap(),
+            FheUintType::Uint16,
+        )
+        .unwrap();
+        let wrong_type = seal(&expanded[ENVELOPE_HEADER_LEN..], FheUintType::Uint8, false);
+
+        load_server_key_safe(SERVER_ONLY_KEY_SET, sks).unwrap();
+        let _key_set = GlobalKeys::use_key_set(SERVER_ONLY_KEY_SET);
+        assert!(GlobalKeys::get_public_key().is_none());
+
+        assert!(open(&expanded, FheUintType::Uint16, false).is_ok());
+        assert!(matches!(
+            open(&wrong_type, FheUintType::Uint8, false),
+            Err(RustError::InvalidCiphertext { .. })
+        ));
+    }
+}
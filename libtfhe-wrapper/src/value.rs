@@ -0,0 +1,320 @@
+use crate::api::{FheUintType, Op, UnaryOp};
+use crate::boolean;
+use crate::envelope::{open, seal};
+use crate::error::RustError;
+use crate::keys::GlobalKeys;
+use crate::math::{
+    check_bool_op, check_cmux_control, compute_bool_not, compute_op, compute_scalar_op,
+    compute_unary_op, scalar_operand, FheInteger, FheIntegerRef,
+};
+use crate::mock::is_mock_backend;
+use crate::serialization::{
+    deserialize_fhe_bool, deserialize_fhe_uint16, deserialize_fhe_uint32, deserialize_fhe_uint64,
+    deserialize_fhe_uint8,
+};
+
+use tfhe::prelude::*;
+use tfhe::{FheUint16, FheUint32, FheUint64, FheUint8};
+
+/// A deserialized ciphertext, tagged with its `FheUintType`.
+///
+/// This lets callers keep intermediate results alive between operations instead of paying for a
+/// bincode round trip on every call.
+#[derive(Clone)]
+pub(crate) enum FheValue {
+    Uint8(FheUint8),
+    Uint16(FheUint16),
+    Uint32(FheUint32),
+    Uint64(FheUint64),
+    Bool(FheUint8),
+}
+
+impl FheValue {
+    pub(crate) fn uint_type(&self) -> FheUintType {
+        match self {
+            FheValue::Uint8(_) => FheUintType::Uint8,
+            FheValue::Uint16(_) => FheUintType::Uint16,
+            FheValue::Uint32(_) => FheUintType::Uint32,
+            FheValue::Uint64(_) => FheUintType::Uint64,
+            FheValue::Bool(_) => FheUintType::Bool,
+        }
+    }
+
+    /// Deserializes an enveloped, expanded (non-compact) ciphertext of type `uint_type`.
+    pub(crate) fn from_bytes(ciphertext: &[u8], uint_type: FheUintType) -> Result<Self, RustError> {
+        if is_mock_backend() {
+            return Err(RustError::unsupported_operation(
+                "live ciphertexts are not supported by the mock backend",
+            ));
+        }
+
+        let ciphertext = open(ciphertext, uint_type, false)?;
+
+        let value = match uint_type {
+            FheUintType::Uint8 => deserialize_fhe_uint8(ciphertext, false).map(FheValue::Uint8),
+            FheUintType::Uint16 => deserialize_fhe_uint16(ciphertext, false).map(FheValue::Uint16),
+            FheUintType::Uint32 => deserialize_fhe_uint32(ciphertext, false).map(FheValue::Uint32),
+            FheUintType::Uint64 => deserialize_fhe_uint64(ciphertext, false).map(FheValue::Uint64),
+            FheUintType::Bool => deserialize_fhe_bool(ciphertext).map(FheValue::Bool),
+        };
+
+        value.map_err(|err| {
+            log::error!("failed deserializing {:?} value: {:?}", uint_type, err);
+            RustError::deserialization_failed(format!("{:?} value", uint_type), err)
+        })
+    }
+
+    pub(crate) fn to_bytes(&self) -> Result<Vec<u8>, RustError> {
+        let serialized = match self {
+            FheValue::Uint8(v) | FheValue::Bool(v) => bincode::serialize(v),
+            FheValue::Uint16(v) => bincode::serialize(v),
+            FheValue::Uint32(v) => bincode::serialize(v),
+            FheValue::Uint64(v) => bincode::serialize(v),
+        };
+
+        let serialized = serialized.map_err(|err| {
+            log::error!("failed serializing value: {:?}", err);
+            RustError::serialization_failed(format!("{:?} value", self.uint_type()))
+        })?;
+
+        Ok(seal(&serialized, self.uint_type(), false))
+    }
+
+    /// Performs `operation` on two values of the same type.
+    pub(crate) fn op(&self, rhs: &FheValue, operation: Op) -> Result<FheValue, RustError> {
+        GlobalKeys::refresh_server_key_for_thread();
+
+        match (self, rhs) {
+            (FheValue::Uint8(l), FheValue::Uint8(r)) => {
+                Ok(FheValue::Uint8(compute_op(l, r, operation)))
+            }
+            (FheValue::Uint16(l), FheValue::Uint16(r)) => {
+                Ok(FheValue::Uint16(compute_op(l, r, operation)))
+            }
+            (FheValue::Uint32(l), FheValue::Uint32(r)) => {
+                Ok(FheValue::Uint32(compute_op(l, r, operation)))
+            }
+            (FheValue::Uint64(l), FheValue::Uint64(r)) => {
+                Ok(FheValue::Uint64(compute_op(l, r, operation)))
+            }
+            (FheValue::Bool(l), FheValue::Bool(r)) => {
+                check_bool_op(operation)?;
+                Ok(FheValue::Bool(compute_op(l, r, operation)))
+            }
+            (l, r) => Err(RustError::type_mismatch(format!(
+                "cannot perform {:?} on {:?} and {:?}",
+                operation,
+                l.uint_type(),
+                r.uint_type()
+            ))),
+        }
+    }
+
+    /// Performs `operation` on this value and a plaintext, truncated to the width of the value.
+    pub(crate) fn scalar_op(&self, rhs: u64, operation: Op) -> Result<FheValue, RustError> {
+        GlobalKeys::refresh_server_key_for_thread();
+
+        let result = match self {
+            FheValue::Uint8(l) => {
+                let rhs = scalar_operand(rhs, operation, u8::BITS) as u8;
+                FheValue::Uint8(compute_scalar_op(l, rhs, operation))
+            }
+            FheValue::Uint16(l) => {
+                let rhs = scalar_operand(rhs, operation, u16::BITS) as u16;
+                FheValue::Uint16(compute_scalar_op(l, rhs, operation))
+            }
+            FheValue::Uint32(l) => {
+                let rhs = scalar_operand(rhs, operation, u32::BITS) as u32;
+                FheValue::Uint32(compute_scalar_op(l, rhs, operation))
+            }
+            FheValue::Uint64(l) => {
+                let rhs = scalar_operand(rhs, operation, u64::BITS);
+                FheValue::Uint64(compute_scalar_op(l, rhs, operation))
+            }
+            FheValue::Bool(l) => {
+                check_bool_op(operation)?;
+                FheValue::Bool(compute_op(l, &boolean::trivial(rhs != 0)?, operation))
+            }
+        };
+
+        Ok(result)
+    }
+
+    pub(crate) fn unary_op(&self, operation: UnaryOp) -> Result<FheValue, RustError> {
+        GlobalKeys::refresh_server_key_for_thread();
+
+        let result = match self {
+            FheValue::Uint8(v) => FheValue::Uint8(compute_unary_op(v, operation)),
+            FheValue::Uint16(v) => FheValue::Uint16(compute_unary_op(v, operation)),
+            FheValue::Uint32(v) => FheValue::Uint32(compute_unary_op(v, operation)),
+            FheValue::Uint64(v) => FheValue::Uint64(compute_unary_op(v, operation)),
+            FheValue::Bool(v) => match operation {
+                UnaryOp::Not => FheValue::Bool(compute_bool_not(v)?),
+            },
+        };
+
+        Ok(result)
+    }
+
+    /// Trivially encrypts `msg`, truncated to the width of `uint_type`.
+    pub(crate) fn trivial(msg: u64, uint_type: FheUintType) -> Result<FheValue, RustError> {
+        GlobalKeys::refresh_server_key_for_thread();
+
+        let value = match uint_type {
+            FheUintType::Uint8 => FheValue::Uint8(FheUint8::encrypt_trivial(msg as u8)),
+            FheUintType::Uint16 => FheValue::Uint16(FheUint16::encrypt_trivial(msg as u16)),
+            FheUintType::Uint32 => FheValue::Uint32(FheUint32::encrypt_trivial(msg as u32)),
+            FheUintType::Uint64 => FheValue::Uint64(FheUint64::encrypt_trivial(msg)),
+            FheUintType::Bool => FheValue::Bool(boolean::trivial(msg != 0)?),
+        };
+
+        Ok(value)
+    }
+
+    /// Casts the value to `to_type`. Casting to `Bool` yields 1 for any non-zero value.
+    ///
+    /// tfhe's casts take their input by value, so unlike the other operations this copies the
+    /// value first.
+    pub(crate) fn cast(&self, to_type: FheUintType) -> Result<FheValue, RustError> {
+        GlobalKeys::refresh_server_key_for_thread();
+
+        let value = match (self, to_type) {
+            (FheValue::Uint8(v), FheUintType::Uint8) => FheValue::Uint8(v.clone()),
+            (FheValue::Uint8(v), FheUintType::Uint16) => {
+                FheValue::Uint16(FheUint16::cast_from(v.clone()))
+            }
+            (FheValue::Uint8(v), FheUintType::Uint32) => {
+                FheValue::Uint32(FheUint32::cast_from(v.clone()))
+            }
+            (FheValue::Uint8(v), FheUintType::Uint64) => {
+                FheValue::Uint64(FheUint64::cast_from(v.clone()))
+            }
+            (FheValue::Uint8(v), FheUintType::Bool) => {
+                FheValue::Bool(boolean::is_non_zero(v, FheUint8::encrypt_trivial(0u8))?)
+            }
+
+            (FheValue::Uint16(v), FheUintType::Uint8) => {
+                FheValue::Uint8(FheUint8::cast_from(v.clone()))
+            }
+            (FheValue::Uint16(v), FheUintType::Uint16) => FheValue::Uint16(v.clone()),
+            (FheValue::Uint16(v), FheUintType::Uint32) => {
+                FheValue::Uint32(FheUint32::cast_from(v.clone()))
+            }
+            (FheValue::Uint16(v), FheUintType::Uint64) => {
+                FheValue::Uint64(FheUint64::cast_from(v.clone()))
+            }
+            (FheValue::Uint16(v), FheUintType::Bool) => {
+                FheValue::Bool(boolean::is_non_zero(v, FheUint16::encrypt_trivial(0u16))?)
+            }
+
+            (FheValue::Uint32(v), FheUintType::Uint8) => {
+                FheValue::Uint8(FheUint8::cast_from(v.clone()))
+            }
+            (FheValue::Uint32(v), FheUintType::Uint16) => {
+                FheValue::Uint16(FheUint16::cast_from(v.clone()))
+            }
+            (FheValue::Uint32(v), FheUintType::Uint32) => FheValue::Uint32(v.clone()),
+            (FheValue::Uint32(v), FheUintType::Uint64) => {
+                FheValue::Uint64(FheUint64::cast_from(v.clone()))
+            }
+            (FheValue::Uint32(v), FheUintType::Bool) => {
+                FheValue::Bool(boolean::is_non_zero(v, FheUint32::encrypt_trivial(0u32))?)
+            }
+
+            (FheValue::Uint64(v), FheUintType::Uint8) => {
+                FheValue::Uint8(FheUint8::cast_from(v.clone()))
+            }
+            (FheValue::Uint64(v), FheUintType::Uint16) => {
+                FheValue::Uint16(FheUint16::cast_from(v.clone()))
+            }
+            (FheValue::Uint64(v), FheUintType::Uint32) => {
+                FheValue::Uint32(FheUint32::cast_from(v.clone()))
+            }
+            (FheValue::Uint64(v), FheUintType::Uint64) => FheValue::Uint64(v.clone()),
+            (FheValue::Uint64(v), FheUintType::Bool) => {
+                FheValue::Bool(boolean::is_non_zero(v, FheUint64::encrypt_trivial(0u64))?)
+            }
+
+            // widening a bool pads its single block with trivial zeros
+            (FheValue::Bool(v), FheUintType::Uint8) => {
+                FheValue::Uint8(boolean::widen(v, &FheUint8::encrypt_trivial(0u8))?)
+            }
+            (FheValue::Bool(v), FheUintType::Uint16) => {
+                FheValue::Uint16(boolean::widen(v, &FheUint16::encrypt_trivial(0u16))?)
+            }
+            (FheValue::Bool(v), FheUintType::Uint32) => {
+                FheValue::Uint32(boolean::widen(v, &FheUint32::encrypt_trivial(0u32))?)
+            }
+            (FheValue::Bool(v), FheUintType::Uint64) => {
+                FheValue::Uint64(boolean::widen(v, &FheUint64::encrypt_trivial(0u64))?)
+            }
+            (FheValue::Bool(v), FheUintType::Bool) => FheValue::Bool(v.clone()),
+        };
+
+        Ok(value)
+    }
+
+    /// Selects `if_true` when `control` is non-zero, `if_false` otherwise.
+    ///
+    /// Both options must be of the same type, and the control a `Bool` or of that type too (see
+    /// `check_cmux_control`). It is expected to hold either 0 or 1.
+    pub(crate) fn cmux(
+        control: &FheValue,
+        if_true: &FheValue,
+        if_false: &FheValue,
+    ) -> Result<FheValue, RustError> {
+        let uint_type = if_true.uint_type();
+        check_cmux_control(control.uint_type(), uint_type)?;
+        let widened;
+        let control = match (control.uint_type(), uint_type) {
+            (from, to) if from == to => control,
+            // a bool control is widened, which never truncates it
+            _ => {
+                widened = control.cast(uint_type)?;
+                &widened
+            }
+        };
+
+        GlobalKeys::refresh_server_key_for_thread();
+
+        match (control, if_true, if_false) {
+            (FheValue::Uint8(c), FheValue::Uint8(t), FheValue::Uint8(f)) => Ok(FheValue::Uint8(
+                select(&FheUint8::encrypt_trivial(0u8), c, t, f),
+            )),
+            (FheValue::Uint16(c), FheValue::Uint16(t), FheValue::Uint16(f)) => Ok(
+                FheValue::Uint16(select(&FheUint16::encrypt_trivial(0u16), c, t, f)),
+            ),
+            (FheValue::Uint32(c), FheValue::Uint32(t), FheValue::Uint32(f)) => Ok(
+                FheValue::Uint32(select(&FheUint32::encrypt_trivial(0u32), c, t, f)),
+            ),
+            (FheValue::Uint64(c), FheValue::Uint64(t), FheValue::Uint64(f)) => Ok(
+                FheValue::Uint64(select(&FheUint64::encrypt_trivial(0u64), c, t, f)),
+            ),
+            (FheValue::Bool(c), FheValue::Bool(t), FheValue::Bool(f)) => {
+                Ok(FheValue::Bool(select(&boolean::trivial(false)?, c, t, f)))
+            }
+            (_, t, f) => Err(RustError::type_mismatch(format!(
+                "cannot select between {:?} and {:?}",
+                t.uint_type(),
+                f.uint_type()
+            ))),
+        }
+    }
+}
+
+/// The mask based select used by `cmux`:
+///     let mask = 0 - control; // either 0 or 0xFFFF....
+///     return (if_true & mask) | (if_false & !mask);
+fn select<T>(zero: &T, control: &T, if_true: &T, if_false: &T) -> T
+where
+    T: FheInteger,
+    for<'a> &'a T: FheIntegerRef<T>,
+{
+    let mask = compute_op(zero, control, Op::Sub);
+    let inv_mask = compute_unary_op(&mask, UnaryOp::Not);
+
+    let left = compute_op(&mask, if_true, Op::BitAnd);
+    let right = compute_op(&inv_mask, if_false, Op::BitAnd);
+
+    compute_op(&left, &right, Op::BitOr)
+}
@@ -0,0 +1,14 @@
+//go:build mock_backend
+
+package tfhe
+
+import "github.com/fhenixprotocol/go-tfhe/internal/api"
+
+// SetMockBackend swaps the FHE backend for a plaintext mock with the same semantics, so contract
+// logic can be tested without keys and in a fraction of the time. Nothing is encrypted while the
+// mock is enabled, so it must never be used outside of tests.
+//
+// Only built with the mock_backend tag, against a library built with the mock-backend feature.
+func SetMockBackend(enabled bool) {
+	api.SetMockBackend(enabled)
+}
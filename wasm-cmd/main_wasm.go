@@ -32,7 +32,7 @@ func Add() error {
 		return err
 	}
 
-	_, err = ct.Add(ct2)
+	_, err = ct.Add(ct2, tfhelib.UnlimitedGas)
 	if err != nil {
 		return err
 	}